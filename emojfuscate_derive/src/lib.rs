@@ -4,14 +4,37 @@ use std::iter::once;
 use syn::spanned::Spanned;
 use syn::{parse_macro_input, Data, DeriveInput, Fields, GenericParam, Ident};
 
-#[proc_macro_derive(Emojfuscate)]
+/// Encode a struct or an enum as its fields, with a tag for the variant of an enum before them.
+///
+/// Fields of recursive types are encoded with boxed iterators that are nested as deeply as the
+/// value is, so encoding values that are nested more than about a thousand times deep overflows
+/// the 2 MiB stack of a thread in a debug build, and more than a few thousand times deep in a
+/// release build. Encoding doesn't check `DEFAULT_NESTING_LIMIT`, only decoding does. Types that
+/// are recursive through another type, like an `Expr` with a `Box<Statement>` that has an `Expr`,
+/// aren't recognized as recursive and can't be encoded.
+#[proc_macro_derive(Emojfuscate, attributes(emojfuscate))]
 pub fn derive_emojfuscate(raw_input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(raw_input as DeriveInput);
+    let attributes = match ContainerAttributes::parse(&input) {
+        Ok(attributes) => attributes,
+        Err(err) => return proc_macro::TokenStream::from(err.to_compile_error()),
+    };
     let name = input.ident;
+    // the bounds on the fields name the types of their iterators, so they are always needed
+    let where_start = where_clause_start(&input.generics, &attributes.bound.emojfuscate);
+    let recursion = is_recursive(&input.data, &name).then_some(&name);
+
+    // the values of a recursive type end up in the boxed iterators of its recursive fields, which
+    // are 'static
+    let type_params = input.generics.type_params().map(|p| &p.ident);
+    let where_start = match recursion {
+        Some(_) => quote! {#where_start #(#type_params: 'static,)*},
+        None => where_start,
+    };
 
     let expanded = match input.data {
         Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
+            Fields::Named(_) => {
                 /*
                 // For a struct with named fields, e.g.
 
@@ -40,68 +63,41 @@ pub fn derive_emojfuscate(raw_input: proc_macro::TokenStream) -> proc_macro::Tok
                             .chain_emoji_bytes(self.name.emojfuscate_stream());
                     }
                 }
-                */
-
-                let alphabetically_sorted_fields = {
-                    let mut named_fields_to_sort = fields.named.iter().collect::<Vec<_>>();
-                    named_fields_to_sort.sort_by_key(|f| &f.ident);
-
-                    named_fields_to_sort
-                };
-
-                let iterator_names =
-                    alphabetically_sorted_fields
-                        .iter()
-                        .enumerate()
-                        .map(|(i, f)| {
-                            let ident = Ident::new(&format!("I{}", i), Span::call_site());
-                            quote_spanned! {f.span()=>#ident}
-                        });
 
-                let chained_fields = alphabetically_sorted_fields
-                    .iter()
-                    .map(|f| {
-                        let name = &f.ident;
-                        quote_spanned! {f.span()=>self.#name.emojfuscate_stream()}
-                    })
-                    .reduce(|prev, f| {
-                        quote_spanned! {f.span()=>#prev.chain_emoji_bytes(#f)}
-                    });
-
-                let field_types = alphabetically_sorted_fields
-                    .iter()
-                    .zip(iterator_names.clone())
-                    .map(|(f, iterator_type_name)| {
-                        let field_type = &f.ty;
-
-                        quote_spanned! {f.span()=>
-                            #field_type: emojfuscate::Emojfuscate<#iterator_type_name>,
-                            #iterator_type_name: Iterator<Item = emojfuscate::ByteInSequence>,
-                        }
-                    });
+                // With #[emojfuscate(evolvable)] each field is wrapped with
+                // `.evolvable_field("age")` and has the type `emojfuscate::EvolvableField<I1>`,
+                // and all of the fields are put between the start and stop emoji.
+                //
+                // Skipped fields are left out and a field with #[emojfuscate(with = module)] is
+                // encoded with `module::emojfuscate_stream(self.age).boxed()`, which has the type
+                // `emojfuscate::BoxedEmojiBytes<'static>` instead of I1.
+                //
+                // Fields that have the type itself in their type, like `Option<Box<Person<A>>>`,
+                // are encoded with `self.friend.emojfuscate_stream().boxed()` as well, since the
+                // type of their iterator would have the type of the iterator of the whole struct
+                // in it. The boxed iterators are 'static, so A, I1, I2 and I3 have to be too.
+                */
 
-                let iterator_chain = iterator_names.clone().reduce(|chain, element| {
-                    quote! {std::iter::Chain<#chain, #element>}
-                });
+                let (iterator_names, field_types, iterator_chain, chained_fields) =
+                    emojfuscate_fields(
+                        &encoded_fields(&data.fields),
+                        attributes.evolvable,
+                        "I",
+                        recursion,
+                        |_, f| {
+                            let name = &f.ident;
+                            quote_spanned! {f.span()=>self.#name}
+                        },
+                    );
 
-                let generics = input
-                    .generics
-                    .params
-                    .iter()
-                    .filter_map(|p| match p {
-                        GenericParam::Type(type_param) => {
-                            let ident = &type_param.ident;
-                            Some(quote! {#ident})
-                        }
-                        _ => None,
-                    })
-                    .chain(iterator_names);
+                let generics = impl_generics(&input.generics, iterator_names);
 
                 let (_, ty_generics, _) = input.generics.split_for_impl();
 
                 quote! {
                     impl<#(#generics),*> emojfuscate::Emojfuscate<#iterator_chain> for #name #ty_generics
                     where
+                        #where_start
                         #(#field_types)*
                     {
                         fn emojfuscate_stream(self) -> emojfuscate::EncodeBytesAsEmoji<#iterator_chain> {
@@ -111,52 +107,29 @@ pub fn derive_emojfuscate(raw_input: proc_macro::TokenStream) -> proc_macro::Tok
                 }
             }
             Fields::Unnamed(ref fields) => {
-                let iterator_names = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let ident = Ident::new(&format!("I{}", i), Span::call_site());
-                    quote_spanned! {f.span()=>#ident}
-                });
-
+                // skipped fields are matched with `_`
                 let fields_with_names = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let name = Ident::new(&format!("field{}", i), Span::call_site());
-                    quote_spanned! {f.span()=>#name}
+                    if FieldAttributes::of(f).skip {
+                        quote_spanned! {f.span()=>_}
+                    } else {
+                        let name = Ident::new(&format!("field{}", i), Span::call_site());
+                        quote_spanned! {f.span()=>#name}
+                    }
                 });
 
-                let chained_fields = fields_with_names
-                    .clone()
-                    .map(|field_name| {
-                        quote_spanned! {field_name.span()=>#field_name.emojfuscate_stream()}
-                    })
-                    .reduce(|prev, f| {
-                        quote_spanned! {f.span()=>#prev.chain_emoji_bytes(#f)}
-                    });
-
-                let field_types = fields.unnamed.iter().zip(iterator_names.clone()).map(
-                    |(f, iterator_type_name)| {
-                        let field_type = &f.ty;
-
-                        quote_spanned! {f.span()=>
-                            #field_type: emojfuscate::Emojfuscate<#iterator_type_name>,
-                            #iterator_type_name: Iterator<Item = emojfuscate::ByteInSequence>,
-                        }
-                    },
-                );
-
-                let iterator_chain = iterator_names.clone().reduce(|chain, element| {
-                    quote! {std::iter::Chain<#chain, #element>}
-                });
+                let (iterator_names, field_types, iterator_chain, chained_fields) =
+                    emojfuscate_fields(
+                        &encoded_fields(&data.fields),
+                        false,
+                        "I",
+                        recursion,
+                        |i, f| {
+                            let name = Ident::new(&format!("field{}", i), Span::call_site());
+                            quote_spanned! {f.span()=>#name}
+                        },
+                    );
 
-                let generics = input
-                    .generics
-                    .params
-                    .iter()
-                    .filter_map(|p| match p {
-                        GenericParam::Type(type_param) => {
-                            let ident = &type_param.ident;
-                            Some(quote! {#ident})
-                        }
-                        _ => None,
-                    })
-                    .chain(iterator_names);
+                let generics = impl_generics(&input.generics, iterator_names);
 
                 let (_, ty_generics, _) = input.generics.split_for_impl();
 
@@ -189,6 +162,7 @@ pub fn derive_emojfuscate(raw_input: proc_macro::TokenStream) -> proc_macro::Tok
                 quote! {
                     impl<#(#generics),*> emojfuscate::Emojfuscate<#iterator_chain> for #name #ty_generics
                     where
+                        #where_start
                         #(#field_types)*
                     {
                         fn emojfuscate_stream(self) -> emojfuscate::EncodeBytesAsEmoji<#iterator_chain> {
@@ -200,24 +174,14 @@ pub fn derive_emojfuscate(raw_input: proc_macro::TokenStream) -> proc_macro::Tok
                 }
             }
             Fields::Unit => {
-                let generics = input
-                    .generics
-                    .params
-                    .iter()
-                    .filter_map(|p| match p {
-                        GenericParam::Type(type_param) => {
-                            let ident = &type_param.ident;
-                            Some(quote! {#ident})
-                        }
-                        _ => None,
-                    })
-                    .chain(once(quote! {I}));
+                let generics = impl_generics(&input.generics, once(quote! {I}));
 
                 let (_, ty_generics, _) = input.generics.split_for_impl();
 
                 quote! {
                     impl<#(#generics),*> emojfuscate::Emojfuscate<I> for #name #ty_generics
                     where
+                        #where_start
                         (): emojfuscate::Emojfuscate<I>,
                         I: Iterator<Item = emojfuscate::ByteInSequence>,
                     {
@@ -240,250 +204,150 @@ pub fn derive_emojfuscate(raw_input: proc_macro::TokenStream) -> proc_macro::Tok
 
             the generated code should look something like this:
 
-            impl<IA, IB> emojfuscate::Emojfuscate<Chain<Chain<Once<emojfuscate::ByteInSequence>, IA>, IB>> for Animal
+            impl<I0_0, I0_1, I1_0> emojfuscate::Emojfuscate<
+                Chain<
+                    Chain<emojfuscate::VariantTag, emojfuscate::VariantFields<Chain<I0_0, I0_1>>>,
+                    emojfuscate::VariantFields<I1_0>,
+                >,
+            > for Animal
             where
-                Option<(bool, String)>: emojfuscate::Emojfuscate<IA>,
-                Option<u32>: emojfuscate::Emojfuscate<IB>,
-                IA: Iterator<Item = emojfuscate::ByteInSequence>,
-                IB: Iterator<Item = emojfuscate::ByteInSequence>,
+                bool: emojfuscate::Emojfuscate<I0_0>,
+                String: emojfuscate::Emojfuscate<I0_1>,
+                u32: emojfuscate::Emojfuscate<I1_0>,
+                I0_0: Iterator<Item = emojfuscate::ByteInSequence>,
+                I0_1: Iterator<Item = emojfuscate::ByteInSequence>,
+                I1_0: Iterator<Item = emojfuscate::ByteInSequence>,
             {
-                fn emojfuscate_stream(self) -> emojfuscate::EncodeBytesAsEmoji<Chain<Chain<Once<emojfuscate::ByteInSequence>, IA>, IB>> {
+                fn emojfuscate_stream(self) -> emojfuscate::EncodeBytesAsEmoji<...> {
                     match self {
-                        Animal::Cat(b,s) => {
-                            0u8
-                                .emojfuscate_stream()
-                                .chain_emoji_bytes(Some((b,s)).emojfuscate_stream())
-                                .chain_emoji_bytes(None.emojfuscate_stream())
+                        Animal::Cat{likes_cuddles, name, ..} => {
+                            emojfuscate::EncodeBytesAsEmoji::variant_tag(0)
+                                .chain_variant(Some(likes_cuddles.emojfuscate_stream().chain_emoji_bytes(name.emojfuscate_stream())))
+                                .chain_variant(None)
                         },
-                        Animal::Dog(i) => {
-                            1u8
-                                .emojfuscate_stream()
-                                .chain_emoji_bytes(None.emojfuscate_stream())
-                                .chain_emoji_bytes(Some(i).emojfuscate_stream())
+                        Animal::Dog(x0) => {
+                            emojfuscate::EncodeBytesAsEmoji::variant_tag(1)
+                                .chain_variant(None)
+                                .chain_variant(Some(x0.emojfuscate_stream()))
                         },
                         Animal::Lizard => {
-                            2u8
-                                .emojfuscate_stream()
-                                .chain_emoji_bytes(None.emojfuscate_stream())
-                                .chain_emoji_bytes(None.emojfuscate_stream())
+                            emojfuscate::EncodeBytesAsEmoji::variant_tag(2)
+                                .chain_variant(None)
+                                .chain_variant(None)
                         }
                     }
                 }
             }
+
+            Every variant has a place in the type so that all of them have the same type, but only
+            the fields of the chosen variant are in the emoji. Variants without any fields in the
+            emoji don't need a place at all. The tags are the ones from `variant_tags`.
             */
 
-            let mut trait_constraints = data
+            // the fields of every variant as they are in the emoji
+            let variant_fields = data
                 .variants
                 .iter()
-                .enumerate()
-                .filter_map(|(variant_index, variant)| match variant.fields {
-                    Fields::Named(ref fields) => {
-                        let alphabetically_sorted_fields = {
-                            let mut named_fields_to_sort = fields.named.iter().collect::<Vec<_>>();
-                            named_fields_to_sort.sort_by_key(|f| &f.ident);
-
-                            named_fields_to_sort
-                        };
-
-                        let mut field_types = alphabetically_sorted_fields.iter().map(|f| &f.ty);
-                        let iterator_name =
-                            Ident::new(&format!("I{}", variant_index), Span::call_site());
-
-                        Some(if field_types.len() == 1 {
-                            let only_field = field_types.next().unwrap();
+                .map(|variant| encoded_fields(&variant.fields))
+                .collect::<Vec<_>>();
+
+            let mut iterator_names = Vec::new();
+            let mut trait_constraints = Vec::new();
+            let mut variant_types = Vec::new();
+            let mut variant_streams = Vec::new();
+
+            for (variant_index, fields) in variant_fields.iter().enumerate() {
+                if fields.is_empty() {
+                    variant_streams.push(None);
+                    continue;
+                }
 
-                            quote! {
-                                Option<#only_field>: emojfuscate::Emojfuscate<#iterator_name>,
-                                #iterator_name: Iterator<Item = emojfuscate::ByteInSequence>
-                            }
-                        } else {
-                            quote! {
-                                Option<(#(#field_types),*)>: emojfuscate::Emojfuscate<#iterator_name>,
-                                #iterator_name: Iterator<Item = emojfuscate::ByteInSequence>
-                            }
-                        })
-                    }
-                    Fields::Unnamed(ref fields) => {
-                        let mut field_types = fields.unnamed.iter().map(|f| &f.ty);
-                        let iterator_name =
-                            Ident::new(&format!("I{}", variant_index), Span::call_site());
+                let (names, field_types, iterator_chain, chained_fields) = emojfuscate_fields(
+                    fields,
+                    false,
+                    &format!("I{}_", variant_index),
+                    recursion,
+                    |i, f| {
+                        let binding = variant_binding(i, f);
+                        quote! {#binding}
+                    },
+                );
 
-                        Some(if field_types.len() == 1 {
-                            let only_field = field_types.next().unwrap();
+                iterator_names.extend(names);
+                trait_constraints.extend(field_types);
+                variant_types.push(quote! {emojfuscate::VariantFields<#iterator_chain>});
+                variant_streams.push(Some(chained_fields));
+            }
 
-                            quote! {
-                                Option<#only_field>: emojfuscate::Emojfuscate<#iterator_name>,
-                                #iterator_name: Iterator<Item = emojfuscate::ByteInSequence>
-                            }
-                        } else {
-                            quote! {
-                                Option<(#(#field_types),*)>: emojfuscate::Emojfuscate<#iterator_name>,
-                                #iterator_name: Iterator<Item = emojfuscate::ByteInSequence>
-                            }
-                        })
-                    }
-                    Fields::Unit => None,
-                })
-                .peekable();
+            let function_body = data
+                .variants
+                .iter()
+                .enumerate()
+                .map(|(variant_index, variant)| {
+                    let tag = attributes.tags[variant_index];
+                    let variant_name = &variant.ident;
 
-            let function_body =
-                data
-                    .variants
-                    .iter()
-                    .enumerate()
-                    .map(|(variant_index_usize, variant)| {
-                        let variant_index = variant_index_usize as u8;
+                    // skipped fields aren't bound
+                    let pattern = match variant.fields {
+                        Fields::Named(_) => {
+                            let field_names = variant_fields[variant_index]
+                                .iter()
+                                .map(|(i, f, _)| variant_binding(*i, f));
 
-                        match variant.fields {
-                            Fields::Named(ref fields) => {
-                                let alphabetically_sorted_fields = {
-                                    let mut named_fields_to_sort = fields.named.iter().collect::<Vec<_>>();
-                                    named_fields_to_sort.sort_by_key(|f| &f.ident);
+                            quote! {#name::#variant_name{#(#field_names,)* ..}}
+                        }
+                        Fields::Unnamed(ref fields) => {
+                            let field_names = fields.unnamed.iter().enumerate().map(|(i, f)| {
+                                if FieldAttributes::of(f).skip {
+                                    quote! {_}
+                                } else {
+                                    let binding = variant_binding(i, f);
+                                    quote! {#binding}
+                                }
+                            });
 
-                                    named_fields_to_sort
-                                };
+                            quote! {#name::#variant_name(#(#field_names),*)}
+                        }
+                        Fields::Unit => quote! {#name::#variant_name},
+                    };
 
-                                let variant_name = &variant.ident;
-
-                                let field_names = alphabetically_sorted_fields.iter()
-                                        .map(|f| {
-                                            let field_name = &f.ident;
-                                            quote_spanned! {f.span()=>#field_name}
-                                        });
-
-                                let fields_to_emojfuscate =
-                                    data.variants.iter().enumerate()
-                                    .filter(|(_, v)| match v.fields {
-                                        Fields::Unit => false,
-                                        _ => true,
-                                    }).map(|(i, _)| {
-                                    if i as u8 == variant_index {
-                                        let field_names2 = alphabetically_sorted_fields.iter()
-                                                .map(|f| {
-                                                    let field_name = &f.ident;
-                                                    quote_spanned! {f.span()=>#field_name}
-                                                });
-
-                                        quote! {
-                                            .chain_emoji_bytes(Some((#(#field_names2),*)).emojfuscate_stream())
-                                        }
-                                    } else {
-                                        quote! {
-                                            .chain_emoji_bytes(None.emojfuscate_stream())
-                                        }
-                                    }
-                                });
+                    let fields_to_emojfuscate =
+                        variant_streams
+                            .iter()
+                            .enumerate()
+                            .filter_map(|(i, stream)| {
+                                let stream = stream.as_ref()?;
 
-                                quote! {
-                                    #name::#variant_name{#(#field_names),*} => {
-                                        #variant_index.emojfuscate_stream()
-                                        #(#fields_to_emojfuscate)*
-                                    }
-                                }
-                            }
-                            Fields::Unnamed(ref fields) => {
-                                let variant_name = &variant.ident;
-                                let field_names = fields.unnamed.iter();
-
-                                let fields_to_emojfuscate =
-                                    data.variants.iter().enumerate()
-                                    .filter(|(_, v)| match v.fields {
-                                        Fields::Unit => false,
-                                        _ => true,
-                                    }).map(|(i, _)| {
-                                    if i as u8 == variant_index {
-                                        let field_names2 = fields.unnamed.iter();
-
-                                        quote! {
-                                            .chain_emoji_bytes(Some((#(#field_names2),*)).emojfuscate_stream())
-                                        }
-                                    } else {
-                                        quote! {
-                                            .chain_emoji_bytes(None.emojfuscate_stream())
-                                        }
-                                    }
-                                });
+                                Some(if i == variant_index {
+                                    quote! {.chain_variant(Some(#stream))}
+                                } else {
+                                    quote! {.chain_variant(None)}
+                                })
+                            });
 
-                                quote! {
-                                    #name::#variant_name(#(#field_names),*) => {
-                                        #variant_index.emojfuscate_stream()
-                                        #(#fields_to_emojfuscate)*
-                                    }
-                                }
-                            },
-                            Fields::Unit => {
-                                let fields_to_emojfuscate =
-                                    data.variants.iter().enumerate()
-                                    .filter(|(_, v)| match v.fields {
-                                        Fields::Unit => false,
-                                        _ => true,
-                                    }).map(|_| {
-                                        quote! {
-                                            .chain_emoji_bytes(None.emojfuscate_stream())
-                                        }
-                                });
-
-                                let variant_name = &variant.ident;
-
-                                quote! {
-                                    #name::#variant_name => {
-                                        #variant_index.emojfuscate_stream()
-                                        #(#fields_to_emojfuscate)*
-                                    }
-                                }
-                            }
+                    quote! {
+                        #pattern => {
+                            emojfuscate::EncodeBytesAsEmoji::variant_tag(#tag)
+                            #(#fields_to_emojfuscate)*
                         }
-                    });
-
-            let iterator_names = data
-                .variants
-                .iter()
-                .enumerate()
-                .filter(|(_, v)| match v.fields {
-                    Fields::Unit => false,
-                    _ => true,
-                })
-                .map(|(i, _)| {
-                    let ident = Ident::new(&format!("I{}", i), Span::call_site());
-                    quote! {#ident}
+                    }
                 });
 
-            let iterator_chain_type = once(quote! {std::iter::Once<emojfuscate::ByteInSequence>})
-                .chain(iterator_names.clone())
+            let iterator_chain_type = once(quote! {emojfuscate::VariantTag})
+                .chain(variant_types)
                 .reduce(|chain, element| {
-                    quote! {std::iter::Chain<#chain, #element>}
+                    quote! {core::iter::Chain<#chain, #element>}
                 });
 
-            let where_clause = match trait_constraints.peek() {
-                Some(_) => {
-                    quote! {
-                        where
-                            #(#trait_constraints),*
-                    }
-                }
-                None => {
-                    quote! {}
-                }
-            };
-
-            let generics = input
-                .generics
-                .params
-                .iter()
-                .filter_map(|p| match p {
-                    GenericParam::Type(type_param) => {
-                        let ident = &type_param.ident;
-                        Some(quote! {#ident})
-                    }
-                    _ => None,
-                })
-                .chain(iterator_names);
+            let generics = impl_generics(&input.generics, iterator_names);
 
             let (_, ty_generics, _) = input.generics.split_for_impl();
 
             quote! {
                 impl<#(#generics),*> emojfuscate::Emojfuscate<#iterator_chain_type> for #name #ty_generics
-                #where_clause
+                where
+                    #where_start
+                    #(#trait_constraints)*
                 {
                     fn emojfuscate_stream(self) -> emojfuscate::EncodeBytesAsEmoji<#iterator_chain_type> {
                         match self {
@@ -499,29 +363,136 @@ pub fn derive_emojfuscate(raw_input: proc_macro::TokenStream) -> proc_macro::Tok
     return proc_macro::TokenStream::from(expanded);
 }
 
-#[proc_macro_derive(ConstructFromEmoji)]
+/// Decode a struct or an enum from the fields that the derived `Emojfuscate` encodes.
+///
+/// Fields of recursive types are constructed with `DecodeEmojiToBytes::construct_nested`, so
+/// values that are nested deeper than the limit of the decoder, `DEFAULT_NESTING_LIMIT` unless it's
+/// changed with `with_nesting_limit`, are an error instead of a stack overflow.
+#[proc_macro_derive(ConstructFromEmoji, attributes(emojfuscate))]
 pub fn derive_construct_from_emoji(raw_input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(raw_input as DeriveInput);
+    let attributes = match ContainerAttributes::parse(&input) {
+        Ok(attributes) => attributes,
+        Err(err) => return proc_macro::TokenStream::from(err.to_compile_error()),
+    };
     let name = input.ident;
+    let name_string = name.to_string();
+    let recursion = is_recursive(&input.data, &name).then_some(&name);
 
     let (_, ty_generics, _) = input.generics.split_for_impl();
 
     let demojfuscated_fields = match input.data {
         Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let alphabetically_sorted_fields = {
-                    let mut named_fields_to_sort = fields.named.iter().collect::<Vec<_>>();
-                    named_fields_to_sort.sort_by_key(|f| &f.ident);
+            // The fields can come in any order and there can be fields that this version of the
+            // struct doesn't know about, so they are collected in Options until the end
+            Fields::Named(ref fields) if attributes.evolvable => {
+                let encoded_fields = encoded_fields(&data.fields);
 
-                    named_fields_to_sort
-                };
+                let declare_fields = encoded_fields.iter().map(|(_, f, _)| {
+                    let field_name = &f.ident;
+                    let field_type = &f.ty;
+                    quote_spanned! {f.span()=>
+                        let mut #field_name: Option<#field_type> = None;
+                    }
+                });
 
-                let declare_fields = alphabetically_sorted_fields.iter().map(|f| {
+                let construct_fields = encoded_fields.iter().map(|(_, f, attributes)| {
                     let field_name = &f.ident;
+                    let field_name_string = field_name.as_ref().unwrap().to_string();
+                    let emoji_name = emoji_name(f, attributes);
                     let field_type = &f.ty;
+
+                    let construct = match attributes.with {
+                        Some(ref module) => {
+                            quote! {byte_stream.construct_field_with(#module::construct_from_emoji)}
+                        }
+                        None if recursion.is_some_and(|name| is_recursive_field(f, name)) => {
+                            let construct = construct_field(f, attributes, recursion);
+                            quote! {byte_stream.construct_field_with(|byte_stream| #construct)}
+                        }
+                        None => quote! {byte_stream.construct_field::<#field_type>()},
+                    };
+
                     quote_spanned! {f.span()=>
-                        let #field_name = match #field_type::construct_from_emoji(byte_stream) {
-                            Err(err) => return Err(err),
+                        Some(#emoji_name) => {
+                            #field_name = Some(match #construct {
+                                Err(err) => return Err(err.within(#name_string, emojfuscate::PathSegment::Field(#field_name_string))),
+                                Ok(result) => result,
+                            });
+                        }
+                    }
+                });
+
+                let field_constructors = fields.named.iter().map(|f| {
+                    let field_name = &f.ident;
+                    let attributes = FieldAttributes::of(f);
+                    let emoji_name = emoji_name(f, &attributes);
+                    let default_value = attributes.default_value();
+
+                    if attributes.skip {
+                        return quote_spanned! {f.span()=>
+                            #field_name: #default_value,
+                        };
+                    }
+
+                    let missing = if attributes.default.is_some() {
+                        default_value
+                    } else {
+                        quote! {
+                            return Err(byte_stream
+                                .error_here(emojfuscate::FromEmojiErrorKind::MissingField(#emoji_name))
+                                .in_type(#name_string))
+                        }
+                    };
+
+                    quote_spanned! {f.span()=>
+                        #field_name: match #field_name {
+                            Some(result) => result,
+                            None => #missing,
+                        },
+                    }
+                });
+
+                quote! {
+                    #(#declare_fields)*
+
+                    if let Err(err) = byte_stream.expect_sequence_start() {
+                        return Err(err.in_type(#name_string));
+                    }
+
+                    loop {
+                        let next_field_name = match byte_stream.next_field_name() {
+                            Err(err) => return Err(err.in_type(#name_string)),
+                            Ok(next_field_name) => next_field_name,
+                        };
+
+                        match next_field_name.as_deref() {
+                            None => break,
+                            #(#construct_fields)*
+                            // a field that has been removed or that was added in a later version
+                            Some(_) => {
+                                if let Err(err) = byte_stream.skip_value() {
+                                    return Err(err.in_type(#name_string));
+                                }
+                            }
+                        }
+                    }
+
+                    return Ok(
+                        #name {
+                            #(#field_constructors)*
+                        }
+                    );
+                }
+            }
+            Fields::Named(ref fields) => {
+                let declare_fields = encoded_fields(&data.fields).into_iter().map(|(_, f, attributes)| {
+                    let field_name = &f.ident;
+                    let field_name_string = field_name.as_ref().unwrap().to_string();
+                    let construct = construct_field(f, &attributes, recursion);
+                    quote_spanned! {f.span()=>
+                        let #field_name = match #construct {
+                            Err(err) => return Err(err.within(#name_string, emojfuscate::PathSegment::Field(#field_name_string))),
                             Ok(result) => {
                                 result
                             }
@@ -530,11 +501,20 @@ pub fn derive_construct_from_emoji(raw_input: proc_macro::TokenStream) -> proc_m
                     }
                 });
 
-                let field_constructors = alphabetically_sorted_fields.iter().map(|f| {
+                // skipped fields get their default value
+                let field_constructors = fields.named.iter().map(|f| {
                     let field_name = &f.ident;
+                    let attributes = FieldAttributes::of(f);
 
-                    quote_spanned! {f.span()=>
-                        #field_name: #field_name,
+                    if attributes.skip {
+                        let default_value = attributes.default_value();
+                        quote_spanned! {f.span()=>
+                            #field_name: #default_value,
+                        }
+                    } else {
+                        quote_spanned! {f.span()=>
+                            #field_name: #field_name,
+                        }
                     }
                 });
 
@@ -549,12 +529,12 @@ pub fn derive_construct_from_emoji(raw_input: proc_macro::TokenStream) -> proc_m
                 }
             }
             Fields::Unnamed(ref fields) => {
-                let declare_fields = fields.unnamed.iter().enumerate().map(|(i, f)| {
+                let declare_fields = encoded_fields(&data.fields).into_iter().map(|(i, f, attributes)| {
                     let field_name = Ident::new(&format!("field{}", i), Span::call_site());
-                    let field_type = &f.ty;
+                    let construct = construct_field(f, &attributes, recursion);
                     quote_spanned! {f.span()=>
-                        let #field_name = match #field_type::construct_from_emoji(byte_stream) {
-                            Err(err) => return Err(err),
+                        let #field_name = match #construct {
+                            Err(err) => return Err(err.within(#name_string, emojfuscate::PathSegment::Position(#i))),
                             Ok(result) => {
                                 result
                             }
@@ -565,9 +545,14 @@ pub fn derive_construct_from_emoji(raw_input: proc_macro::TokenStream) -> proc_m
 
                 let field_constructors = fields.unnamed.iter().enumerate().map(|(i, f)| {
                     let field_name = Ident::new(&format!("field{}", i), Span::call_site());
+                    let attributes = FieldAttributes::of(f);
 
-                    quote_spanned! {f.span()=>
-                        #field_name
+                    if attributes.skip {
+                        attributes.default_value()
+                    } else {
+                        quote_spanned! {f.span()=>
+                            #field_name
+                        }
                     }
                 });
 
@@ -591,219 +576,94 @@ pub fn derive_construct_from_emoji(raw_input: proc_macro::TokenStream) -> proc_m
             }
         },
         Data::Enum(ref data) => {
-            let parsed_data = data.variants.iter().enumerate().filter_map(|(variant_index, variant)|
-                match variant.fields {
-                    Fields::Named(ref fields) => {
-                        let alphabetically_sorted_fields = {
-                            let mut named_fields_to_sort = fields.named.iter().collect::<Vec<_>>();
-                            named_fields_to_sort.sort_by_key(|f| &f.ident);
-
-                            named_fields_to_sort
-                        };
-
-                        let mut field_types = alphabetically_sorted_fields.iter().map(|f| &f.ty);
-                        let constructor_name =
-                            Ident::new(&format!("constructor{}", variant_index), Span::call_site());
-
-                        Some(if field_types.len() == 1 {
-                            let only_field = field_types.next().unwrap();
-
-                            quote! {
-                                let #constructor_name =
-                                    match Option::<#only_field>::construct_from_emoji(byte_stream) {
-                                        Err(err) => return Err(err),
-                                        Ok(x) => {
-                                            x
-                                        }
-                                    };
-                            }
-                        } else {
-                            quote! {
-                                let #constructor_name =
-                                    match Option::<(#(#field_types),*)>::construct_from_emoji(byte_stream) {
-                                        Err(err) => return Err(err),
-                                        Ok(x) => {
-                                            x
-                                        }
-                                    };
-                            }
-                        })
-                    }
-                    Fields::Unnamed(ref fields) => {
-                        let mut field_types = fields.unnamed.iter().map(|f| &f.ty);
-                        let constructor_name =
-                            Ident::new(&format!("constructor{}", variant_index), Span::call_site());
-
-                        Some(if field_types.len() == 1 {
-                            let only_field = field_types.next().unwrap();
-
-                            quote! {
-                                let #constructor_name =
-                                    match Option::<#only_field>::construct_from_emoji(byte_stream) {
-                                        Err(err) => return Err(err),
-                                        Ok(x) => {
-                                            x
-                                        }
-                                    };
-                            }
-                        } else {
-                            quote! {
-                                let #constructor_name =
-                                    match Option::<(#(#field_types),*)>::construct_from_emoji(byte_stream) {
-                                        Err(err) => return Err(err),
-                                        Ok(x) => {
-                                            x
-                                        }
-                                    };
-                            }
-                        })
-                    }
-                    Fields::Unit => None,
-                },
-            );
-
+            // only the fields of the chosen variant are in the emoji, right after the tag
             let constructors = data
                 .variants
                 .iter()
                 .enumerate()
                 .map(|(variant_index, variant)| {
+                    let tag = attributes.tags[variant_index];
                     let variant_name = &variant.ident;
-
-                    match variant.fields {
-                        Fields::Named(ref fields) => {
-                            let alphabetically_sorted_fields = {
-                                let mut named_fields_to_sort = fields.named.iter().collect::<Vec<_>>();
-                                named_fields_to_sort.sort_by_key(|f| &f.ident);
-
-                                named_fields_to_sort
+                    let variant_name_string = variant.ident.to_string();
+
+                    let construct_fields = encoded_fields(&variant.fields).into_iter().map(
+                        |(field_index, f, attributes)| {
+                            let binding = variant_binding(field_index, f);
+                            let construct = construct_field(f, &attributes, recursion);
+                            let segment = match f.ident {
+                                Some(ref ident) => {
+                                    let field_name_string = ident.to_string();
+                                    quote! {emojfuscate::PathSegment::Field(#field_name_string)}
+                                }
+                                None => quote! {emojfuscate::PathSegment::Position(#field_index)},
                             };
 
-                            let field_names = alphabetically_sorted_fields.iter().map(|f| &f.ident);
-                            let index = variant_index as u8;
-
-                            let pattern_matching_data =
-                                data
-                                    .variants
-                                    .iter()
-                                    .enumerate()
-                                    .filter(|(_, v)| match v.fields {
-                                        Fields::Unit => false,
-                                        _ => true,
-                                    })
-                                    .map(|(i, _)| {
-                                        if i as u8 == variant_index as u8 {
-                                            let field_names2 = alphabetically_sorted_fields.iter().map(|f| &f.ident);
-
-                                            quote! {
-                                                Some((#(#field_names2),*))
-                                            }
-                                        } else {
-                                            quote! {None}
-                                        }
-                                    });
-
-
-                            quote! {
-                                (#index #(, #pattern_matching_data)*) => Ok(#name::#variant_name{#(#field_names),*})
+                            quote_spanned! {f.span()=>
+                                let #binding = match #construct {
+                                    Err(err) => {
+                                        return Err(err
+                                            .within(#name_string, #segment)
+                                            .within(#name_string, emojfuscate::PathSegment::Variant(#variant_name_string)))
+                                    }
+                                    Ok(result) => result,
+                                };
                             }
+                        },
+                    );
+
+                    // skipped fields get their default value
+                    let field_values = variant.fields.iter().enumerate().map(|(field_index, f)| {
+                        let attributes = FieldAttributes::of(f);
+
+                        if attributes.skip {
+                            attributes.default_value()
+                        } else {
+                            let binding = variant_binding(field_index, f);
+                            quote! {#binding}
                         }
-                        Fields::Unnamed(ref fields) => {
-                            let field_names = fields.unnamed.iter().enumerate().map(|(field_index, _)| { Ident::new(&format!("x{}", field_index), Span::call_site()) });
-                            let index = variant_index as u8;
-
-                            let pattern_matching_data =
-                                data
-                                    .variants
-                                    .iter()
-                                    .enumerate()
-                                    .filter(|(_, v)| match v.fields {
-                                        Fields::Unit => false,
-                                        _ => true,
-                                    })
-                                    .map(|(i, _)| {
-                                        if i as u8 == variant_index as u8 {
-                                            let field_names2 = fields.unnamed.iter().enumerate().map(|(field_index, _)| { Ident::new(&format!("x{}", field_index), Span::call_site()) });
-
-                                            quote! {
-                                                Some((#(#field_names2),*))
-                                            }
-                                        } else {
-                                            quote! {None}
-                                        }
-                                    });
-
-
-                            quote! {
-                                (#index #(, #pattern_matching_data)*) => Ok(#name::#variant_name(#(#field_names),*))
-                            }
+                    });
+
+                    let constructor = match variant.fields {
+                        Fields::Named(_) => {
+                            let field_names = variant.fields.iter().map(|f| &f.ident);
+                            quote! {#name::#variant_name{#(#field_names: #field_values),*}}
                         }
-                        Fields::Unit => {
-                            let index = variant_index as u8;
-
-                            let pattern_matching_data =
-                                data
-                                    .variants
-                                    .iter()
-                                    .enumerate()
-                                    .filter(|(_, v)| match v.fields {
-                                        Fields::Unit => false,
-                                        _ => true,
-                                    })
-                                    .map(|_| { quote! {None} });
-
-                            quote! {
-                                (#index #(, #pattern_matching_data)*) => Ok(#name::#variant_name)
-                            }
+                        Fields::Unnamed(_) => quote! {#name::#variant_name(#(#field_values),*)},
+                        Fields::Unit => quote! {#name::#variant_name},
+                    };
+
+                    quote! {
+                        #tag => {
+                            #(#construct_fields)*
+                            Ok(#constructor)
                         }
                     }
                 });
 
-            let parsed_data_variable_names = data.variants.iter().enumerate().filter_map(
-                |(variant_index, variant)| match variant.fields {
-                    Fields::Named(_) | Fields::Unnamed(_) => Some(Ident::new(
-                        &format!("constructor{}", variant_index),
-                        Span::call_site(),
-                    )),
-                    Fields::Unit => None,
-                },
-            );
-
             quote! {
-                let constructor_discriminator =
-                    match u8::construct_from_emoji(byte_stream) {
-                        Err(err) => return Err(err),
-                        Ok(n) => {
-                            n
-                        }
+                let tag =
+                    match byte_stream.read_variant_tag() {
+                        Err(err) => return Err(err.in_type(#name_string)),
+                        Ok(tag) => tag,
                     };
 
-                #(#parsed_data)*
-
-                match (constructor_discriminator #(, #parsed_data_variable_names)*) {
-                    #(#constructors),*,
-                    _ => Err(emojfuscate::FromEmojiError::UnexpectedInput("Constructor choice and data don't agree when demojfuscating #name".to_string()))
+                match tag {
+                    #(#constructors)*
+                    tag => Err(byte_stream.error_here(emojfuscate::FromEmojiErrorKind::UnknownVariant(tag)).in_type(#name_string))
                 }
             }
         }
         Data::Union(_) => unimplemented!(),
     };
 
-    // <I, ...> where ... are any generics from the type implementing ConstructFromEmoji
-    let generics = input
-        .generics
-        .params
-        .iter()
-        .filter_map(|p| match p {
-            GenericParam::Type(type_param) => {
-                let ident = &type_param.ident;
-                Some(quote! {#ident})
-            }
-            _ => None,
-        })
-        .chain(once(quote! {I}));
+    // <..., I> where ... are the generics of the type implementing ConstructFromEmoji
+    let generics = impl_generics(&input.generics, once(quote! {I}));
+
+    let where_start = where_clause_start(&input.generics, &attributes.bound.construct_from_emoji);
+    let inferred = attributes.bound.construct_from_emoji.is_none();
 
     let implementations = input.generics.params.iter().filter_map(|p| match p {
-        GenericParam::Type(type_param) => {
+        GenericParam::Type(type_param) if inferred => {
             let ident = &type_param.ident;
             Some(quote! {#ident: ConstructFromEmoji<#ident, I>,
             })
@@ -811,6 +671,18 @@ pub fn derive_construct_from_emoji(raw_input: proc_macro::TokenStream) -> proc_m
         _ => None,
     });
 
+    // fields that aren't in the emoji get their value from Default, unless they say otherwise
+    let all_fields: Vec<&syn::Field> = match input.data {
+        Data::Struct(ref data) => data.fields.iter().collect(),
+        Data::Enum(ref data) => data.variants.iter().flat_map(|v| v.fields.iter()).collect(),
+        Data::Union(_) => Vec::new(),
+    };
+
+    let default_types = all_fields
+        .into_iter()
+        .filter(|f| inferred && FieldAttributes::of(f).needs_default_trait())
+        .map(|f| &f.ty);
+
     /*
     // For a struct with named fields, e.g.
 
@@ -832,7 +704,7 @@ pub fn derive_construct_from_emoji(raw_input: proc_macro::TokenStream) -> proc_m
         {
             let age = match u8::construct_from_emoji(byte_stream)
                 {
-                    Err(err) => return Err(err),
+                    Err(err) => return Err(err.within("Person", emojfuscate::PathSegment::Field("age"))),
                     Ok(result) => {
                         result
                     }
@@ -840,7 +712,7 @@ pub fn derive_construct_from_emoji(raw_input: proc_macro::TokenStream) -> proc_m
 
             let name = match String::construct_from_emoji(byte_stream)
                 {
-                    Err(err) => return Err(err),
+                    Err(err) => return Err(err.within("Person", emojfuscate::PathSegment::Field("name"))),
                     Ok(result) => {
                         result
                     }
@@ -848,7 +720,7 @@ pub fn derive_construct_from_emoji(raw_input: proc_macro::TokenStream) -> proc_m
 
             let luggage = match A::construct_from_emoji(byte_stream)
                 {
-                    Err(err) => return Err(err),
+                    Err(err) => return Err(err.within("Person", emojfuscate::PathSegment::Field("luggage"))),
                     Ok(result) => {
                         result
                     }
@@ -863,7 +735,9 @@ pub fn derive_construct_from_emoji(raw_input: proc_macro::TokenStream) -> proc_m
         impl<#(#generics),*> ConstructFromEmoji<#name #ty_generics, I> for #name #ty_generics
         where
             I: Iterator<Item = u8>,
+            #where_start
             #(#implementations)*
+            #(#default_types: core::default::Default,)*
         {
             fn construct_from_emoji(
                 byte_stream: &mut emojfuscate::DecodeEmojiToBytes<I>,
@@ -875,3 +749,900 @@ pub fn derive_construct_from_emoji(raw_input: proc_macro::TokenStream) -> proc_m
 
     return proc_macro::TokenStream::from(expanded);
 }
+
+#[proc_macro_derive(EmojfuscateFixedSize, attributes(emojfuscate))]
+pub fn derive_emojfuscate_fixed_size(
+    raw_input: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(raw_input as DeriveInput);
+    let attributes = match ContainerAttributes::parse(&input) {
+        Ok(attributes) => attributes,
+        Err(err) => return proc_macro::TokenStream::from(err.to_compile_error()),
+    };
+    let name = input.ident;
+
+    /*
+    // For a struct, e.g.
+
+    struct Id<A> {
+        shard: u16,
+        id: A,
+    }
+
+    // It should generate code that looks like this:
+
+    impl<A> emojfuscate::EmojfuscateFixedSize for Id<A>
+    where
+        u16: emojfuscate::EmojfuscateFixedSize,
+        A: emojfuscate::EmojfuscateFixedSize,
+    {
+        const ENCODED_BYTES: usize = 0
+            + <u16 as emojfuscate::EmojfuscateFixedSize>::ENCODED_BYTES
+            + <A as emojfuscate::EmojfuscateFixedSize>::ENCODED_BYTES;
+    }
+    */
+
+    // the order of the fields doesn't matter for the size
+    let field_types: Vec<_> = match input.data {
+        // the names of the fields and the start and stop emoji are in the emoji too
+        Data::Struct(_) if attributes.evolvable => {
+            return proc_macro::TokenStream::from(
+                syn::Error::new(
+                    name.span(),
+                    "EmojfuscateFixedSize can't be derived for evolvable structs",
+                )
+                .to_compile_error(),
+            )
+        }
+        // whatever the module does is up to the module
+        Data::Struct(ref data)
+            if data
+                .fields
+                .iter()
+                .any(|f| FieldAttributes::of(f).with.is_some()) =>
+        {
+            return proc_macro::TokenStream::from(
+                syn::Error::new(
+                    name.span(),
+                    "EmojfuscateFixedSize can't be derived for structs with `with` fields",
+                )
+                .to_compile_error(),
+            )
+        }
+        // the values can be nested any number of times
+        Data::Struct(_) if is_recursive(&input.data, &name) => {
+            return proc_macro::TokenStream::from(
+                syn::Error::new(
+                    name.span(),
+                    "EmojfuscateFixedSize can't be derived for recursive structs",
+                )
+                .to_compile_error(),
+            )
+        }
+        Data::Struct(ref data) => encoded_fields(&data.fields)
+            .into_iter()
+            .map(|(_, f, _)| &f.ty)
+            .collect(),
+        // the variants of an enum can have different sizes
+        _ => {
+            return proc_macro::TokenStream::from(
+                syn::Error::new(
+                    name.span(),
+                    "EmojfuscateFixedSize can only be derived for structs",
+                )
+                .to_compile_error(),
+            )
+        }
+    };
+
+    let where_start = where_clause_start(&input.generics, &attributes.bound.emojfuscate_fixed_size);
+    let bounds = match attributes.bound.emojfuscate_fixed_size {
+        Some(_) => Vec::new(),
+        None => field_types.clone(),
+    };
+
+    let (impl_generics, ty_generics, _) = input.generics.split_for_impl();
+
+    let expanded = quote! {
+        impl #impl_generics emojfuscate::EmojfuscateFixedSize for #name #ty_generics
+        where
+            #where_start
+            #(#bounds: emojfuscate::EmojfuscateFixedSize,)*
+        {
+            const ENCODED_BYTES: usize = 0
+                #(+ <#field_types as emojfuscate::EmojfuscateFixedSize>::ENCODED_BYTES)*;
+        }
+    };
+
+    return proc_macro::TokenStream::from(expanded);
+}
+
+#[proc_macro_derive(EmojiSchema, attributes(emojfuscate))]
+pub fn derive_emoji_schema(raw_input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(raw_input as DeriveInput);
+    let attributes = match ContainerAttributes::parse(&input) {
+        Ok(attributes) => attributes,
+        Err(err) => return proc_macro::TokenStream::from(err.to_compile_error()),
+    };
+    let name = input.ident;
+    let name_string = name.to_string();
+    let recursion = is_recursive(&input.data, &name).then_some(&name);
+
+    /*
+    // For a struct, e.g.
+
+    struct Person<A> {
+        name: String,
+        luggage: A,
+    }
+
+    // It should generate code that looks like this:
+    // (the fields come in alphabetical order, like in the emoji)
+
+    impl<A> emojfuscate::EmojiSchema for Person<A>
+    where
+        A: emojfuscate::EmojiSchema,
+        String: emojfuscate::EmojiSchema,
+    {
+        fn emoji_schema() -> emojfuscate::Schema {
+            emojfuscate::Schema::Struct {
+                name: "Person",
+                fields: emojfuscate::Fields::Named(core::convert::From::from([
+                    emojfuscate::Field {
+                        name: "luggage",
+                        schema: <A as emojfuscate::EmojiSchema>::emoji_schema(),
+                    },
+                    emojfuscate::Field {
+                        name: "name",
+                        schema: <String as emojfuscate::EmojiSchema>::emoji_schema(),
+                    },
+                ])),
+            }
+        }
+    }
+
+    // and enums become an emojfuscate::Schema::Enum with an emojfuscate::Variant for each variant
+
+    // The schema of a field that has the type itself in its type, like `Vec<Person<A>>`, would
+    // go on forever, so the type is replaced with a struct whose schema is
+    // `emojfuscate::Schema::Recursive("Person")` and the schema is the one of
+    // `Vec<EmojfuscateRecursion>` instead
+    */
+
+    let (schema, field_types) = match input.data {
+        Data::Struct(ref data) => {
+            let fields = fields_schema(&data.fields, attributes.evolvable, recursion);
+
+            (
+                quote! {
+                    emojfuscate::Schema::Struct {
+                        name: #name_string,
+                        fields: #fields,
+                    }
+                },
+                data.fields.iter().collect::<Vec<_>>(),
+            )
+        }
+        Data::Enum(ref data) => {
+            let variants = data
+                .variants
+                .iter()
+                .enumerate()
+                .map(|(variant_index, variant)| {
+                    let variant_name_string = variant.ident.to_string();
+                    let tag = attributes.tags[variant_index];
+                    let fields = fields_schema(&variant.fields, false, recursion);
+
+                    quote! {
+                        emojfuscate::Variant {
+                            name: #variant_name_string,
+                            tag: #tag,
+                            fields: #fields,
+                        }
+                    }
+                });
+
+            (
+                quote! {
+                    emojfuscate::Schema::Enum {
+                        name: #name_string,
+                        variants: core::convert::From::from([#(#variants),*]),
+                    }
+                },
+                data.variants
+                    .iter()
+                    .flat_map(|v| v.fields.iter())
+                    .collect::<Vec<_>>(),
+            )
+        }
+        Data::Union(_) => {
+            return proc_macro::TokenStream::from(
+                syn::Error::new(name.span(), "EmojiSchema can't be derived for unions")
+                    .to_compile_error(),
+            )
+        }
+    };
+
+    // the schemas of skipped fields and of fields with `with` aren't needed, and the bounds of
+    // fields with the type itself in their type have `()` in its place, which has a schema just
+    // like the struct that stands in for it
+    let field_types = field_types
+        .into_iter()
+        .filter(|f| {
+            let field_attributes = FieldAttributes::of(f);
+            attributes.bound.emoji_schema.is_none()
+                && !field_attributes.skip
+                && field_attributes.with.is_none()
+        })
+        .map(|f| {
+            let mut field_type = f.ty.clone();
+
+            if let Some(name) = recursion {
+                replace_recursion(&mut field_type, name, &syn::parse_quote!(()));
+            }
+
+            field_type
+        });
+
+    let recursion_schema = recursion.map(|_| {
+        quote! {
+            struct EmojfuscateRecursion;
+
+            impl emojfuscate::EmojiSchema for EmojfuscateRecursion {
+                fn emoji_schema() -> emojfuscate::Schema {
+                    emojfuscate::Schema::Recursive(#name_string)
+                }
+            }
+        }
+    });
+
+    let where_start = where_clause_start(&input.generics, &attributes.bound.emoji_schema);
+    let (impl_generics, ty_generics, _) = input.generics.split_for_impl();
+
+    let expanded = quote! {
+        impl #impl_generics emojfuscate::EmojiSchema for #name #ty_generics
+        where
+            #where_start
+            #(#field_types: emojfuscate::EmojiSchema,)*
+        {
+            fn emoji_schema() -> emojfuscate::Schema {
+                #recursion_schema
+                #schema
+            }
+        }
+    };
+
+    return proc_macro::TokenStream::from(expanded);
+}
+
+/// The `emojfuscate::Fields` of a struct or an enum variant, named fields in alphabetical order
+/// of their names in the emoji. Skipped fields are left out. `recursion` is the name of the type
+/// if it's recursive, see `is_recursive`, which is replaced with `EmojfuscateRecursion` in the
+/// types of the fields.
+fn fields_schema(
+    fields: &Fields,
+    evolvable: bool,
+    recursion: Option<&Ident>,
+) -> proc_macro2::TokenStream {
+    let schemas = encoded_fields(fields).into_iter().map(|(_, f, attributes)| {
+        let schema = match attributes.with {
+            Some(ref module) => quote_spanned! {f.span()=>#module::emoji_schema()},
+            None => {
+                let mut field_type = f.ty.clone();
+
+                if let Some(name) = recursion {
+                    replace_recursion(&mut field_type, name, &syn::parse_quote!(EmojfuscateRecursion));
+                }
+
+                quote_spanned! {f.span()=><#field_type as emojfuscate::EmojiSchema>::emoji_schema()}
+            }
+        };
+
+        (emoji_name(f, &attributes), schema)
+    });
+
+    match fields {
+        Fields::Named(_) => {
+            let fields = schemas.map(|(field_name_string, schema)| {
+                quote! {
+                    emojfuscate::Field {
+                        name: #field_name_string,
+                        schema: #schema,
+                    }
+                }
+            });
+
+            if evolvable {
+                quote! {
+                    emojfuscate::Fields::Evolvable(core::convert::From::from([#(#fields),*]))
+                }
+            } else {
+                quote! {
+                    emojfuscate::Fields::Named(core::convert::From::from([#(#fields),*]))
+                }
+            }
+        }
+        Fields::Unnamed(_) => {
+            let schemas = schemas.map(|(_, schema)| schema);
+
+            quote! {
+                emojfuscate::Fields::Unnamed(core::convert::From::from([#(#schemas),*]))
+            }
+        }
+        Fields::Unit => quote! {emojfuscate::Fields::Unit},
+    }
+}
+
+/// The `#[emojfuscate(...)]` attributes of a struct or an enum.
+#[derive(Default)]
+struct ContainerAttributes {
+    /// `#[emojfuscate(evolvable)]`, the fields are encoded with their names so that fields can be
+    /// added and removed without breaking old emoji.
+    evolvable: bool,
+    /// `#[emojfuscate(bound = "T: Trait")]` or `#[emojfuscate(bound(construct_from_emoji = "..."))]`,
+    /// the bounds of the impls instead of the ones the derives infer.
+    bound: Bounds,
+    /// The tag of every variant of an enum, in declaration order, see `variant_tags`.
+    tags: Vec<u32>,
+}
+
+/// The bounds of each derived impl that were given with `#[emojfuscate(bound = ...)]`, `None`
+/// means that the derive infers them from the fields and type parameters. The bounds that name
+/// the types of the iterators of the fields in a derived `Emojfuscate` are always needed, so they
+/// stay either way.
+#[derive(Default)]
+struct Bounds {
+    emojfuscate: Option<Vec<syn::WherePredicate>>,
+    construct_from_emoji: Option<Vec<syn::WherePredicate>>,
+    emojfuscate_fixed_size: Option<Vec<syn::WherePredicate>>,
+    emoji_schema: Option<Vec<syn::WherePredicate>>,
+}
+
+/// The `#[emojfuscate(...)]` attributes of an enum variant.
+#[derive(Default)]
+struct VariantAttributes {
+    /// `#[emojfuscate(tag = N)]`, the tag of the variant in the emoji instead of its discriminant.
+    tag: Option<u32>,
+}
+
+/// The `#[emojfuscate(...)]` attributes of a field.
+#[derive(Default)]
+struct FieldAttributes {
+    /// `#[emojfuscate(skip)]`, the field isn't in the emoji and gets its default value.
+    skip: bool,
+    /// `#[emojfuscate(default)]` or `#[emojfuscate(default = path)]`, the value of a skipped field
+    /// or of a field of an evolvable struct that isn't in the emoji.
+    default: Option<DefaultValue>,
+    /// `#[emojfuscate(rename = "name")]`, the name of the field in the emoji. The fields are
+    /// sorted by this name, so renaming a field in Rust doesn't have to move it in the emoji.
+    rename: Option<String>,
+    /// `#[emojfuscate(with = module)]`, the field is encoded with `module::emojfuscate_stream`,
+    /// decoded with `module::construct_from_emoji` and described by `module::emoji_schema`.
+    with: Option<syn::Path>,
+}
+
+enum DefaultValue {
+    Trait,
+    Function(syn::Path),
+}
+
+impl ContainerAttributes {
+    /// Parse the attributes of the type and of all of its fields, so that mistakes are reported
+    /// before any code is generated.
+    fn parse(input: &DeriveInput) -> syn::Result<ContainerAttributes> {
+        let mut attributes = ContainerAttributes::default();
+
+        for attr in input
+            .attrs
+            .iter()
+            .filter(|a| a.path().is_ident("emojfuscate"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("evolvable") {
+                    attributes.evolvable = true;
+                    Ok(())
+                } else if meta.path.is_ident("bound") && meta.input.peek(syn::Token![=]) {
+                    let predicates = parse_bound(&meta)?;
+                    attributes.bound = Bounds {
+                        emojfuscate: Some(predicates.clone()),
+                        construct_from_emoji: Some(predicates.clone()),
+                        emojfuscate_fixed_size: Some(predicates.clone()),
+                        emoji_schema: Some(predicates),
+                    };
+                    Ok(())
+                } else if meta.path.is_ident("bound") {
+                    let bound = &mut attributes.bound;
+                    meta.parse_nested_meta(|meta| {
+                        let derive = if meta.path.is_ident("emojfuscate") {
+                            &mut bound.emojfuscate
+                        } else if meta.path.is_ident("construct_from_emoji") {
+                            &mut bound.construct_from_emoji
+                        } else if meta.path.is_ident("emojfuscate_fixed_size") {
+                            &mut bound.emojfuscate_fixed_size
+                        } else if meta.path.is_ident("emoji_schema") {
+                            &mut bound.emoji_schema
+                        } else {
+                            return Err(meta.error("unknown derive in emojfuscate bound"));
+                        };
+                        *derive = Some(parse_bound(&meta)?);
+                        Ok(())
+                    })
+                } else {
+                    Err(meta.error("unknown emojfuscate attribute"))
+                }
+            })?;
+        }
+
+        let fields_in_order: Vec<&Fields> = match input.data {
+            Data::Struct(ref data) => {
+                if attributes.evolvable && !matches!(data.fields, Fields::Named(_)) {
+                    return Err(syn::Error::new(
+                        input.ident.span(),
+                        "only structs with named fields can be evolvable",
+                    ));
+                }
+
+                Vec::from([&data.fields])
+            }
+            Data::Enum(ref data) => {
+                if attributes.evolvable {
+                    return Err(syn::Error::new(
+                        input.ident.span(),
+                        "only structs with named fields can be evolvable",
+                    ));
+                }
+
+                attributes.tags = variant_tags(data)?;
+                data.variants.iter().map(|v| &v.fields).collect()
+            }
+            Data::Union(_) => Vec::new(),
+        };
+
+        for fields in fields_in_order {
+            for field in fields {
+                let field_attributes = FieldAttributes::parse(field)?;
+
+                let mistake = if field_attributes.default.is_some()
+                    && !field_attributes.skip
+                    && !attributes.evolvable
+                {
+                    Some(
+                        "`default` is for skipped fields and fields of structs with \
+                         #[emojfuscate(evolvable)], other fields are always in the emoji",
+                    )
+                } else if field_attributes.skip
+                    && (field_attributes.rename.is_some() || field_attributes.with.is_some())
+                {
+                    Some("a skipped field isn't in the emoji, so it can't be renamed or encoded with a module")
+                } else if field_attributes.rename.is_some() && field.ident.is_none() {
+                    Some("only named fields can be renamed")
+                } else {
+                    None
+                };
+
+                if let Some(mistake) = mistake {
+                    return Err(syn::Error::new(field.span(), mistake));
+                }
+            }
+
+            let mut names = encoded_fields(fields)
+                .into_iter()
+                .filter_map(|(_, f, attributes)| Some((emoji_name(f, &attributes)?, f)))
+                .collect::<Vec<_>>();
+            names.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+            if let Some(pair) = names.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+                return Err(syn::Error::new(
+                    pair[1].1.span(),
+                    format!(
+                        "there is already a field called `{}` in the emoji",
+                        pair[1].0
+                    ),
+                ));
+            }
+        }
+
+        Ok(attributes)
+    }
+}
+
+impl VariantAttributes {
+    fn parse(variant: &syn::Variant) -> syn::Result<VariantAttributes> {
+        let mut attributes = VariantAttributes::default();
+
+        for attr in variant
+            .attrs
+            .iter()
+            .filter(|a| a.path().is_ident("emojfuscate"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("tag") {
+                    let tag: syn::LitInt = meta.value()?.parse()?;
+                    attributes.tag = Some(tag.base10_parse()?);
+                    Ok(())
+                } else {
+                    Err(meta.error("unknown emojfuscate attribute"))
+                }
+            })?;
+        }
+
+        Ok(attributes)
+    }
+}
+
+/// The tags of the variants of an enum in the emoji, in declaration order. A variant gets the tag
+/// from `#[emojfuscate(tag = N)]` or from its discriminant, and otherwise the tag after the one of
+/// the variant before it, starting at 0, just like discriminants. So variants can be reordered
+/// without changing the emoji as long as they have explicit tags.
+fn variant_tags(data: &syn::DataEnum) -> syn::Result<Vec<u32>> {
+    let mut tags: Vec<u32> = Vec::new();
+    let mut next_tag = Some(0u32);
+
+    for variant in &data.variants {
+        let tag = match (
+            VariantAttributes::parse(variant)?.tag,
+            &variant.discriminant,
+        ) {
+            (Some(tag), _) => tag,
+            (None, Some((_, discriminant))) => match discriminant {
+                syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Int(int),
+                    ..
+                }) => int.base10_parse()?,
+                _ => {
+                    return Err(syn::Error::new(
+                        discriminant.span(),
+                        "only integer literals can be used as tags, \
+                         use #[emojfuscate(tag = N)] to give the variant a tag",
+                    ))
+                }
+            },
+            (None, None) => next_tag.ok_or_else(|| {
+                syn::Error::new(
+                    variant.ident.span(),
+                    "the tag of the variant doesn't fit in a u32",
+                )
+            })?,
+        };
+
+        if let Some(i) = tags.iter().position(|t| *t == tag) {
+            return Err(syn::Error::new(
+                variant.ident.span(),
+                format!(
+                    "the tag {} is already used by `{}`",
+                    tag, data.variants[i].ident
+                ),
+            ));
+        }
+
+        tags.push(tag);
+        next_tag = tag.checked_add(1);
+    }
+
+    Ok(tags)
+}
+
+impl FieldAttributes {
+    fn parse(field: &syn::Field) -> syn::Result<FieldAttributes> {
+        let mut attributes = FieldAttributes::default();
+
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|a| a.path().is_ident("emojfuscate"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    attributes.skip = true;
+                } else if meta.path.is_ident("default") {
+                    attributes.default = Some(if meta.input.peek(syn::Token![=]) {
+                        DefaultValue::Function(meta.value()?.parse()?)
+                    } else {
+                        DefaultValue::Trait
+                    });
+                } else if meta.path.is_ident("rename") {
+                    let name: syn::LitStr = meta.value()?.parse()?;
+                    attributes.rename = Some(name.value());
+                } else if meta.path.is_ident("with") {
+                    attributes.with = Some(meta.value()?.parse()?);
+                } else {
+                    return Err(meta.error("unknown emojfuscate attribute"));
+                }
+
+                Ok(())
+            })?;
+        }
+
+        Ok(attributes)
+    }
+
+    /// The attributes of a field that has already been checked by `ContainerAttributes::parse`.
+    fn of(field: &syn::Field) -> FieldAttributes {
+        FieldAttributes::parse(field).unwrap_or_default()
+    }
+
+    /// The value of the field when it isn't in the emoji.
+    fn default_value(&self) -> proc_macro2::TokenStream {
+        match self.default {
+            Some(DefaultValue::Function(ref path)) => quote! {#path()},
+            _ => quote! {core::default::Default::default()},
+        }
+    }
+
+    /// Whether `default_value` needs the type of the field to implement `Default`.
+    fn needs_default_trait(&self) -> bool {
+        (self.skip || self.default.is_some())
+            && !matches!(self.default, Some(DefaultValue::Function(_)))
+    }
+}
+
+/// The fields that are in the emoji, i.e. the ones that aren't skipped, in the order they are in
+/// the emoji along with their position in the declaration. Named fields are sorted by their names
+/// in the emoji.
+fn encoded_fields(fields: &Fields) -> Vec<(usize, &syn::Field, FieldAttributes)> {
+    let mut encoded_fields = fields
+        .iter()
+        .enumerate()
+        .map(|(i, f)| (i, f, FieldAttributes::of(f)))
+        .filter(|(_, _, attributes)| !attributes.skip)
+        .collect::<Vec<_>>();
+
+    encoded_fields.sort_by_cached_key(|(_, f, attributes)| emoji_name(f, attributes));
+    encoded_fields
+}
+
+/// The name of a named field in the emoji.
+fn emoji_name(field: &syn::Field, attributes: &FieldAttributes) -> Option<String> {
+    match attributes.rename {
+        Some(ref name) => Some(name.clone()),
+        None => field.ident.as_ref().map(|ident| ident.to_string()),
+    }
+}
+
+/// The parts of a derived `Emojfuscate` implementation that come from the fields that are in the
+/// emoji: the type parameters for the iterators of the fields, the where clause, the type of the
+/// iterator of all of the fields and the expression that chains them together. The type
+/// parameters are called `iterator_prefix` followed by a number, `recursion` is the name of the
+/// type if it's recursive, see `is_recursive`, and `value_of` is the expression for the value of a
+/// field, given its position in the declaration.
+fn emojfuscate_fields<F>(
+    encoded_fields: &[(usize, &syn::Field, FieldAttributes)],
+    evolvable: bool,
+    iterator_prefix: &str,
+    recursion: Option<&Ident>,
+    value_of: F,
+) -> (
+    Vec<proc_macro2::TokenStream>,
+    Vec<proc_macro2::TokenStream>,
+    proc_macro2::TokenStream,
+    proc_macro2::TokenStream,
+)
+where
+    F: Fn(usize, &syn::Field) -> proc_macro2::TokenStream,
+{
+    let mut iterator_names = Vec::new();
+    let mut field_types = Vec::new();
+    let mut iterator_types = Vec::new();
+    let mut streams = Vec::new();
+
+    for (i, (field_index, f, attributes)) in encoded_fields.iter().enumerate() {
+        let value = value_of(*field_index, f);
+
+        let (iterator_type, stream) = match attributes.with {
+            Some(ref module) => (
+                quote! {emojfuscate::BoxedEmojiBytes<'static>},
+                quote_spanned! {f.span()=>#module::emojfuscate_stream(#value).boxed()},
+            ),
+            // the type of the iterator would have itself in it
+            None if recursion.is_some_and(|name| is_recursive_field(f, name)) => (
+                quote! {emojfuscate::BoxedEmojiBytes<'static>},
+                quote_spanned! {f.span()=>#value.emojfuscate_stream().boxed()},
+            ),
+            None => {
+                let iterator_name =
+                    Ident::new(&format!("{}{}", iterator_prefix, i), Span::call_site());
+                let field_type = &f.ty;
+                // the iterators of recursive types end up in the boxed iterators of their fields
+                let lifetime = recursion.map(|_| quote! {+ 'static});
+
+                field_types.push(quote_spanned! {f.span()=>
+                    #field_type: emojfuscate::Emojfuscate<#iterator_name>,
+                    #iterator_name: Iterator<Item = emojfuscate::ByteInSequence> #lifetime,
+                });
+                iterator_names.push(quote! {#iterator_name});
+
+                (
+                    quote! {#iterator_name},
+                    quote_spanned! {f.span()=>#value.emojfuscate_stream()},
+                )
+            }
+        };
+
+        if evolvable {
+            let name_string = emoji_name(f, attributes);
+
+            iterator_types.push(quote! {emojfuscate::EvolvableField<#iterator_type>});
+            streams.push(quote_spanned! {f.span()=>#stream.evolvable_field(#name_string)});
+        } else {
+            iterator_types.push(iterator_type);
+            streams.push(stream);
+        }
+    }
+
+    let iterator_chain = iterator_types
+        .into_iter()
+        .reduce(|chain, element| quote! {core::iter::Chain<#chain, #element>})
+        .unwrap_or_else(|| quote! {core::iter::Empty<emojfuscate::ByteInSequence>});
+
+    let chained_fields = streams
+        .into_iter()
+        .reduce(|prev, f| quote! {#prev.chain_emoji_bytes(#f)})
+        .unwrap_or_else(|| quote! {emojfuscate::Emojfuscate::emojfuscate_stream(())});
+
+    if evolvable {
+        (
+            iterator_names,
+            field_types,
+            quote! {
+                core::iter::Chain<
+                    core::iter::Chain<core::iter::Once<emojfuscate::ByteInSequence>, #iterator_chain>,
+                    core::iter::Once<emojfuscate::ByteInSequence>,
+                >
+            },
+            quote! {#chained_fields.add_start_emoji().add_stop_emoji()},
+        )
+    } else {
+        (iterator_names, field_types, iterator_chain, chained_fields)
+    }
+}
+
+/// Whether any of the fields of a struct or an enum that are in the emoji has the type itself in
+/// its type, like the `Box<Expr>` in `enum Expr { Lit(i64), Neg(Box<Expr>) }`. Fields with `with`
+/// are left to their modules.
+fn is_recursive(data: &Data, name: &Ident) -> bool {
+    let fields: Vec<&Fields> = match data {
+        Data::Struct(data) => Vec::from([&data.fields]),
+        Data::Enum(data) => data.variants.iter().map(|v| &v.fields).collect(),
+        Data::Union(_) => Vec::new(),
+    };
+
+    fields.into_iter().any(|fields| {
+        encoded_fields(fields)
+            .into_iter()
+            .any(|(_, f, attributes)| attributes.with.is_none() && is_recursive_field(f, name))
+    })
+}
+
+/// Whether the type of the field has the type called `name` in it, see `replace_recursion`.
+fn is_recursive_field(field: &syn::Field, name: &Ident) -> bool {
+    let placeholder = syn::Type::Verbatim(proc_macro2::TokenStream::new());
+    replace_recursion(&mut field.ty.clone(), name, &placeholder)
+}
+
+/// Replaces the type called `name`, or `Self`, wherever it is in `ty` with `replacement` and
+/// returns whether it was there. Only paths of a single segment count, e.g. `Box<Expr>` and
+/// `Box<Expr<T>>` have `Expr` in them but `Box<other::Expr>` is a different type.
+fn replace_recursion(ty: &mut syn::Type, name: &Ident, replacement: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(type_path)
+            if type_path.qself.is_none()
+                && type_path.path.leading_colon.is_none()
+                && type_path.path.segments.len() == 1
+                && (type_path.path.segments[0].ident == "Self"
+                    || type_path.path.segments[0].ident == *name) =>
+        {
+            *ty = replacement.clone();
+            true
+        }
+        syn::Type::Path(type_path) => {
+            let mut replaced = false;
+
+            if let Some(qself) = type_path.qself.as_mut() {
+                replaced |= replace_recursion(&mut qself.ty, name, replacement);
+            }
+
+            for segment in type_path.path.segments.iter_mut() {
+                if let syn::PathArguments::AngleBracketed(arguments) = &mut segment.arguments {
+                    for argument in arguments.args.iter_mut() {
+                        if let syn::GenericArgument::Type(ty) = argument {
+                            replaced |= replace_recursion(ty, name, replacement);
+                        }
+                    }
+                }
+            }
+
+            replaced
+        }
+        syn::Type::Tuple(tuple) => tuple.elems.iter_mut().fold(false, |replaced, ty| {
+            replace_recursion(ty, name, replacement) || replaced
+        }),
+        syn::Type::Array(array) => replace_recursion(&mut array.elem, name, replacement),
+        syn::Type::Slice(slice) => replace_recursion(&mut slice.elem, name, replacement),
+        syn::Type::Reference(reference) => {
+            replace_recursion(&mut reference.elem, name, replacement)
+        }
+        syn::Type::Paren(paren) => replace_recursion(&mut paren.elem, name, replacement),
+        syn::Type::Group(group) => replace_recursion(&mut group.elem, name, replacement),
+        _ => false,
+    }
+}
+
+/// The parameters of the type for an impl, with their bounds but without their defaults,
+/// followed by `extra` type parameters, e.g. for the iterators of the fields.
+fn impl_generics<E>(generics: &syn::Generics, extra: E) -> Vec<proc_macro2::TokenStream>
+where
+    E: IntoIterator<Item = proc_macro2::TokenStream>,
+{
+    let params = generics.params.iter().map(|p| match p {
+        GenericParam::Lifetime(lifetime_param) => quote! {#lifetime_param},
+        GenericParam::Type(type_param) => {
+            let mut type_param = type_param.clone();
+            type_param.eq_token = None;
+            type_param.default = None;
+            quote! {#type_param}
+        }
+        GenericParam::Const(const_param) => {
+            let mut const_param = const_param.clone();
+            const_param.eq_token = None;
+            const_param.default = None;
+            quote! {#const_param}
+        }
+    });
+
+    params.chain(extra).collect()
+}
+
+/// Parses the predicates in the string of `bound = "..."`.
+fn parse_bound(meta: &syn::meta::ParseNestedMeta) -> syn::Result<Vec<syn::WherePredicate>> {
+    let bound: syn::LitStr = meta.value()?.parse()?;
+    let predicates = bound.parse_with(
+        syn::punctuated::Punctuated::<syn::WherePredicate, syn::Token![,]>::parse_terminated,
+    )?;
+    Ok(predicates.into_iter().collect())
+}
+
+/// The predicates that every impl starts its where clause with: the ones from the where clause
+/// of the type and the ones given for the impl with `#[emojfuscate(bound = ...)]`, each followed
+/// by a comma.
+fn where_clause_start(
+    generics: &syn::Generics,
+    bound: &Option<Vec<syn::WherePredicate>>,
+) -> proc_macro2::TokenStream {
+    let own = generics
+        .where_clause
+        .iter()
+        .flat_map(|where_clause| where_clause.predicates.iter());
+    let bound = bound.iter().flatten();
+
+    quote! {#(#own,)* #(#bound,)*}
+}
+
+/// The name that a field of an enum variant is bound to in a match, the name of the field or
+/// `x0`, `x1` and so on for unnamed fields.
+fn variant_binding(field_index: usize, field: &syn::Field) -> Ident {
+    match field.ident {
+        Some(ref ident) => ident.clone(),
+        None => Ident::new(&format!("x{}", field_index), Span::call_site()),
+    }
+}
+
+/// The expression that constructs the value of a field from the `byte_stream`. `recursion` is
+/// the name of the type if it's recursive, see `is_recursive`.
+fn construct_field(
+    field: &syn::Field,
+    attributes: &FieldAttributes,
+    recursion: Option<&Ident>,
+) -> proc_macro2::TokenStream {
+    match attributes.with {
+        Some(ref module) => quote! {#module::construct_from_emoji(byte_stream)},
+        // the values could otherwise be nested until the stack overflows
+        None if recursion.is_some_and(|name| is_recursive_field(field, name)) => {
+            let field_type = &field.ty;
+            quote! {byte_stream.construct_nested(<#field_type>::construct_from_emoji)}
+        }
+        None => {
+            let field_type = &field.ty;
+            quote! {<#field_type>::construct_from_emoji(byte_stream)}
+        }
+    }
+}
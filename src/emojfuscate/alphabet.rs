@@ -0,0 +1,256 @@
+use super::constants::{ALPHABET_SIZE, EMOJI, MAX_EMOJI_VALUE};
+
+/// Characters that the decoder skips over, so they can't be part of an alphabet.
+pub const WHITE_SPACE_CHARS: [char; 4] = [' ', '\r', '\n', '\t'];
+
+/// The emoji table that data is encoded with unless something else is specified. It's the first
+/// `ALPHABET_SIZE` emoji from `constants::EMOJI`.
+const DEFAULT_EMOJI: [char; ALPHABET_SIZE] = {
+    let mut emoji = ['a'; ALPHABET_SIZE];
+    let mut index = 0;
+
+    while index < ALPHABET_SIZE {
+        emoji[index] = match char::from_u32(EMOJI[index]) {
+            Some(c) => c,
+            None => panic!("unexpected unicode"),
+        };
+
+        index = index + 1;
+    }
+
+    emoji
+};
+
+static DEFAULT_ALPHABET: Alphabet = match Alphabet::from_array(DEFAULT_EMOJI) {
+    Ok(alphabet) => alphabet,
+    Err(_) => panic!("the default alphabet is invalid"),
+};
+
+/// A table of the emoji that data is encoded as. The emoji at index `n` represents the value `n`,
+/// the first 1024 emoji carry 10 bits of data each and the remaining ones are used for padding and
+/// to mark the start and end of sequences.
+///
+/// Looking up the value of an emoji is a binary search in a table sorted by code point, which is
+/// built when the alphabet is created. For the default alphabet that happens at compile time.
+///
+/// Both the encoder and the decoder need to use the same alphabet. Since they hold on to a
+/// `&'static Alphabet`, custom alphabets are easiest to define with `lazy_static!`, or as a
+/// `static` with `Alphabet::from_array` if the emoji are known at compile time:
+///
+/// ```
+/// use emojfuscate::{Alphabet, Demojfuscate, Emojfuscate, IsEmojiRepresentation};
+///
+/// lazy_static::lazy_static! {
+///     static ref REVERSED: Alphabet = {
+///         let mut emoji = Alphabet::default_alphabet().emoji().to_vec();
+///         emoji.reverse();
+///         Alphabet::new(emoji).unwrap()
+///     };
+/// }
+///
+/// let emojified: String = 1234u16.emojfuscate_stream().with_alphabet(&REVERSED).collect();
+///
+/// let demojfuscated: Result<u16, _> = emojified
+///     .demojfuscate_byte_stream()
+///     .with_alphabet(&REVERSED)
+///     .demojfuscate();
+///
+/// assert_eq!(demojfuscated, Ok(1234));
+/// ```
+pub struct Alphabet {
+    emoji: [char; ALPHABET_SIZE],
+    sorted_emoji: [char; ALPHABET_SIZE],
+    sorted_values: [u16; ALPHABET_SIZE],
+    fingerprint: u16,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum AlphabetError {
+    WrongNumberOfEmoji { expected: usize, found: usize },
+    DuplicateEmoji(char),
+    WhiteSpace(char),
+}
+
+impl Alphabet {
+    /// Create an alphabet from exactly `ALPHABET_SIZE` unique characters. White space isn't
+    /// allowed since the decoder ignores it.
+    pub fn new<E>(emoji: E) -> Result<Alphabet, AlphabetError>
+    where
+        E: IntoIterator<Item = char>,
+    {
+        let mut array = ['\0'; ALPHABET_SIZE];
+        let mut found = 0;
+
+        for c in emoji {
+            if found < ALPHABET_SIZE {
+                array[found] = c;
+            }
+
+            found += 1;
+        }
+
+        if found != ALPHABET_SIZE {
+            return Err(AlphabetError::WrongNumberOfEmoji {
+                expected: ALPHABET_SIZE,
+                found,
+            });
+        }
+
+        Alphabet::from_array(array)
+    }
+
+    /// Like `Alphabet::new`, but usable in a `const` or `static` so the lookup table is built at
+    /// compile time.
+    pub const fn from_array(emoji: [char; ALPHABET_SIZE]) -> Result<Alphabet, AlphabetError> {
+        let mut index = 0;
+
+        while index < ALPHABET_SIZE {
+            let mut white_space_index = 0;
+
+            while white_space_index < WHITE_SPACE_CHARS.len() {
+                if emoji[index] == WHITE_SPACE_CHARS[white_space_index] {
+                    return Err(AlphabetError::WhiteSpace(emoji[index]));
+                }
+
+                white_space_index += 1;
+            }
+
+            index += 1;
+        }
+
+        let mut sorted_emoji = emoji;
+        let mut sorted_values = [0; ALPHABET_SIZE];
+
+        let mut index = 0;
+        while index < ALPHABET_SIZE {
+            sorted_values[index] = index as u16;
+            index += 1;
+        }
+
+        sort_by_code_point(&mut sorted_emoji, &mut sorted_values);
+
+        // after sorting any duplicates end up next to each other
+        let mut index = 1;
+        while index < ALPHABET_SIZE {
+            if sorted_emoji[index] == sorted_emoji[index - 1] {
+                return Err(AlphabetError::DuplicateEmoji(sorted_emoji[index]));
+            }
+
+            index += 1;
+        }
+
+        Ok(Alphabet {
+            emoji,
+            sorted_emoji,
+            sorted_values,
+            fingerprint: fingerprint(&emoji),
+        })
+    }
+
+    /// The alphabet that is used when nothing else is specified.
+    pub fn default_alphabet() -> &'static Alphabet {
+        &DEFAULT_ALPHABET
+    }
+
+    /// All emoji in the alphabet, ordered by the value they represent.
+    pub fn emoji(&self) -> &[char] {
+        &self.emoji
+    }
+
+    /// The emoji that represents `value`. Panics if the value is outside of the alphabet.
+    pub fn value_to_emoji(&self, value: u16) -> char {
+        self.emoji[usize::from(value)]
+    }
+
+    /// The value represented by `emoji`, or `None` if it isn't a part of the alphabet.
+    pub fn emoji_to_value(&self, emoji: char) -> Option<u16> {
+        match self.sorted_emoji.binary_search(&emoji) {
+            Ok(index) => Some(self.sorted_values[index]),
+            Err(_) => None,
+        }
+    }
+
+    /// A 10 bit hash of the emoji in the alphabet. It's written in the header so the decoder can
+    /// tell if it's using a different alphabet than the encoder did.
+    pub fn fingerprint(&self) -> u16 {
+        self.fingerprint
+    }
+}
+
+/// FNV-1a over the code points of the emoji, folded down to the size of one emoji.
+const fn fingerprint(emoji: &[char]) -> u16 {
+    let mut hash = 0x811c9dc5u32;
+
+    let mut index = 0;
+    while index < emoji.len() {
+        let bytes = (emoji[index] as u32).to_be_bytes();
+
+        let mut byte_index = 0;
+        while byte_index < bytes.len() {
+            hash = (hash ^ bytes[byte_index] as u32).wrapping_mul(0x01000193);
+            byte_index += 1;
+        }
+
+        index += 1;
+    }
+
+    let folded = hash ^ (hash >> 10) ^ (hash >> 20) ^ (hash >> 30);
+    (folded % MAX_EMOJI_VALUE as u32) as u16
+}
+
+/// Heapsort the emoji by code point, moving their values along with them. The sorting functions
+/// in `core` can't be called in a `const fn`.
+const fn sort_by_code_point(emoji: &mut [char; ALPHABET_SIZE], values: &mut [u16; ALPHABET_SIZE]) {
+    let mut start = ALPHABET_SIZE / 2;
+    while start > 0 {
+        start -= 1;
+        sift_down(emoji, values, start, ALPHABET_SIZE);
+    }
+
+    let mut end = ALPHABET_SIZE;
+    while end > 1 {
+        end -= 1;
+        swap(emoji, values, 0, end);
+        sift_down(emoji, values, 0, end);
+    }
+}
+
+const fn sift_down(
+    emoji: &mut [char; ALPHABET_SIZE],
+    values: &mut [u16; ALPHABET_SIZE],
+    mut root: usize,
+    end: usize,
+) {
+    loop {
+        let mut child = 2 * root + 1;
+        if child >= end {
+            return;
+        }
+
+        if child + 1 < end && (emoji[child] as u32) < (emoji[child + 1] as u32) {
+            child += 1;
+        }
+
+        if (emoji[root] as u32) >= (emoji[child] as u32) {
+            return;
+        }
+
+        swap(emoji, values, root, child);
+        root = child;
+    }
+}
+
+const fn swap(
+    emoji: &mut [char; ALPHABET_SIZE],
+    values: &mut [u16; ALPHABET_SIZE],
+    a: usize,
+    b: usize,
+) {
+    let c = emoji[a];
+    emoji[a] = emoji[b];
+    emoji[b] = c;
+
+    let v = values[a];
+    values[a] = values[b];
+    values[b] = v;
+}
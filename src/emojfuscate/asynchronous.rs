@@ -0,0 +1,594 @@
+use std::boxed::Box;
+use std::collections::VecDeque;
+use std::io;
+use std::pin::Pin;
+use std::string::String;
+use std::task::{ready, Context, Poll};
+use std::vec;
+
+use futures::io::{AsyncRead, AsyncWrite};
+use futures::stream::Stream;
+
+use super::alphabet::Alphabet;
+use super::bulk::EmojiPacker;
+use super::compression::Compress;
+use super::constants::ByteInSequence;
+use super::demojfuscate::{
+    ConstructFromEmoji, DecodeEmojiToBytes, Demojfuscate, DemojfuscateIterator,
+};
+use super::emojfuscate::{Emojfuscate, EncodeBytesAsEmoji};
+use super::error::FromEmojiError;
+use super::error_correction::ErrorCorrection;
+
+/// How much is read from the inner reader of an `AsyncEmojiReader` at a time.
+const READ_CHUNK_SIZE: usize = 8 * 1024;
+
+/// The async version of `Emojfuscate` for a stream of values: the values are encoded one at a
+/// time as they arrive, the same way an iterator of them would be, so the memory use doesn't grow
+/// with the length of the stream.
+pub trait EmojfuscateAsync: Stream + Sized {
+    fn emojfuscate_async_stream<J>(self) -> EncodeBytesAsEmojiStream<Self, StreamInput<J>>
+    where
+        Self::Item: Emojfuscate<J>,
+        J: Iterator<Item = ByteInSequence>,
+    {
+        EncodeBytesAsEmojiStream {
+            stream: self,
+            encoder: EncodeBytesAsEmoji::new(StreamInput {
+                started: false,
+                current: None,
+                ended: false,
+                wrote_end: false,
+            }),
+            stream_ended: false,
+        }
+    }
+}
+
+impl<S: Stream> EmojfuscateAsync for S {}
+
+/// The async version of `Demojfuscate::demojfuscate_stream`, for emoji that arrive in chunks of
+/// bytes, e.g. `String`s or `Vec<u8>`s. The chunks don't need to line up with emoji or values.
+pub trait DemojfuscateAsync: Stream + Sized {
+    fn demojfuscate_async_stream<A>(self) -> DemojfuscateStream<A, Self>
+    where
+        Self::Item: AsRef<[u8]>,
+        A: ConstructFromEmoji<A, StreamBytes>,
+    {
+        DemojfuscateStream {
+            stream: self,
+            values: DecodeEmojiToBytes::new(StreamBytes::default()).demojfuscate_stream(),
+        }
+    }
+}
+
+impl<S: Stream> DemojfuscateAsync for S {}
+
+/// The bytes of the values that have come out of the stream so far, in a sequence like the one
+/// `Emojfuscate` makes for an iterator. Running out of bytes means that the next value hasn't
+/// arrived yet, unless the stream has ended.
+pub struct StreamInput<J> {
+    started: bool,
+    current: Option<J>,
+    ended: bool,
+    wrote_end: bool,
+}
+
+impl<J> Iterator for StreamInput<J>
+where
+    J: Iterator<Item = ByteInSequence>,
+{
+    type Item = ByteInSequence;
+    fn next(&mut self) -> Option<ByteInSequence> {
+        if !self.started {
+            self.started = true;
+            return Some(ByteInSequence::SequenceStart);
+        }
+
+        if let Some(b) = self.current.as_mut().and_then(Iterator::next) {
+            return Some(b);
+        }
+
+        self.current = None;
+
+        if self.ended && !self.wrote_end {
+            self.wrote_end = true;
+            return Some(ByteInSequence::SequenceEnd);
+        }
+
+        None
+    }
+}
+
+/// The iterators that an `EncodeBytesAsEmojiStream` can be encoding, i.e. the `StreamInput`
+/// itself or the `StreamInput` compressed.
+pub trait HasStreamInput {
+    type Bytes: Iterator<Item = ByteInSequence>;
+    fn stream_input(&mut self) -> &mut StreamInput<Self::Bytes>;
+}
+
+impl<J> HasStreamInput for StreamInput<J>
+where
+    J: Iterator<Item = ByteInSequence>,
+{
+    type Bytes = J;
+    fn stream_input(&mut self) -> &mut StreamInput<J> {
+        self
+    }
+}
+
+impl<J> HasStreamInput for Compress<StreamInput<J>>
+where
+    J: Iterator<Item = ByteInSequence>,
+{
+    type Bytes = J;
+    fn stream_input(&mut self) -> &mut StreamInput<J> {
+        self.get_mut()
+    }
+}
+
+/// A stream of the emoji for the values of `S`. The emoji for a value are produced as soon as it
+/// arrives, except for the last few bits that need the next value to fill up an emoji (and the
+/// rest of the block, if there's error correction).
+pub struct EncodeBytesAsEmojiStream<S, I>
+where
+    I: Iterator<Item = ByteInSequence>,
+{
+    stream: S,
+    encoder: EncodeBytesAsEmoji<I>,
+    stream_ended: bool,
+}
+
+impl<S, I> EncodeBytesAsEmojiStream<S, I>
+where
+    I: Iterator<Item = ByteInSequence>,
+{
+    /// See `EncodeBytesAsEmoji::with_alphabet`.
+    pub fn with_alphabet(self, alphabet: &'static Alphabet) -> Self {
+        EncodeBytesAsEmojiStream {
+            encoder: self.encoder.with_alphabet(alphabet),
+            ..self
+        }
+    }
+
+    /// See `EncodeBytesAsEmoji::with_header`.
+    pub fn with_header(self) -> Self {
+        EncodeBytesAsEmojiStream {
+            encoder: self.encoder.with_header(),
+            ..self
+        }
+    }
+
+    /// See `EncodeBytesAsEmoji::with_checksum`.
+    pub fn with_checksum(self) -> Self {
+        EncodeBytesAsEmojiStream {
+            encoder: self.encoder.with_checksum(),
+            ..self
+        }
+    }
+
+    /// See `EncodeBytesAsEmoji::with_error_correction`.
+    pub fn with_error_correction(self, error_correction: ErrorCorrection) -> Self {
+        EncodeBytesAsEmojiStream {
+            encoder: self.encoder.with_error_correction(error_correction),
+            ..self
+        }
+    }
+
+    /// See `EncodeBytesAsEmoji::with_compression`.
+    pub fn with_compression(self) -> EncodeBytesAsEmojiStream<S, Compress<I>> {
+        EncodeBytesAsEmojiStream {
+            stream: self.stream,
+            encoder: self.encoder.with_compression(),
+            stream_ended: self.stream_ended,
+        }
+    }
+}
+
+// nothing is pinned in place, the stream is polled through Pin::new
+impl<S, I> Unpin for EncodeBytesAsEmojiStream<S, I>
+where
+    S: Unpin,
+    I: Iterator<Item = ByteInSequence>,
+{
+}
+
+impl<S, I> Stream for EncodeBytesAsEmojiStream<S, I>
+where
+    S: Stream + Unpin,
+    S::Item: Emojfuscate<I::Bytes>,
+    I: Iterator<Item = ByteInSequence> + HasStreamInput,
+{
+    type Item = char;
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<char>> {
+        let this = self.get_mut();
+
+        loop {
+            if this.stream_ended {
+                return Poll::Ready(this.encoder.next());
+            }
+
+            if let Some(emoji) = this.encoder.next_available() {
+                return Poll::Ready(Some(emoji));
+            }
+
+            match ready!(Pin::new(&mut this.stream).poll_next(cx)) {
+                Some(value) => {
+                    this.encoder.get_mut().stream_input().current =
+                        Some(value.emojfuscate_stream().into_inner());
+                }
+                None => {
+                    this.stream_ended = true;
+                    this.encoder.get_mut().stream_input().ended = true;
+                }
+            }
+        }
+    }
+}
+
+/// The bytes that have arrived but haven't been decoded yet. Only whole UTF-8 characters are
+/// handed out until the input has ended, so the decoder never sees half an emoji just because the
+/// rest of it is still on its way. Running out of bytes before the end is recorded as `starved`.
+///
+/// The bytes that have been handed out are kept until they're committed, so decoding can be
+/// rewound to try again once more bytes have arrived.
+#[derive(Clone, Default)]
+pub struct StreamBytes {
+    buffer: VecDeque<u8>,
+    read: usize,
+    // the number of bytes at the front of the buffer that make up whole characters
+    complete: usize,
+    ended: bool,
+    starved: bool,
+}
+
+impl StreamBytes {
+    fn push(&mut self, chunk: &[u8]) {
+        self.buffer.extend(chunk);
+        self.complete = self.buffer.len();
+
+        // hold back a character that is cut off at the end, see `decode_emoji_value` for how the
+        // length of a character is found
+        for back in 1..=std::cmp::min(3, self.buffer.len()) {
+            let b = self.buffer[self.buffer.len() - back];
+
+            if b & 0b1100_0000 != 0b1000_0000 {
+                if b.leading_ones() as usize > back {
+                    self.complete -= back;
+                }
+                break;
+            }
+        }
+    }
+
+    fn end(&mut self) {
+        self.ended = true;
+        self.complete = self.buffer.len();
+    }
+
+    /// Forget the bytes that have been handed out.
+    fn commit(&mut self) {
+        self.buffer.drain(..self.read);
+        self.complete -= self.read;
+        self.read = 0;
+    }
+
+    /// Hand out the bytes again, starting from the last commit.
+    fn rewind(&mut self) {
+        self.read = 0;
+        self.starved = false;
+    }
+}
+
+impl Iterator for StreamBytes {
+    type Item = u8;
+    fn next(&mut self) -> Option<u8> {
+        if self.read == self.complete {
+            self.starved = !self.ended;
+            return None;
+        }
+
+        self.read += 1;
+        Some(self.buffer[self.read - 1])
+    }
+}
+
+/// A stream of the values that are decoded from `S`. A value is only produced once all of its
+/// emoji have arrived, until then the emoji are buffered. Decoding the value starts over every
+/// time a chunk arrives, so values that are split across many chunks take longer to decode.
+pub struct DemojfuscateStream<A, S>
+where
+    A: ConstructFromEmoji<A, StreamBytes>,
+{
+    stream: S,
+    values: DemojfuscateIterator<A, StreamBytes>,
+}
+
+impl<A, S> DemojfuscateStream<A, S>
+where
+    A: ConstructFromEmoji<A, StreamBytes>,
+{
+    /// See `DecodeEmojiToBytes::with_alphabet`.
+    pub fn with_alphabet(self, alphabet: &'static Alphabet) -> Self {
+        self.map_decoder(|decoder| decoder.with_alphabet(alphabet))
+    }
+
+    /// See `DecodeEmojiToBytes::with_error_correction`.
+    pub fn with_error_correction(self, error_correction: ErrorCorrection) -> Self {
+        self.map_decoder(|decoder| decoder.with_error_correction(error_correction))
+    }
+
+    /// See `DecodeEmojiToBytes::with_compression`.
+    pub fn with_compression(self) -> Self {
+        self.map_decoder(|decoder| decoder.with_compression())
+    }
+
+    /// See `DemojfuscateIterator::recover_from_errors`.
+    pub fn recover_from_errors(self) -> Self {
+        DemojfuscateStream {
+            values: self.values.recover_from_errors(),
+            ..self
+        }
+    }
+
+    /// See `DemojfuscateIterator::join_midway`.
+    pub fn join_midway(self) -> Self {
+        DemojfuscateStream {
+            values: self.values.join_midway(),
+            ..self
+        }
+    }
+
+    fn map_decoder<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(DecodeEmojiToBytes<StreamBytes>) -> DecodeEmojiToBytes<StreamBytes>,
+    {
+        let decoder = self.values.get_mut();
+        let placeholder = DecodeEmojiToBytes::new(StreamBytes::default());
+        *decoder = f(std::mem::replace(decoder, placeholder));
+        self
+    }
+}
+
+// nothing is pinned in place, the stream is polled through Pin::new
+impl<A, S> Unpin for DemojfuscateStream<A, S>
+where
+    A: ConstructFromEmoji<A, StreamBytes>,
+    S: Unpin,
+{
+}
+
+impl<A, S> Stream for DemojfuscateStream<A, S>
+where
+    A: ConstructFromEmoji<A, StreamBytes>,
+    S: Stream + Unpin,
+    S::Item: AsRef<[u8]>,
+{
+    type Item = Result<A, FromEmojiError>;
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<A, FromEmojiError>>> {
+        let this = self.get_mut();
+
+        loop {
+            // The value might only have partly arrived. The state of the decoder is saved so it can
+            // start over once more has arrived, the bytes are taken out so they aren't copied.
+            let mut bytes = std::mem::take(this.values.get_mut().get_mut());
+            let saved = this.values.clone();
+            *this.values.get_mut().get_mut() = bytes;
+
+            let next = this.values.next();
+
+            bytes = std::mem::take(this.values.get_mut().get_mut());
+
+            if !bytes.starved {
+                bytes.commit();
+                *this.values.get_mut().get_mut() = bytes;
+                return Poll::Ready(next);
+            }
+
+            bytes.rewind();
+            this.values = saved;
+            *this.values.get_mut().get_mut() = bytes;
+
+            match ready!(Pin::new(&mut this.stream).poll_next(cx)) {
+                Some(chunk) => this.values.get_mut().get_mut().push(chunk.as_ref()),
+                None => this.values.get_mut().get_mut().end(),
+            }
+        }
+    }
+}
+
+/// The async version of `EmojiWriter`. Like `EmojiWriter`, up to a byte of data can be waiting
+/// for more bits to fill up an emoji, which `poll_flush` writes with padding. There's no way to
+/// do that when the writer is dropped, so make sure to flush or close it.
+pub struct AsyncEmojiWriter<W: AsyncWrite> {
+    inner: W,
+    packer: EmojiPacker,
+    // emoji that the inner writer hasn't taken yet
+    encoded: String,
+    written: usize,
+}
+
+impl<W: AsyncWrite> AsyncEmojiWriter<W> {
+    pub fn new(inner: W) -> Self {
+        AsyncEmojiWriter {
+            inner,
+            packer: EmojiPacker::new(),
+            encoded: String::new(),
+            written: 0,
+        }
+    }
+
+    /// Write the emoji from a different emoji table than the default one.
+    pub fn with_alphabet(mut self, alphabet: &'static Alphabet) -> Self {
+        self.packer.set_alphabet(alphabet);
+        self
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncEmojiWriter<W> {
+    /// Write the emoji that were encoded earlier.
+    fn poll_write_encoded(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.written < self.encoded.len() {
+            let buf = &self.encoded.as_bytes()[self.written..];
+            let written = ready!(Pin::new(&mut self.inner).poll_write(cx, buf))?;
+
+            if written == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+
+            self.written += written;
+        }
+
+        self.encoded.clear();
+        self.written = 0;
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for AsyncEmojiWriter<W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        // the emoji for the previous write have to be out of the way first, so that at most one
+        // write worth of emoji is kept in memory
+        ready!(this.poll_write_encoded(cx))?;
+
+        this.packer.pack(buf, &mut this.encoded);
+
+        // the bytes are taken either way, the emoji that don't fit are written on the next call
+        if let Poll::Ready(Err(err)) = this.poll_write_encoded(cx) {
+            return Poll::Ready(Err(err));
+        }
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        ready!(this.poll_write_encoded(cx))?;
+        this.packer.pad(&mut this.encoded);
+        ready!(this.poll_write_encoded(cx))?;
+
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.as_mut().poll_flush(cx))?;
+        Pin::new(&mut self.inner).poll_close(cx)
+    }
+}
+
+/// The async version of `EmojiReader`. Emoji that can't be decoded are reported as
+/// `io::ErrorKind::InvalidData`, with the `FromEmojiError` as the inner error.
+pub struct AsyncEmojiReader<R: AsyncRead> {
+    inner: R,
+    decoder: DecodeEmojiToBytes<StreamBytes>,
+    chunk: Box<[u8]>,
+    // whether any emoji have arrived yet, so a header isn't missed by looking for it too early
+    received: bool,
+    error: Option<io::Error>,
+}
+
+impl<R: AsyncRead> AsyncEmojiReader<R> {
+    pub fn new(inner: R) -> Self {
+        AsyncEmojiReader {
+            inner,
+            decoder: DecodeEmojiToBytes::new(StreamBytes::default()),
+            chunk: vec![0; READ_CHUNK_SIZE].into_boxed_slice(),
+            received: false,
+            error: None,
+        }
+    }
+
+    /// Read emoji from a different emoji table than the default one.
+    pub fn with_alphabet(self, alphabet: &'static Alphabet) -> Self {
+        AsyncEmojiReader {
+            decoder: self.decoder.with_alphabet(alphabet),
+            ..self
+        }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for AsyncEmojiReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        if let Some(err) = this.error.take() {
+            return Poll::Ready(Err(err));
+        }
+
+        loop {
+            let mut read = 0;
+
+            while this.received && read < buf.len() {
+                let err = match this.decoder.next() {
+                    Some(Ok(ByteInSequence::Byte(b))) => {
+                        buf[read] = b;
+                        read += 1;
+                        continue;
+                    }
+                    Some(Ok(found)) => this.decoder.unexpected("a byte", Some(found)),
+                    Some(Err(err)) => err,
+                    None => break,
+                };
+
+                let err = io::Error::new(io::ErrorKind::InvalidData, err);
+
+                if read == 0 {
+                    return Poll::Ready(Err(err));
+                }
+
+                // the bytes before the error are returned first
+                this.error = Some(err);
+                break;
+            }
+
+            // a byte is never decoded from half an emoji, so nothing needs to be rewound
+            this.decoder.get_mut().commit();
+
+            if read > 0 || buf.is_empty() || this.decoder.get_ref().ended {
+                return Poll::Ready(Ok(read));
+            }
+
+            let received = ready!(Pin::new(&mut this.inner).poll_read(cx, &mut this.chunk))?;
+            let bytes = this.decoder.get_mut();
+
+            if received == 0 {
+                bytes.end();
+            } else {
+                bytes.push(&this.chunk[..received]);
+            }
+
+            this.received |= bytes.complete > 0 || bytes.ended;
+        }
+    }
+}
@@ -0,0 +1,280 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use arrayvec::ArrayString;
+
+use super::alphabet::{Alphabet, WHITE_SPACE_CHARS};
+use super::constants::{
+    ByteInSequence, BITS_IN_A_BYTE, BITS_PER_EMOJI, CHECKSUM_EMOJI_VALUE, HEADER_EMOJI_VALUE,
+    MAX_EMOJI_VALUE, START_EMOJI_VALUE, STOP_EMOJI_VALUE, SYNC_EMOJI_VALUE,
+};
+use super::error::{FromEmojiError, FromEmojiErrorKind, Token};
+
+/// Five bytes are exactly four emoji, so blocks of them can be converted without keeping track of
+/// any leftover bits.
+const BYTES_PER_BLOCK: usize = 5;
+const EMOJI_PER_BLOCK: usize = 4;
+
+/// Append the emoji for `bytes` to `encoded`. This produces the same emoji as
+/// `EmojfuscateByteStream::emojfuscate_byte_stream_no_start_or_stop`, but it works on whole blocks
+/// of bytes at a time instead of going through the iterators, which is a lot faster for big slices.
+///
+/// ```
+/// use emojfuscate::{decode_str_into, encode_slice_into, EmojfuscateByteStream};
+///
+/// let mut emojified = String::new();
+/// encode_slice_into(b"hello", &mut emojified);
+///
+/// let expected: String = b"hello"
+///     .iter()
+///     .copied()
+///     .emojfuscate_byte_stream_no_start_or_stop()
+///     .collect();
+/// assert_eq!(emojified, expected);
+///
+/// let mut decoded = Vec::new();
+/// decode_str_into(&emojified, &mut decoded).unwrap();
+/// assert_eq!(decoded, b"hello");
+/// ```
+pub fn encode_slice_into(bytes: &[u8], encoded: &mut String) {
+    encode_slice_into_with_alphabet(bytes, Alphabet::default_alphabet(), encoded);
+}
+
+/// Like `encode_slice_into`, but with a different emoji table than the default one.
+pub fn encode_slice_into_with_alphabet(
+    bytes: &[u8],
+    alphabet: &'static Alphabet,
+    encoded: &mut String,
+) {
+    let mut packer = EmojiPacker::new();
+    packer.set_alphabet(alphabet);
+    packer.pack(bytes, encoded);
+    packer.pad(encoded);
+}
+
+/// Append the bytes that `emoji` were made from to `decoded`, i.e. it undoes `encode_slice_into`.
+/// White space is skipped like everywhere else. Only bytes are allowed, so emoji with a header,
+/// a checksum or sequences in them need to go through `DecodeEmojiToBytes` instead.
+///
+/// If the emoji can't be decoded the bytes before the error have already been added to `decoded`.
+pub fn decode_str_into(emoji: &str, decoded: &mut Vec<u8>) -> Result<(), FromEmojiError> {
+    decode_str_into_with_alphabet(emoji, Alphabet::default_alphabet(), decoded)
+}
+
+/// Like `decode_str_into`, but with a different emoji table than the default one.
+pub fn decode_str_into_with_alphabet(
+    emoji: &str,
+    alphabet: &'static Alphabet,
+    decoded: &mut Vec<u8>,
+) -> Result<(), FromEmojiError> {
+    const EXPECTED: &str = "a byte";
+
+    // the emoji of the default alphabet are four bytes of UTF-8 each
+    decoded.reserve(emoji.len() / 16 * BYTES_PER_BLOCK);
+
+    let mut chars = emoji.char_indices();
+    let mut accumulated_data: u32 = 0;
+    let mut defined_bits: u16 = 0;
+    let mut bits_to_truncate: u16 = 0;
+    let mut emoji_offset = 0;
+
+    loop {
+        if defined_bits == 0 && bits_to_truncate == 0 {
+            let mut block_chars = chars.clone();
+
+            if let Some(block) = read_block(&mut block_chars, alphabet) {
+                decoded.extend_from_slice(&block.to_be_bytes()[8 - BYTES_PER_BLOCK..]);
+                chars = block_chars;
+                emoji_offset += EMOJI_PER_BLOCK;
+                continue;
+            }
+        }
+
+        let (byte_offset, c) = match chars.next() {
+            Some(next) => next,
+            None => return Ok(()),
+        };
+
+        let error = |kind| FromEmojiError {
+            emoji_offset,
+            byte_offset,
+            ..FromEmojiError::new(kind)
+        };
+
+        let value = match alphabet.emoji_to_value(c) {
+            Some(value) => value,
+            None if WHITE_SPACE_CHARS.contains(&c) => continue,
+            None => return Err(error(FromEmojiErrorKind::InputIsNotAnEmoji(c))),
+        };
+
+        let found = match value {
+            START_EMOJI_VALUE => ByteInSequence::SequenceStart,
+            STOP_EMOJI_VALUE => ByteInSequence::SequenceEnd,
+            SYNC_EMOJI_VALUE => ByteInSequence::SyncMarker,
+            HEADER_EMOJI_VALUE | CHECKSUM_EMOJI_VALUE => {
+                return Err(error(FromEmojiErrorKind::UnexpectedInput)
+                    .expected(EXPECTED, Token::Emoji(value)))
+            }
+            // says how many bits to drop from the emoji after it, at the end of the data
+            _ if value >= MAX_EMOJI_VALUE => {
+                bits_to_truncate = value - MAX_EMOJI_VALUE;
+                emoji_offset += 1;
+                continue;
+            }
+            _ => {
+                accumulated_data =
+                    ((accumulated_data << BITS_PER_EMOJI) | u32::from(value)) >> bits_to_truncate;
+                defined_bits += BITS_PER_EMOJI - bits_to_truncate;
+                bits_to_truncate = 0;
+
+                while defined_bits >= BITS_IN_A_BYTE {
+                    defined_bits -= BITS_IN_A_BYTE;
+                    decoded.push((accumulated_data >> defined_bits) as u8);
+                    accumulated_data &= (1 << defined_bits) - 1;
+                }
+
+                emoji_offset += 1;
+                continue;
+            }
+        };
+
+        let kind = match found {
+            ByteInSequence::SequenceStart => FromEmojiErrorKind::UnexpectedSequenceStart,
+            ByteInSequence::SequenceEnd => FromEmojiErrorKind::UnexpectedSequenceEnd,
+            _ => FromEmojiErrorKind::UnexpectedSyncMarker,
+        };
+
+        return Err(error(kind).expected(EXPECTED, Token::from(Some(found))));
+    }
+}
+
+/// The 40 bits of the next four emoji, or `None` if they aren't all data emoji.
+fn read_block<I>(chars: &mut I, alphabet: &Alphabet) -> Option<u64>
+where
+    I: Iterator<Item = (usize, char)>,
+{
+    let mut block = 0;
+
+    for _ in 0..EMOJI_PER_BLOCK {
+        let (_, c) = chars.next()?;
+
+        match alphabet.emoji_to_value(c) {
+            Some(value) if value < MAX_EMOJI_VALUE => {
+                block = (block << BITS_PER_EMOJI) | u64::from(value);
+            }
+            _ => return None,
+        }
+    }
+
+    Some(block)
+}
+
+/// Somewhere that packed emoji can be put, so that emoji can be packed into an `ArrayString`
+/// without allocating as well as into a `String`.
+pub trait EmojiSink {
+    fn push_emoji(&mut self, emoji: char);
+
+    fn reserve_emoji(&mut self, _bytes: usize) {}
+}
+
+impl EmojiSink for String {
+    fn push_emoji(&mut self, emoji: char) {
+        self.push(emoji);
+    }
+
+    fn reserve_emoji(&mut self, bytes: usize) {
+        self.reserve(bytes);
+    }
+}
+
+/// Panics if the emoji don't fit.
+impl<const CAP: usize> EmojiSink for ArrayString<CAP> {
+    fn push_emoji(&mut self, emoji: char) {
+        self.push(emoji);
+    }
+}
+
+/// Packs bytes into emoji for the writers and the bulk functions, which get their data in pieces
+/// rather than from an iterator.
+pub struct EmojiPacker {
+    alphabet: &'static Alphabet,
+    input_data: u32,
+    defined_bits: u16,
+}
+
+impl EmojiPacker {
+    pub fn new() -> Self {
+        EmojiPacker {
+            alphabet: Alphabet::default_alphabet(),
+            input_data: 0,
+            defined_bits: 0,
+        }
+    }
+
+    pub fn set_alphabet(&mut self, alphabet: &'static Alphabet) {
+        self.alphabet = alphabet;
+    }
+
+    /// Add the emoji for `buf` to `encoded`. The bits that don't fill up an emoji are kept for the
+    /// next call.
+    pub fn pack<S: EmojiSink>(&mut self, buf: &[u8], encoded: &mut S) {
+        // emoji are at most 4 bytes and there are fewer emoji than bytes
+        encoded.reserve_emoji(buf.len() * 4);
+
+        let mut rest = buf;
+
+        while let Some((b, after)) = rest.split_first() {
+            // whole blocks can be converted directly when there are no bits waiting
+            if self.defined_bits == 0 && rest.len() >= BYTES_PER_BLOCK {
+                let (block, after) = rest.split_at(BYTES_PER_BLOCK);
+                self.pack_block(block, encoded);
+                rest = after;
+                continue;
+            }
+
+            self.input_data = (self.input_data << BITS_IN_A_BYTE) | u32::from(*b);
+            self.defined_bits += BITS_IN_A_BYTE;
+            rest = after;
+
+            if self.defined_bits < BITS_PER_EMOJI {
+                continue;
+            }
+
+            let bits_used = self.defined_bits - BITS_PER_EMOJI;
+            let emoji_index = self.input_data >> bits_used;
+
+            // remove the used bits
+            self.input_data ^= emoji_index << bits_used;
+            self.defined_bits -= BITS_PER_EMOJI;
+
+            encoded.push_emoji(self.alphabet.value_to_emoji(emoji_index as u16));
+        }
+    }
+
+    fn pack_block<S: EmojiSink>(&self, block: &[u8], encoded: &mut S) {
+        let bits = block
+            .iter()
+            .fold(0u64, |bits, b| (bits << BITS_IN_A_BYTE) | u64::from(*b));
+
+        for index in (0..EMOJI_PER_BLOCK).rev() {
+            let value = (bits >> (index * usize::from(BITS_PER_EMOJI))) as u16;
+            encoded.push_emoji(self.alphabet.value_to_emoji(value & (MAX_EMOJI_VALUE - 1)));
+        }
+    }
+
+    /// Add the bits that are waiting for a full emoji to `encoded`, preceded by the emoji that says
+    /// how many bits of padding were added.
+    pub fn pad<S: EmojiSink>(&mut self, encoded: &mut S) {
+        if self.defined_bits == 0 {
+            return;
+        }
+
+        let padding = BITS_PER_EMOJI - self.defined_bits;
+        let final_value = (self.input_data << padding) as u16;
+
+        encoded.push_emoji(self.alphabet.value_to_emoji(MAX_EMOJI_VALUE + padding));
+        encoded.push_emoji(self.alphabet.value_to_emoji(final_value));
+
+        self.input_data = 0;
+        self.defined_bits = 0;
+    }
+}
@@ -0,0 +1,57 @@
+/// The number of emoji after the checksum emoji, each one holds one byte of the CRC-32.
+pub const CHECKSUM_LENGTH: usize = 4;
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut index = 0;
+
+    while index < 256 {
+        let mut crc = index as u32;
+        let mut bit = 0;
+
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB88320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+
+        table[index] = crc;
+        index += 1;
+    }
+
+    table
+};
+
+/// A CRC-32 (the same one as zip and PNG uses) that is calculated over the values of the emoji
+/// rather than the data they represent. That way it also catches broken start/stop and padding
+/// emoji.
+#[derive(Copy, Clone)]
+pub struct Checksum {
+    crc: u32,
+}
+
+impl Checksum {
+    pub fn new() -> Self {
+        Checksum { crc: 0xFFFFFFFF }
+    }
+
+    pub fn update(&mut self, emoji_value: u16) {
+        for byte in emoji_value.to_be_bytes() {
+            let index = (self.crc ^ u32::from(byte)) & 0xFF;
+            self.crc = (self.crc >> 8) ^ CRC32_TABLE[index as usize];
+        }
+    }
+
+    /// The values of the emoji that follow the checksum emoji.
+    pub fn to_emoji_values(self) -> [u16; CHECKSUM_LENGTH] {
+        (self.crc ^ 0xFFFFFFFF).to_be_bytes().map(u16::from)
+    }
+
+    /// Check the emoji that followed the checksum emoji against what we have calculated.
+    pub fn matches(self, emoji_values: [u16; CHECKSUM_LENGTH]) -> bool {
+        self.to_emoji_values() == emoji_values
+    }
+}
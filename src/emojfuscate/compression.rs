@@ -0,0 +1,315 @@
+use alloc::collections::VecDeque;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use super::constants::ByteInSequence;
+use super::error::FromEmojiErrorKind;
+
+// The compressed data is a series of tokens, each one starting with a tag byte:
+//
+// 0x00..=0x7F  a run of 1 to 128 literal bytes follows
+// 0x80..=0xFD  a copy of 3 to 128 bytes from earlier in the data, the distance back to where the
+//              copy starts follows as two big endian bytes
+// 0xFE         the start of a sequence
+// 0xFF         the end of a sequence
+const MAX_LITERAL_TAG: u8 = 0x7F;
+const MAX_COPY_TAG: u8 = 0xFD;
+const SEQUENCE_START_TAG: u8 = 0xFE;
+const SEQUENCE_END_TAG: u8 = 0xFF;
+
+const MAX_LITERAL_RUN: usize = MAX_LITERAL_TAG as usize + 1;
+const MIN_COPY_LENGTH: usize = 3;
+const MAX_COPY_LENGTH: usize = MIN_COPY_LENGTH + (MAX_COPY_TAG - MAX_LITERAL_TAG - 1) as usize;
+
+/// How far back a copy can reach. This (along with the hash table) is all the memory that is
+/// needed, no matter how much data is compressed.
+const WINDOW_SIZE: usize = 4096;
+const HASH_SIZE: usize = 4096;
+
+/// How many earlier occurrences of the same three bytes are checked when looking for the longest
+/// copy. Higher is slower but compresses better.
+const MAX_CHAIN_LENGTH: usize = 16;
+
+/// An LZ77 style compressor that sits between the data and the encoder. The start and end of
+/// sequences are encoded as tokens in the compressed data, so the result is just a stream of bytes
+/// and sync markers. Nothing after a sync marker refers back to data before it, so decompression
+/// can start from any sync marker.
+pub struct Compress<I>
+where
+    I: Iterator<Item = ByteInSequence>,
+{
+    iter: I,
+    lookahead: VecDeque<u8>,
+    pending_marker: Option<ByteInSequence>,
+    literals: Vec<u8>,
+    output: VecDeque<ByteInSequence>,
+    window: Vec<u8>,
+    position: usize,
+    // positions are stored plus one so that zero can mean that there is no earlier position
+    hash_heads: Vec<usize>,
+    previous_positions: Vec<usize>,
+}
+
+impl<I> Compress<I>
+where
+    I: Iterator<Item = ByteInSequence>,
+{
+    pub fn new(iter: I) -> Self {
+        Compress {
+            iter,
+            lookahead: VecDeque::with_capacity(MAX_COPY_LENGTH),
+            pending_marker: None,
+            literals: Vec::with_capacity(MAX_LITERAL_RUN),
+            output: VecDeque::new(),
+            window: vec![0; WINDOW_SIZE],
+            position: 0,
+            hash_heads: vec![0; HASH_SIZE],
+            previous_positions: vec![0; WINDOW_SIZE],
+        }
+    }
+
+    pub fn get_mut(&mut self) -> &mut I {
+        &mut self.iter
+    }
+
+    /// Read bytes until there is enough to find the longest possible copy, or until the start or
+    /// end of a sequence is reached. Running out of bytes isn't treated as the end for good, so
+    /// compression carries on if the underlying iterator has more bytes later.
+    fn fill_lookahead(&mut self) {
+        while self.lookahead.len() < MAX_COPY_LENGTH && self.pending_marker.is_none() {
+            match self.iter.next() {
+                Some(ByteInSequence::Byte(b)) => self.lookahead.push_back(b),
+                Some(marker) => self.pending_marker = Some(marker),
+                None => break,
+            }
+        }
+    }
+
+    /// Produce the next token(s). Returns false if there is nothing more to compress.
+    fn compress_next(&mut self) -> bool {
+        self.fill_lookahead();
+
+        if self.lookahead.is_empty() {
+            self.flush_literals();
+
+            match self.pending_marker.take() {
+                Some(ByteInSequence::SequenceStart) => self.push_output(SEQUENCE_START_TAG),
+                Some(ByteInSequence::SequenceEnd) => self.push_output(SEQUENCE_END_TAG),
+                Some(ByteInSequence::SyncMarker) => {
+                    self.hash_heads.fill(0);
+                    self.position = 0;
+                    self.output.push_back(ByteInSequence::SyncMarker);
+                }
+                Some(ByteInSequence::Byte(_)) | None => return !self.output.is_empty(),
+            }
+
+            return true;
+        }
+
+        let (distance, length) = self.longest_copy();
+
+        if length >= MIN_COPY_LENGTH {
+            self.flush_literals();
+            self.push_output(MAX_LITERAL_TAG + 1 + (length - MIN_COPY_LENGTH) as u8);
+
+            for b in (distance as u16).to_be_bytes() {
+                self.push_output(b);
+            }
+
+            for _ in 0..length {
+                self.advance();
+            }
+        } else {
+            let b = self.advance();
+            self.literals.push(b);
+
+            if self.literals.len() == MAX_LITERAL_RUN {
+                self.flush_literals();
+            }
+        }
+
+        true
+    }
+
+    fn flush_literals(&mut self) {
+        if self.literals.is_empty() {
+            return;
+        }
+
+        self.push_output((self.literals.len() - 1) as u8);
+        self.output
+            .extend(self.literals.drain(..).map(ByteInSequence::Byte));
+    }
+
+    fn push_output(&mut self, b: u8) {
+        self.output.push_back(ByteInSequence::Byte(b));
+    }
+
+    /// Move the first byte of the lookahead into the window.
+    fn advance(&mut self) -> u8 {
+        if self.lookahead.len() >= MIN_COPY_LENGTH {
+            let hash = hash(self.lookahead[0], self.lookahead[1], self.lookahead[2]);
+            self.previous_positions[self.position % WINDOW_SIZE] = self.hash_heads[hash];
+            self.hash_heads[hash] = self.position + 1;
+        }
+
+        let b = self.lookahead.pop_front().unwrap();
+        self.window[self.position % WINDOW_SIZE] = b;
+        self.position += 1;
+
+        b
+    }
+
+    fn byte_at(&self, position: usize) -> u8 {
+        if position < self.position {
+            self.window[position % WINDOW_SIZE]
+        } else {
+            self.lookahead[position - self.position]
+        }
+    }
+
+    /// Find the longest earlier occurrence of the start of the lookahead. Returns the distance back
+    /// to it and its length.
+    fn longest_copy(&self) -> (usize, usize) {
+        if self.lookahead.len() < MIN_COPY_LENGTH {
+            return (0, 0);
+        }
+
+        let hash = hash(self.lookahead[0], self.lookahead[1], self.lookahead[2]);
+        let mut candidate = self.hash_heads[hash];
+        let mut best = (0, 0);
+
+        for _ in 0..MAX_CHAIN_LENGTH {
+            if candidate == 0 || self.position - (candidate - 1) > WINDOW_SIZE {
+                break;
+            }
+
+            let start = candidate - 1;
+            let length = (0..self.lookahead.len())
+                .take_while(|offset| self.byte_at(start + offset) == self.lookahead[*offset])
+                .count();
+
+            if length > best.1 {
+                best = (self.position - start, length);
+            }
+
+            candidate = self.previous_positions[start % WINDOW_SIZE];
+        }
+
+        best
+    }
+}
+
+impl<I> Iterator for Compress<I>
+where
+    I: Iterator<Item = ByteInSequence>,
+{
+    type Item = ByteInSequence;
+    fn next(&mut self) -> Option<ByteInSequence> {
+        while self.output.is_empty() {
+            if !self.compress_next() {
+                return None;
+            }
+        }
+
+        self.output.pop_front()
+    }
+}
+
+fn hash(b0: u8, b1: u8, b2: u8) -> usize {
+    ((usize::from(b0) << 8) ^ (usize::from(b1) << 4) ^ usize::from(b2)) % HASH_SIZE
+}
+
+#[derive(Clone)]
+enum DecompressState {
+    Tag,
+    Literals(usize),
+    DistanceHigh(usize),
+    DistanceLow(usize, u8),
+}
+
+/// Undoes `Compress`, one byte of compressed data at a time.
+#[derive(Clone)]
+pub struct Decompressor {
+    state: DecompressState,
+    window: Vec<u8>,
+    position: usize,
+    copy_distance: usize,
+    copy_remaining: usize,
+}
+
+impl Decompressor {
+    pub fn new() -> Self {
+        Decompressor {
+            state: DecompressState::Tag,
+            window: vec![0; WINDOW_SIZE],
+            position: 0,
+            copy_distance: 0,
+            copy_remaining: 0,
+        }
+    }
+
+    /// Whether the compressed data can end here, i.e. it's not in the middle of a token.
+    pub fn at_token_boundary(&self) -> bool {
+        matches!(self.state, DecompressState::Tag) && self.copy_remaining == 0
+    }
+
+    /// The next byte of an ongoing copy, these don't need any more compressed data.
+    pub fn next_copied_byte(&mut self) -> Option<u8> {
+        if self.copy_remaining == 0 {
+            return None;
+        }
+
+        self.copy_remaining -= 1;
+        let b = self.window[(self.position - self.copy_distance) % WINDOW_SIZE];
+        self.write_to_window(b);
+
+        Some(b)
+    }
+
+    /// Feed one byte of compressed data, which might produce a byte of decompressed data or the
+    /// start/end of a sequence.
+    pub fn decompress(&mut self, b: u8) -> Result<Option<ByteInSequence>, FromEmojiErrorKind> {
+        match self.state {
+            DecompressState::Tag => match b {
+                SEQUENCE_START_TAG => return Ok(Some(ByteInSequence::SequenceStart)),
+                SEQUENCE_END_TAG => return Ok(Some(ByteInSequence::SequenceEnd)),
+                0..=MAX_LITERAL_TAG => self.state = DecompressState::Literals(usize::from(b) + 1),
+                _ => {
+                    let length = usize::from(b - MAX_LITERAL_TAG - 1) + MIN_COPY_LENGTH;
+                    self.state = DecompressState::DistanceHigh(length);
+                }
+            },
+            DecompressState::Literals(remaining) => {
+                self.state = match remaining - 1 {
+                    0 => DecompressState::Tag,
+                    remaining => DecompressState::Literals(remaining),
+                };
+
+                self.write_to_window(b);
+                return Ok(Some(ByteInSequence::Byte(b)));
+            }
+            DecompressState::DistanceHigh(length) => {
+                self.state = DecompressState::DistanceLow(length, b);
+            }
+            DecompressState::DistanceLow(length, high) => {
+                let distance = usize::from(u16::from_be_bytes([high, b]));
+                self.state = DecompressState::Tag;
+
+                if distance == 0 || distance > WINDOW_SIZE || distance > self.position {
+                    return Err(FromEmojiErrorKind::InvalidCompressedData);
+                }
+
+                self.copy_distance = distance;
+                self.copy_remaining = length;
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn write_to_window(&mut self, b: u8) {
+        self.window[self.position % WINDOW_SIZE] = b;
+        self.position += 1;
+    }
+}
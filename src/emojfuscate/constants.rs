@@ -1,44 +1,35 @@
-use lazy_static::lazy_static;
-use std::collections::HashMap;
-
 pub const BITS_IN_A_BYTE: u16 = 8;
 pub const BITS_PER_EMOJI: u16 = 10;
 pub const MAX_EMOJI_VALUE: u16 = u16::pow(2, BITS_PER_EMOJI as u32);
 pub const START_EMOJI_VALUE: u16 = MAX_EMOJI_VALUE + BITS_PER_EMOJI + 1;
 pub const STOP_EMOJI_VALUE: u16 = MAX_EMOJI_VALUE + BITS_PER_EMOJI + 2;
+pub const HEADER_EMOJI_VALUE: u16 = MAX_EMOJI_VALUE + BITS_PER_EMOJI + 3;
+pub const CHECKSUM_EMOJI_VALUE: u16 = MAX_EMOJI_VALUE + BITS_PER_EMOJI + 4;
+pub const SYNC_EMOJI_VALUE: u16 = MAX_EMOJI_VALUE + BITS_PER_EMOJI + 5;
+
+/// The number of distinct emoji an alphabet needs: one for every 10 bit value, the ones used to
+/// signal padding at the end of a message, the start/stop emoji, the header and checksum emoji and
+/// the sync marker.
+pub const ALPHABET_SIZE: usize = SYNC_EMOJI_VALUE as usize + 1;
+
+/// The revision of the format that is announced in the header. It needs to be bumped whenever a
+/// change is made that makes old messages decode differently.
+pub const FORMAT_VERSION: u16 = 2;
 
-#[derive(Copy, Clone)]
+/// How many values of recursive types can be inside of each other before decoding them is a
+/// `TooDeeplyNested` error, unless the decoder is given another limit with `with_nesting_limit`.
+/// It's deep enough for any sensible value and shallow enough for the stack of a thread in a debug
+/// build. Encoding doesn't check it, so values nested deeper than this encode fine but can only be
+/// decoded with a higher limit.
+pub const DEFAULT_NESTING_LIMIT: usize = 128;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum ByteInSequence {
     SequenceStart,
     Byte(u8),
     SequenceEnd,
-}
-
-// This code isn't dead at all, I'm not sure why Rust is complaining about it
-#[allow(dead_code)]
-pub fn usize_to_emoji(u: usize) -> char {
-    let emoji_unicode = EMOJI[u];
-    return char::from_u32(emoji_unicode).unwrap();
-}
-
-const EMOJI_VALUE_TUPLES: [(char, u16); 1385] = {
-    let mut emoji_value_pairs = [('a', 0); 1385];
-    let mut index = 0;
-
-    while index < EMOJI.len() {
-        emoji_value_pairs[index] = match char::from_u32(EMOJI[index]) {
-            Some(c) => (c, index as u16),
-            None => panic!("unexpected unicode"),
-        };
-
-        index = index + 1;
-    }
-
-    emoji_value_pairs
-};
-
-lazy_static! {
-    pub static ref EMOJI_VALUES: HashMap<char, u16> = HashMap::from(EMOJI_VALUE_TUPLES);
+    /// A point between two values in a stream where the decoder can pick up again after an error.
+    SyncMarker,
 }
 
 pub const EMOJI: [u32; 1385] = [
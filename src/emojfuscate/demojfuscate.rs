@@ -1,12 +1,26 @@
 use crate::util::iterator_wrapper::IteratorWrapper;
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 use arrayvec::ArrayVec;
+use core::str;
 use paste::paste;
-use std::str;
 use uuid::Uuid;
 
+use super::alphabet::{Alphabet, WHITE_SPACE_CHARS};
+use super::checksum::{Checksum, CHECKSUM_LENGTH};
+use super::compression::Decompressor;
 use super::constants::{
-    ByteInSequence, BITS_IN_A_BYTE, BITS_PER_EMOJI, EMOJI_VALUES, MAX_EMOJI_VALUE,
-    START_EMOJI_VALUE, STOP_EMOJI_VALUE,
+    ByteInSequence, ALPHABET_SIZE, BITS_IN_A_BYTE, BITS_PER_EMOJI, CHECKSUM_EMOJI_VALUE,
+    DEFAULT_NESTING_LIMIT, FORMAT_VERSION, HEADER_EMOJI_VALUE, MAX_EMOJI_VALUE, START_EMOJI_VALUE,
+    STOP_EMOJI_VALUE, SYNC_EMOJI_VALUE,
+};
+use super::error::{FromEmojiError, FromEmojiErrorKind, PathSegment, Token};
+use super::error_correction::{self, ErrorCorrection, ERROR_CORRECTION_LENGTH, ESCAPE_SYMBOL};
+use super::header::{
+    Header, FLAG_CHECKSUM, FLAG_COMPRESSION, FLAG_ERROR_CORRECTION, HEADER_LENGTH, SUPPORTED_FLAGS,
 };
 
 /// A trait representing some source of emoji data. This abstraction let's us use both Strings and
@@ -30,18 +44,26 @@ impl<'a, I: Iterator<Item = u8>> IsEmojiRepresentation<IteratorWrapper<&'a mut I
     }
 }
 
-impl IsEmojiRepresentation<std::vec::IntoIter<u8>> for String {
-    fn demojfuscate_byte_stream(self) -> DecodeEmojiToBytes<std::vec::IntoIter<u8>> {
+/// A decoder that has already been set up, e.g. with a custom alphabet, can be demojfuscated
+/// directly.
+impl<I: Iterator<Item = u8>> IsEmojiRepresentation<I> for DecodeEmojiToBytes<I> {
+    fn demojfuscate_byte_stream(self) -> DecodeEmojiToBytes<I> {
+        self
+    }
+}
+
+impl IsEmojiRepresentation<alloc::vec::IntoIter<u8>> for String {
+    fn demojfuscate_byte_stream(self) -> DecodeEmojiToBytes<alloc::vec::IntoIter<u8>> {
         self.into_bytes().into_iter().demojfuscate_byte_stream()
     }
 }
 
-impl<'a> IsEmojiRepresentation<core::iter::Map<std::slice::Iter<'a, u8>, fn(&'a u8) -> u8>>
+impl<'a> IsEmojiRepresentation<core::iter::Map<core::slice::Iter<'a, u8>, fn(&'a u8) -> u8>>
     for &'a String
 {
     fn demojfuscate_byte_stream(
         self,
-    ) -> DecodeEmojiToBytes<core::iter::Map<std::slice::Iter<'a, u8>, fn(&'a u8) -> u8>> {
+    ) -> DecodeEmojiToBytes<core::iter::Map<core::slice::Iter<'a, u8>, fn(&'a u8) -> u8>> {
         self.as_bytes()
             .into_iter()
             .map((|b| b.clone()) as fn(&u8) -> u8)
@@ -57,22 +79,38 @@ impl<'a> IsEmojiRepresentation<core::str::Bytes<'a>> for &'a str {
 
 impl<I: Iterator<Item = char>>
     IsEmojiRepresentation<
-        std::iter::FlatMap<I, std::vec::IntoIter<u8>, fn(char) -> std::vec::IntoIter<u8>>,
+        core::iter::FlatMap<I, alloc::vec::IntoIter<u8>, fn(char) -> alloc::vec::IntoIter<u8>>,
     > for I
 {
     fn demojfuscate_byte_stream(
         self,
     ) -> DecodeEmojiToBytes<
-        std::iter::FlatMap<I, std::vec::IntoIter<u8>, fn(char) -> std::vec::IntoIter<u8>>,
+        core::iter::FlatMap<I, alloc::vec::IntoIter<u8>, fn(char) -> alloc::vec::IntoIter<u8>>,
     > {
         // this feels awfully inefficient, is there a better way to do it?
         self.flat_map(
-            (|c| c.to_string().into_bytes().into_iter()) as fn(char) -> std::vec::IntoIter<u8>,
+            (|c| c.to_string().into_bytes().into_iter()) as fn(char) -> alloc::vec::IntoIter<u8>,
         )
         .demojfuscate_byte_stream()
     }
 }
 
+/// Receives the tokens of a value from `DecodeEmojiToBytes::visit_value`, e.g. to validate or
+/// pretty print emoji without knowing what type they were made from. The methods do nothing by
+/// default, so only the interesting ones need to be implemented.
+pub trait EmojiVisitor {
+    fn visit_byte(&mut self, _byte: u8) {}
+
+    fn visit_sequence_start(&mut self) {}
+
+    fn visit_sequence_end(&mut self) {}
+
+    fn visit_sync_marker(&mut self) {}
+}
+
+/// Ignores everything, used to skip values.
+impl EmojiVisitor for () {}
+
 /// A trait representing things that can be constructed from Emoji
 pub trait ConstructFromEmoji<A, I>
 where
@@ -112,31 +150,87 @@ where
             past_sequence_start: false,
             reached_sequence_end: false,
             encountered_error: false,
-            _phantom: std::marker::PhantomData,
+            recover_from_errors: false,
+            join_midway: false,
+            skipped_emoji: 0,
+            _phantom: core::marker::PhantomData,
         }
     }
 
-    /// Try to construct a value from a source of emoji
+    /// Try to construct a value from a source of emoji. If the emoji end with a checksum it's
+    /// verified before anything is constructed.
     fn demojfuscate(self) -> Result<A, FromEmojiError>
     where
         Self: Sized,
     {
-        A::construct_from_emoji(&mut self.demojfuscate_byte_stream())
+        let mut byte_stream = self.demojfuscate_byte_stream();
+        byte_stream.verify_checksum()?;
+        A::construct_from_emoji(&mut byte_stream)
     }
 }
 
-#[derive(Debug, PartialEq)]
-pub enum FromEmojiError {
-    InvalidUtf8,
-    InputIsNotAnEmoji(String),
-    NotEnoughEmoji,
-    UnexpectedInput(String),
-    MissingSequenceStart,
-    UnexpectedSequenceStart(String),
-    UnexpectedSequenceEnd,
+/// Where an emoji was found in the input.
+#[derive(Clone, Copy, Default)]
+struct Offset {
+    emoji: usize,
+    byte: usize,
+}
+
+impl Offset {
+    fn error(self, kind: FromEmojiErrorKind) -> FromEmojiError {
+        FromEmojiError {
+            emoji_offset: self.emoji,
+            byte_offset: self.byte,
+            ..FromEmojiError::new(kind)
+        }
+    }
+}
+
+impl From<&FromEmojiError> for Offset {
+    fn from(err: &FromEmojiError) -> Self {
+        Offset {
+            emoji: err.emoji_offset,
+            byte: err.byte_offset,
+        }
+    }
+}
+
+/// Decode the bytes of exactly one UTF-8 character. Unlike `str::from_utf8` this doesn't need the
+/// bytes to be copied anywhere first. Returns `None` for anything that isn't valid UTF-8, including
+/// overlong encodings and surrogates.
+fn decode_utf8(bytes: &[u8]) -> Option<char> {
+    // the smallest code point that needs this many bytes, anything below it is an overlong
+    // encoding
+    const MIN_CODE_POINT: [u32; 4] = [0, 0x80, 0x800, 0x10000];
+
+    let (first, rest) = bytes.split_first()?;
+
+    let mut code_point = match rest.len() {
+        0 if *first < 0x80 => u32::from(*first),
+        // the input can end before all the bytes the first one promised
+        1..=3 if first.leading_ones() as usize == rest.len() + 1 => {
+            u32::from(*first & (0x7f >> (rest.len() + 1)))
+        }
+        _ => return None,
+    };
+
+    for b in rest {
+        if b & 0b1100_0000 != 0b1000_0000 {
+            return None;
+        }
+
+        code_point = (code_point << 6) | u32::from(b & 0b0011_1111);
+    }
+
+    if code_point < MIN_CODE_POINT[rest.len()] {
+        return None;
+    }
+
+    char::from_u32(code_point)
 }
 
 /// This holds an iterator that produces bytes that are interpreted as UTF-8 encoded emoji
+#[derive(Clone)]
 pub struct DecodeEmojiToBytes<I>
 where
     I: Iterator<Item = u8>,
@@ -146,6 +240,29 @@ where
     defined_bits: u16,
     bits_to_truncate: u16,
     peeked_at: Option<Option<Result<ByteInSequence, FromEmojiError>>>,
+    alphabet: &'static Alphabet,
+    require_header: bool,
+    read_first_emoji: bool,
+    header: Option<Header>,
+    expect_checksum: bool,
+    checksum: Checksum,
+    verified_checksum: bool,
+    buffered_values: VecDeque<(u16, Offset)>,
+    unread_value: Option<Result<(u16, Offset), FromEmojiError>>,
+    error_correction: Option<ErrorCorrection>,
+    corrected_values: VecDeque<(u16, Offset)>,
+    read_escape_symbol: bool,
+    corrected_symbols: usize,
+    decompressor: Option<Decompressor>,
+    after_sync_marker: bool,
+    depth: usize,
+    // how many values of recursive types the current one is inside of, see `construct_nested`
+    nesting: usize,
+    nesting_limit: usize,
+    // how much of the input has been read
+    input_offset: Offset,
+    // where the emoji that is currently being decoded came from
+    offset: Offset,
 }
 
 impl<I> DecodeEmojiToBytes<I>
@@ -159,32 +276,677 @@ where
             defined_bits: 0,
             bits_to_truncate: 0,
             peeked_at: None,
+            alphabet: Alphabet::default_alphabet(),
+            require_header: false,
+            read_first_emoji: false,
+            header: None,
+            expect_checksum: false,
+            checksum: Checksum::new(),
+            verified_checksum: false,
+            buffered_values: VecDeque::new(),
+            unread_value: None,
+            error_correction: None,
+            corrected_values: VecDeque::new(),
+            read_escape_symbol: false,
+            corrected_symbols: 0,
+            decompressor: None,
+            after_sync_marker: false,
+            depth: 0,
+            nesting: 0,
+            nesting_limit: DEFAULT_NESTING_LIMIT,
+            input_offset: Offset::default(),
+            offset: Offset::default(),
+        }
+    }
+
+    /// Decode the emoji with a different emoji table than the default one. It has to be the same
+    /// alphabet that the data was emojfuscated with.
+    pub fn with_alphabet(self, alphabet: &'static Alphabet) -> Self {
+        Self { alphabet, ..self }
+    }
+
+    /// How many values of recursive types can be inside of each other, `DEFAULT_NESTING_LIMIT` by
+    /// default. Values are constructed recursively, so without a limit a value that is nested deep
+    /// enough would overflow the stack. A deeper value is a `FromEmojiErrorKind::TooDeeplyNested`
+    /// error.
+    pub fn with_nesting_limit(self, nesting_limit: usize) -> Self {
+        Self {
+            nesting_limit,
+            ..self
+        }
+    }
+
+    /// A header is always checked if there is one, this makes it an error if there isn't.
+    pub fn require_header(self) -> Self {
+        Self {
+            require_header: true,
+            ..self
+        }
+    }
+
+    /// The bytes that the emoji are read from.
+    pub fn get_ref(&self) -> &I {
+        &self.iter
+    }
+
+    pub fn get_mut(&mut self) -> &mut I {
+        &mut self.iter
+    }
+
+    /// The header at the start of the emoji, if there was one and it has been read yet.
+    pub fn header(&self) -> Option<Header> {
+        self.header
+    }
+
+    /// Make it an error if the emoji don't end with a checksum. This is only needed for messages
+    /// without a header, the header says if there's a checksum or not.
+    pub fn expect_checksum(self) -> Self {
+        Self {
+            expect_checksum: true,
+            ..self
         }
     }
 
-    /// If I were a more competent rustacean I would have written an implementation of `peek`,
-    /// Alas...
+    /// Repair the emoji with the parity emoji that were added by
+    /// `EncodeBytesAsEmoji::with_error_correction`. This is only needed for messages without a
+    /// header, otherwise the parameters are read from the header.
+    pub fn with_error_correction(self, error_correction: ErrorCorrection) -> Self {
+        Self {
+            error_correction: Some(error_correction),
+            ..self
+        }
+    }
+
+    /// Decompress the data that was compressed by `EncodeBytesAsEmoji::with_compression`. This is
+    /// only needed for messages without a header, the header says if the data is compressed.
+    pub fn with_compression(self) -> Self {
+        Self {
+            decompressor: Some(Decompressor::new()),
+            ..self
+        }
+    }
+
+    /// The number of emoji that were wrong or unreadable and have been repaired so far.
+    pub fn corrected_symbols(&self) -> usize {
+        self.corrected_symbols
+    }
+
+    /// An error located at the emoji that is currently being decoded.
+    pub fn error_here(&self, kind: FromEmojiErrorKind) -> FromEmojiError {
+        self.offset.error(kind)
+    }
+
+    /// An error for when something other than `expected` was found, located at the emoji that is
+    /// currently being decoded.
+    pub fn unexpected(
+        &self,
+        expected: &'static str,
+        found: Option<ByteInSequence>,
+    ) -> FromEmojiError {
+        let kind = match found {
+            Some(ByteInSequence::Byte(_)) => FromEmojiErrorKind::UnexpectedInput,
+            Some(ByteInSequence::SequenceStart) => FromEmojiErrorKind::UnexpectedSequenceStart,
+            Some(ByteInSequence::SequenceEnd) => FromEmojiErrorKind::UnexpectedSequenceEnd,
+            Some(ByteInSequence::SyncMarker) => FromEmojiErrorKind::UnexpectedSyncMarker,
+            None => FromEmojiErrorKind::NotEnoughEmoji,
+        };
+
+        self.error_here(kind).expected(expected, Token::from(found))
+    }
+
+    /// Read the start of a sequence, e.g. of a `String` or a `Vec`.
+    pub fn expect_sequence_start(&mut self) -> Result<(), FromEmojiError> {
+        const EXPECTED: &str = "the start of a sequence";
+
+        match self.next() {
+            Some(Ok(ByteInSequence::SequenceStart)) => Ok(()),
+            Some(Ok(found)) => Err(self
+                .error_here(FromEmojiErrorKind::MissingSequenceStart)
+                .expected(EXPECTED, Token::from(Some(found)))),
+            Some(Err(err)) => Err(err),
+            None => Err(self.unexpected(EXPECTED, None)),
+        }
+    }
+
+    fn checksum_expected(&self) -> bool {
+        self.expect_checksum
+            || self
+                .header
+                .is_some_and(|header| header.flags & FLAG_CHECKSUM != 0)
+    }
+
+    /// If the message has a checksum, read all of it and make sure that the checksum matches
+    /// before anything is demojfuscated. The emoji are buffered so they can be decoded afterwards.
+    ///
+    /// Messages without a checksum are left untouched, so this doesn't consume any more emoji
+    /// than necessary from a stream that holds more than one message.
+    pub fn verify_checksum(&mut self) -> Result<(), FromEmojiError> {
+        // the header says if there is supposed to be a checksum
+        self.read_header_if_present()?;
+
+        if !self.checksum_expected() {
+            return Ok(());
+        }
+
+        loop {
+            match self.next_message_value() {
+                Some(Ok(value)) => self.buffered_values.push_back(value),
+                Some(Err(err)) => return Err(err),
+                None => break,
+            }
+        }
+
+        let mut checksum = self.checksum;
+        let mut values = self.buffered_values.iter();
+
+        let checksum_offset = loop {
+            match values.next() {
+                Some(&(CHECKSUM_EMOJI_VALUE, offset)) => break offset,
+                Some((value, _)) => checksum.update(*value),
+                None => return Err(self.input_offset.error(FromEmojiErrorKind::MissingChecksum)),
+            }
+        };
+
+        let mut checksum_values = [0u16; CHECKSUM_LENGTH];
+        for checksum_value in checksum_values.iter_mut() {
+            *checksum_value = match values.next() {
+                Some((value, _)) => *value,
+                None => return Err(self.input_offset.error(FromEmojiErrorKind::NotEnoughEmoji)),
+            };
+        }
+
+        if !checksum.matches(checksum_values) {
+            return Err(checksum_offset.error(FromEmojiErrorKind::ChecksumMismatch));
+        }
+
+        Ok(())
+    }
+
+    /// Look at the next token without consuming it.
+    pub fn peek(&mut self) -> Option<&Result<ByteInSequence, FromEmojiError>> {
+        if self.peeked_at.is_none() {
+            self.peeked_at = Some(self.next_token());
+        }
+
+        self.peeked_at.as_ref()?.as_ref()
+    }
+
+    /// Whether the next token is the end of a sequence.
     pub fn reached_end_of_sequence(&mut self) -> bool {
-        match &self.peeked_at {
-            Some(Some(Ok(ByteInSequence::SequenceEnd))) => {
-                return true;
+        matches!(self.peek(), Some(Ok(ByteInSequence::SequenceEnd)))
+    }
+
+    /// The number of sequences that have been started but not ended by the tokens consumed so
+    /// far, e.g. 2 inside of the strings of a `Vec<String>`.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Skip the next value without constructing it, see `visit_value`.
+    pub fn skip_value(&mut self) -> Result<(), FromEmojiError> {
+        self.visit_value(&mut ())
+    }
+
+    /// Read the tag of the chosen variant of an enum, see `EncodeBytesAsEmoji::variant_tag`.
+    pub fn read_variant_tag(&mut self) -> Result<u32, FromEmojiError> {
+        let mut tag: u32 = 0;
+        let mut shift = 0;
+
+        loop {
+            let byte = u8::construct_from_emoji(self)?;
+            let bits = u32::from(byte & 0x7f);
+
+            if shift >= u32::BITS || (bits << shift) >> shift != bits {
+                return Err(self.unexpected("a variant tag", Some(ByteInSequence::Byte(byte))));
+            }
+
+            tag |= bits << shift;
+
+            if byte & 0x80 == 0 {
+                return Ok(tag);
+            }
+
+            shift += 7;
+        }
+    }
+
+    /// Read the name of the next field of a struct with `#[emojfuscate(evolvable)]`, or `None`
+    /// at the end of the struct. The value of the field comes next, it can be constructed with
+    /// `construct_field` or skipped with `skip_value` if the field is unknown.
+    pub fn next_field_name(&mut self) -> Result<Option<String>, FromEmojiError> {
+        if self.reached_end_of_sequence() {
+            self.next(); // pop off the SequenceEnd value
+            return Ok(None);
+        }
+
+        String::construct_from_emoji(self).map(Some)
+    }
+
+    /// Construct the value of a field of a struct with `#[emojfuscate(evolvable)]`, see
+    /// `next_field_name`.
+    pub fn construct_field<A>(&mut self) -> Result<A, FromEmojiError>
+    where
+        A: ConstructFromEmoji<A, I>,
+    {
+        self.construct_field_with(A::construct_from_emoji)
+    }
+
+    /// Like `construct_field`, but the value is constructed by `construct`, e.g. for a field with
+    /// `#[emojfuscate(with = module)]`.
+    pub fn construct_field_with<A, F>(&mut self, construct: F) -> Result<A, FromEmojiError>
+    where
+        F: FnOnce(&mut Self) -> Result<A, FromEmojiError>,
+    {
+        const EXPECTED: &str = "the end of the field";
+
+        self.expect_sequence_start()?;
+        let value = construct(self)?;
+
+        match self.next() {
+            Some(Ok(ByteInSequence::SequenceEnd)) => Ok(value),
+            Some(Ok(found)) => Err(self.unexpected(EXPECTED, Some(found))),
+            Some(Err(err)) => Err(err),
+            None => Err(self.unexpected(EXPECTED, None)),
+        }
+    }
+
+    /// Construct a value that may be inside of other values of the same type with `construct`,
+    /// e.g. the `Box<Expr>` of an `Expr`. It's an error if that makes the values nested deeper than
+    /// the limit, see `with_nesting_limit`.
+    pub fn construct_nested<A, F>(&mut self, construct: F) -> Result<A, FromEmojiError>
+    where
+        F: FnOnce(&mut Self) -> Result<A, FromEmojiError>,
+    {
+        if self.nesting >= self.nesting_limit {
+            return Err(self.error_here(FromEmojiErrorKind::TooDeeplyNested));
+        }
+
+        self.nesting += 1;
+        let value = construct(self);
+        self.nesting -= 1;
+        value
+    }
+
+    /// Feed the next value to `visitor`. The emoji don't say what type they were made from, so a
+    /// value is either a single byte or a whole sequence, including any sequences inside of it.
+    /// Sync markers are visited as they come, they are never a value on their own.
+    pub fn visit_value<V: EmojiVisitor>(&mut self, visitor: &mut V) -> Result<(), FromEmojiError> {
+        const EXPECTED: &str = "a byte or the start of a sequence";
+        let mut nesting = 0;
+
+        loop {
+            match self.next() {
+                Some(Ok(ByteInSequence::Byte(b))) => visitor.visit_byte(b),
+                Some(Ok(ByteInSequence::SequenceStart)) => {
+                    nesting += 1;
+                    visitor.visit_sequence_start();
+                }
+                Some(Ok(ByteInSequence::SequenceEnd)) if nesting > 0 => {
+                    nesting -= 1;
+                    visitor.visit_sequence_end();
+                }
+                Some(Ok(ByteInSequence::SyncMarker)) => {
+                    visitor.visit_sync_marker();
+                    continue;
+                }
+                Some(Ok(found)) => return Err(self.unexpected(EXPECTED, Some(found))),
+                Some(Err(err)) => return Err(err),
+                None => return Err(self.unexpected(EXPECTED, None)),
             }
-            Some(_) => {
-                return false;
+
+            if nesting == 0 {
+                return Ok(());
             }
+        }
+    }
+
+    /// Feed all of the remaining values to `visitor`.
+    pub fn visit<V: EmojiVisitor>(&mut self, visitor: &mut V) -> Result<(), FromEmojiError> {
+        while self.peek().is_some() {
+            self.visit_value(visitor)?;
+        }
+
+        Ok(())
+    }
+
+    /// Skip ahead to just after the next sync marker, so that decoding can continue from there.
+    /// Returns the number of emoji that were skipped or `None` if there are no more sync markers.
+    fn skip_to_sync_marker(&mut self) -> Option<usize> {
+        // a broken header isn't going to stop us from looking for sync markers
+        let _ = self.read_header_if_present();
+
+        let peeked_at = self.peeked_at.take();
+        let mut skipped = 0;
+
+        if !self.after_sync_marker
+            && !matches!(peeked_at, Some(Some(Ok(ByteInSequence::SyncMarker))))
+        {
+            loop {
+                match self.next_emoji_value() {
+                    Some(Ok(SYNC_EMOJI_VALUE)) => break,
+                    Some(_) => skipped += 1,
+                    None => return None,
+                }
+            }
+        }
+
+        self.accumulated_data = 0;
+        self.defined_bits = 0;
+        self.bits_to_truncate = 0;
+        self.after_sync_marker = true;
+        // sync markers are only put between the values of a stream
+        self.depth = 1;
+
+        if self.decompressor.is_some() {
+            self.decompressor = Some(Decompressor::new());
+        }
+
+        Some(skipped)
+    }
+
+    /// Sync markers are only used to recover from errors, otherwise they're skipped over.
+    fn skip_sync_markers(&mut self) {
+        while let Some(Ok(ByteInSequence::SyncMarker)) = self.peek() {
+            self.peeked_at = None;
+        }
+    }
+
+    /// Read the value of the next emoji and add it to the checksum.
+    fn next_emoji_value(&mut self) -> Option<Result<u16, FromEmojiError>> {
+        let (value, offset) = match self.read_emoji_value() {
+            Some(Ok(value_and_offset)) => value_and_offset,
+            Some(Err(err)) => return Some(Err(err)),
             None => {
-                let peeked_at = self.next();
+                // errors about missing emoji are located at the end of the input
+                self.offset = self.input_offset;
+                return None;
+            }
+        };
 
-                let reached_end = match peeked_at {
-                    Some(Ok(ByteInSequence::SequenceEnd)) => true,
-                    _ => false,
-                };
+        self.offset = offset;
 
-                self.peeked_at = Some(peeked_at);
+        if value != CHECKSUM_EMOJI_VALUE {
+            self.checksum.update(value);
+        }
+
+        Some(Ok(value))
+    }
+
+    /// Take the next value from the emoji that were buffered when verifying the checksum, or read
+    /// a new one if there aren't any.
+    fn read_emoji_value(&mut self) -> Option<Result<(u16, Offset), FromEmojiError>> {
+        match self.buffered_values.pop_front() {
+            Some(value) => Some(Ok(value)),
+            None => self.next_message_value(),
+        }
+    }
 
-                return reached_end;
+    /// The values of the emoji after the header, repaired with the parity emoji if the message has
+    /// error correction.
+    fn next_message_value(&mut self) -> Option<Result<(u16, Offset), FromEmojiError>> {
+        let error_correction = match self.error_correction {
+            Some(error_correction) => error_correction,
+            None => return self.decode_emoji_value(),
+        };
+
+        loop {
+            if let Some(value) = self.corrected_values.pop_front() {
+                return Some(Ok(value));
+            }
+
+            match self.read_block(error_correction) {
+                Ok(true) => {}
+                Ok(false) => return None,
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+
+    /// Read and repair the next block of emoji and queue up the values in it. Returns false if
+    /// there are no more blocks.
+    fn read_block(&mut self, error_correction: ErrorCorrection) -> Result<bool, FromEmojiError> {
+        let data_symbols = usize::from(error_correction.data_symbols());
+        let parity_symbols = usize::from(error_correction.parity_symbols());
+
+        let mut block = Vec::with_capacity(data_symbols + parity_symbols);
+        let mut offsets = Vec::with_capacity(data_symbols + parity_symbols);
+        let mut erasures = Vec::new();
+
+        while block.len() < data_symbols + parity_symbols {
+            match self.decode_emoji_value() {
+                Some(Ok((value, offset))) if value < MAX_EMOJI_VALUE => {
+                    block.push(value);
+                    offsets.push(offset);
+                }
+                // emoji that can't be part of a block are known to be wrong, which makes them
+                // cheaper to repair than emoji that just have the wrong value
+                Some(value_or_err) => {
+                    erasures.push(block.len());
+                    block.push(0);
+                    offsets.push(match value_or_err {
+                        Ok((_, offset)) => offset,
+                        Err(err) => Offset::from(&err),
+                    });
+                }
+                None => break,
+            }
+        }
+
+        if block.is_empty() {
+            return Ok(false);
+        }
+
+        if block.len() <= parity_symbols {
+            return Err(self.input_offset.error(FromEmojiErrorKind::NotEnoughEmoji));
+        }
+
+        match error_correction::correct(&mut block, parity_symbols, &erasures) {
+            Some(corrected) => self.corrected_symbols += corrected,
+            None => return Err(offsets[0].error(FromEmojiErrorKind::TooManyErrors)),
+        }
+
+        block.truncate(block.len() - parity_symbols);
+
+        for (symbol, offset) in block.into_iter().zip(offsets) {
+            if self.read_escape_symbol {
+                self.read_escape_symbol = false;
+
+                let value = ESCAPE_SYMBOL + symbol;
+                if usize::from(value) >= ALPHABET_SIZE {
+                    return Err(offset.error(FromEmojiErrorKind::InvalidEscapedValue(value)));
+                }
+
+                self.corrected_values.push_back((value, offset));
+            } else if symbol == ESCAPE_SYMBOL {
+                self.read_escape_symbol = true;
+            } else {
+                self.corrected_values.push_back((symbol, offset));
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Read the next emoji from the underlying bytes and look up which value it represents.
+    fn decode_emoji_value(&mut self) -> Option<Result<(u16, Offset), FromEmojiError>> {
+        if let Some(value) = self.unread_value.take() {
+            return Some(value);
+        }
+
+        loop {
+            let offset = self.input_offset;
+            let b = self.iter.next()?;
+
+            // The first bits of the first byte signify how long (in bytes) the UTF-8 character is.
+            // 0b0XXXXXXX means a one byte character, 0b110XXXXX means a two byte character,
+            // 0b1110XXXX means a three byte character and so on up to four bytes.
+            //
+            // Notice that 0X is zero and 110X is two, you would expect the pattern to be 10X,
+            // 110X or 0X, 10X. This is why we need to do catch the special case of just one
+            // byte characters that break the pattern.
+            //
+            // By counting the leading ones in the byte we can deduce how many more trailing
+            // bytes there is in character we are currently decoding.
+            //
+            // Source: https://stackoverflow.com/questions/643694/what-is-the-difference-between-utf-8-and-unicode
+            let remaining_bytes_in_char = core::cmp::max(b.leading_ones() as usize, 1) - 1;
+
+            // The character is decoded on the stack, a byte with eight leading ones claims seven
+            // more bytes which is the most there can be
+            let mut input_bytes = [0u8; 8];
+            input_bytes[0] = b;
+            let mut length = 1;
+
+            for next in self.iter.by_ref().take(remaining_bytes_in_char) {
+                input_bytes[length] = next;
+                length += 1;
+            }
+
+            self.input_offset.byte += length;
+
+            let emoji = match decode_utf8(&input_bytes[..length]) {
+                Some(c) => c,
+                None => {
+                    self.input_offset.emoji += 1;
+                    return Some(Err(offset.error(FromEmojiErrorKind::InvalidUtf8)));
+                }
+            };
+
+            match self.alphabet.emoji_to_value(emoji) {
+                Some(value) => {
+                    self.input_offset.emoji += 1;
+                    return Some(Ok((value, offset)));
+                }
+                None => {
+                    // We ignore white space, tabs and line break chars to make the CLI easier to
+                    // use
+                    if WHITE_SPACE_CHARS.contains(&emoji) {
+                        continue;
+                    }
+
+                    self.input_offset.emoji += 1;
+                    return Some(Err(
+                        offset.error(FromEmojiErrorKind::InputIsNotAnEmoji(emoji))
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Read the header if the message starts with one and make sure that we know how to decode the
+    /// rest of the message. This only does anything the first time it's called.
+    fn read_header_if_present(&mut self) -> Result<(), FromEmojiError> {
+        if self.read_first_emoji {
+            return Ok(());
+        }
+
+        self.read_first_emoji = true;
+
+        let first_value = self.decode_emoji_value();
+        let header_offset = match first_value {
+            Some(Ok((HEADER_EMOJI_VALUE, offset))) => offset,
+            Some(Err(err)) if self.require_header => return Err(err),
+            _ if self.require_header => {
+                return Err(Offset::default().error(FromEmojiErrorKind::MissingHeader))
+            }
+            _ => {
+                self.unread_value = first_value;
+                return Ok(());
             }
         };
+
+        self.checksum.update(HEADER_EMOJI_VALUE);
+
+        let [version, flags, alphabet_fingerprint]: [u16; HEADER_LENGTH - 1] =
+            self.read_header_values()?;
+
+        if version != FORMAT_VERSION {
+            return Err(header_offset.error(FromEmojiErrorKind::UnsupportedVersion(version)));
+        }
+
+        if flags & !SUPPORTED_FLAGS != 0 {
+            return Err(header_offset.error(FromEmojiErrorKind::UnsupportedHeaderFlags(flags)));
+        }
+
+        if alphabet_fingerprint != self.alphabet.fingerprint() {
+            return Err(header_offset.error(FromEmojiErrorKind::AlphabetMismatch));
+        }
+
+        self.error_correction = None;
+
+        if flags & FLAG_ERROR_CORRECTION != 0 {
+            let [data_symbols, parity_symbols]: [u16; ERROR_CORRECTION_LENGTH] =
+                self.read_header_values()?;
+
+            match ErrorCorrection::new(data_symbols, parity_symbols) {
+                Ok(error_correction) => self.error_correction = Some(error_correction),
+                Err(err) => {
+                    return Err(header_offset.error(FromEmojiErrorKind::InvalidErrorCorrection(err)))
+                }
+            }
+        }
+
+        self.decompressor = match flags & FLAG_COMPRESSION {
+            0 => None,
+            _ => Some(Decompressor::new()),
+        };
+
+        self.header = Some(Header {
+            version,
+            flags,
+            alphabet_fingerprint,
+        });
+
+        Ok(())
+    }
+
+    /// Read emoji that are part of the header, they are included in the checksum but never in the
+    /// error correction.
+    fn read_header_values<const N: usize>(&mut self) -> Result<[u16; N], FromEmojiError> {
+        let mut header_values = [0u16; N];
+
+        for header_value in header_values.iter_mut() {
+            *header_value = match self.decode_emoji_value() {
+                Some(Ok((value, _))) if value < MAX_EMOJI_VALUE => value,
+                Some(Ok((value, offset))) => {
+                    return Err(offset
+                        .error(FromEmojiErrorKind::UnexpectedInput)
+                        .expected("a header value", Token::Emoji(value)))
+                }
+                Some(Err(err)) => return Err(err),
+                None => return Err(self.input_offset.error(FromEmojiErrorKind::NotEnoughEmoji)),
+            };
+
+            self.checksum.update(*header_value);
+        }
+
+        Ok(header_values)
+    }
+
+    /// Read the emoji after the checksum emoji and compare them to the checksum of everything
+    /// before it. Nothing is allowed to come after the checksum.
+    fn read_checksum(&mut self) -> Result<(), FromEmojiError> {
+        let mut checksum_values = [0u16; CHECKSUM_LENGTH];
+
+        for checksum_value in checksum_values.iter_mut() {
+            *checksum_value = match self.read_emoji_value() {
+                Some(Ok((value, _))) => value,
+                Some(Err(err)) => return Err(err),
+                None => return Err(self.input_offset.error(FromEmojiErrorKind::NotEnoughEmoji)),
+            };
+        }
+
+        if !self.checksum.matches(checksum_values) {
+            return Err(self.error_here(FromEmojiErrorKind::ChecksumMismatch));
+        }
+
+        match self.read_emoji_value() {
+            None => Ok(()),
+            Some(Err(err)) => Err(err),
+            Some(Ok((value, offset))) => Err(offset
+                .error(FromEmojiErrorKind::UnexpectedInput)
+                .expected("the end of the message", Token::Emoji(value))),
+        }
     }
 }
 
@@ -194,13 +956,78 @@ where
 {
     type Item = Result<ByteInSequence, FromEmojiError>;
     fn next(&mut self) -> Option<Result<ByteInSequence, FromEmojiError>> {
-        match self.peeked_at.take() {
-            Some(peeked_at) => {
-                return peeked_at;
-            }
-            None => (),
+        let next = match self.peeked_at.take() {
+            Some(peeked_at) => peeked_at,
+            None => self.next_token(),
+        };
+
+        match next {
+            Some(Ok(ByteInSequence::SequenceStart)) => self.depth += 1,
+            Some(Ok(ByteInSequence::SequenceEnd)) => self.depth = self.depth.saturating_sub(1),
+            _ => {}
+        }
+
+        next
+    }
+}
+
+impl<I> DecodeEmojiToBytes<I>
+where
+    I: Iterator<Item = u8>,
+{
+    fn next_token(&mut self) -> Option<Result<ByteInSequence, FromEmojiError>> {
+        // the header says how the rest of the message should be decoded
+        if let Err(err) = self.read_header_if_present() {
+            return Some(Err(err));
+        }
+
+        let next = match self.decompressor {
+            Some(_) => self.decompress_next(),
+            None => self.unpack_next(),
         };
 
+        self.after_sync_marker = matches!(next, Some(Ok(ByteInSequence::SyncMarker)));
+        next
+    }
+
+    /// Undo the compression, the start and end of sequences are part of the compressed data.
+    fn decompress_next(&mut self) -> Option<Result<ByteInSequence, FromEmojiError>> {
+        loop {
+            if let Some(b) = self.decompressor.as_mut()?.next_copied_byte() {
+                return Some(Ok(ByteInSequence::Byte(b)));
+            }
+
+            let b = match self.unpack_next() {
+                Some(Ok(ByteInSequence::Byte(b))) => b,
+                Some(Ok(ByteInSequence::SyncMarker))
+                    if self.decompressor.as_ref()?.at_token_boundary() =>
+                {
+                    // nothing after a sync marker refers to data before it
+                    self.decompressor = Some(Decompressor::new());
+                    return Some(Ok(ByteInSequence::SyncMarker));
+                }
+                Some(Ok(found)) => {
+                    return Some(Err(self.unexpected("compressed data", Some(found))))
+                }
+                Some(Err(err)) => return Some(Err(err)),
+                None if self.decompressor.as_ref()?.at_token_boundary() => return None,
+                None => {
+                    // reset it so the error is only reported once
+                    self.decompressor = Some(Decompressor::new());
+                    return Some(Err(self.error_here(FromEmojiErrorKind::NotEnoughEmoji)));
+                }
+            };
+
+            match self.decompressor.as_mut()?.decompress(b) {
+                Ok(Some(byte_in_sequence)) => return Some(Ok(byte_in_sequence)),
+                Ok(None) => {}
+                Err(kind) => return Some(Err(self.error_here(kind))),
+            }
+        }
+    }
+
+    /// Turn the emoji back into the bytes (and the start and end of sequences) they were made from.
+    fn unpack_next(&mut self) -> Option<Result<ByteInSequence, FromEmojiError>> {
         loop {
             if self.defined_bits >= BITS_IN_A_BYTE {
                 let u16_byte_to_output =
@@ -213,68 +1040,52 @@ where
                 return Some(Ok(ByteInSequence::Byte(byte_to_output)));
             }
 
-            let emoji = {
-                let Some(b) = self.iter.next() else {
-                    return None;
-                };
-
-                // The first bits of the first byte signify how long (in bytes) the UTF-8 character is.
-                // 0b0XXXXXXX means a one byte character, 0b110XXXXX means a two byte character,
-                // 0b1110XXXX means a three byte character and so on up to four bytes.
-                //
-                // Notice that 0X is zero and 110X is two, you would expect the pattern to be 10X,
-                // 110X or 0X, 10X. This is why we need to do catch the special case of just one
-                // byte characters that break the pattern.
-                //
-                // By counting the leading ones in the byte we can deduce how many more trailing
-                // bytes there is in character we are currently decoding.
-                //
-                // Source: https://stackoverflow.com/questions/643694/what-is-the-difference-between-utf-8-and-unicode
-                let remaining_bytes_in_char = std::cmp::max(b.leading_ones() as usize, 1) - 1;
-
-                let mut input_bytes = vec![b];
-                let mut bytes_after_first: Vec<u8> =
-                    self.iter.by_ref().take(remaining_bytes_in_char).collect();
-
-                input_bytes.append(&mut bytes_after_first);
-
-                match str::from_utf8(&input_bytes) {
-                    Ok(s) => s.chars().nth(0).unwrap(),
-                    Err(_) => return Some(Err(FromEmojiError::InvalidUtf8)),
+            let emoji_value = match self.next_emoji_value() {
+                Some(Ok(value)) => value,
+                Some(Err(err)) => return Some(Err(err)),
+                None if self.checksum_expected() && !self.verified_checksum => {
+                    self.verified_checksum = true;
+                    return Some(Err(self.error_here(FromEmojiErrorKind::MissingChecksum)));
                 }
+                None => return None,
             };
 
-            let emoji_value = match EMOJI_VALUES.get(&emoji) {
-                Some(x) => x,
-                None => {
-                    // We ignore white space, tabs and line break chars to make the CLI easier to
-                    // use
-                    let white_space_chars = [' ', '\r', '\n', '\t'];
-                    if white_space_chars.contains(&emoji) {
-                        continue;
-                    }
+            // the header can only be the very first emoji, it tells us how the rest of the message
+            // was encoded
+            if emoji_value == HEADER_EMOJI_VALUE {
+                return Some(Err(self
+                    .error_here(FromEmojiErrorKind::UnexpectedInput)
+                    .expected("data", Token::Emoji(HEADER_EMOJI_VALUE))));
+            }
+
+            // the checksum comes after all of the data
+            if emoji_value == CHECKSUM_EMOJI_VALUE {
+                self.verified_checksum = true;
 
-                    return Some(Err(FromEmojiError::InputIsNotAnEmoji(format!(
-                        "Unexpected input character: {}",
-                        emoji
-                    ))));
+                match self.read_checksum() {
+                    Ok(()) => return None,
+                    Err(err) => return Some(Err(err)),
                 }
-            };
+            }
 
             // the start/stop emoji are used by types whose type is unknown at compile time (e.g.
             // strings) to indicate beginning and end of data with dynamic length
-            if *emoji_value == START_EMOJI_VALUE {
+            if emoji_value == START_EMOJI_VALUE {
                 return Some(Ok(ByteInSequence::SequenceStart));
             }
 
-            if *emoji_value == STOP_EMOJI_VALUE {
+            if emoji_value == STOP_EMOJI_VALUE {
                 return Some(Ok(ByteInSequence::SequenceEnd));
             }
 
+            if emoji_value == SYNC_EMOJI_VALUE {
+                return Some(Ok(ByteInSequence::SyncMarker));
+            }
+
             // emoji beyond 2047 are used to indicate that the next emoji produces too many bits. This
             // happens at the end of the encoded message
-            if *emoji_value >= MAX_EMOJI_VALUE {
-                self.bits_to_truncate = *emoji_value - MAX_EMOJI_VALUE;
+            if emoji_value >= MAX_EMOJI_VALUE {
+                self.bits_to_truncate = emoji_value - MAX_EMOJI_VALUE;
                 continue;
             }
 
@@ -299,7 +1110,83 @@ where
     past_sequence_start: bool,
     reached_sequence_end: bool,
     encountered_error: bool,
-    _phantom: std::marker::PhantomData<A>,
+    recover_from_errors: bool,
+    join_midway: bool,
+    skipped_emoji: usize,
+    _phantom: core::marker::PhantomData<A>,
+}
+
+impl<A, I> DemojfuscateIterator<A, I>
+where
+    A: ConstructFromEmoji<A, I>,
+    I: Iterator<Item = u8>,
+{
+    /// Normally the stream ends after the first error. With this the error is returned and then
+    /// the emoji are skipped until the next sync marker (see
+    /// `EmojfuscateWithSyncMarkers::emojfuscate_stream_with_sync_markers`) where decoding
+    /// continues.
+    pub fn recover_from_errors(self) -> Self {
+        Self {
+            recover_from_errors: true,
+            ..self
+        }
+    }
+
+    /// Start decoding from the first sync marker instead of from the start of the stream. This is
+    /// for joining a stream that is already underway.
+    pub fn join_midway(self) -> Self {
+        Self {
+            join_midway: true,
+            ..self
+        }
+    }
+
+    /// The number of emoji that have been skipped while looking for sync markers.
+    pub fn skipped_emoji(&self) -> usize {
+        self.skipped_emoji
+    }
+
+    pub fn get_ref(&self) -> &DecodeEmojiToBytes<I> {
+        &self.iter
+    }
+
+    pub fn get_mut(&mut self) -> &mut DecodeEmojiToBytes<I> {
+        &mut self.iter
+    }
+
+    /// Give up after an error, unless we're supposed to skip ahead to the next sync marker.
+    fn recover(&mut self, err: FromEmojiError) -> Option<Result<A, FromEmojiError>> {
+        if self.recover_from_errors {
+            if let Some(skipped) = self.iter.skip_to_sync_marker() {
+                self.skipped_emoji += skipped;
+                self.past_sequence_start = true;
+                return Some(Err(err));
+            }
+        }
+
+        self.encountered_error = true;
+        Some(Err(err))
+    }
+}
+
+// derived it would require A to be Clone as well
+impl<A, I> Clone for DemojfuscateIterator<A, I>
+where
+    A: ConstructFromEmoji<A, I>,
+    I: Iterator<Item = u8> + Clone,
+{
+    fn clone(&self) -> Self {
+        DemojfuscateIterator {
+            iter: self.iter.clone(),
+            past_sequence_start: self.past_sequence_start,
+            reached_sequence_end: self.reached_sequence_end,
+            encountered_error: self.encountered_error,
+            recover_from_errors: self.recover_from_errors,
+            join_midway: self.join_midway,
+            skipped_emoji: self.skipped_emoji,
+            _phantom: core::marker::PhantomData,
+        }
+    }
 }
 
 impl<A, I> Iterator for DemojfuscateIterator<A, I>
@@ -313,38 +1200,54 @@ where
             return None;
         }
 
-        if !self.past_sequence_start {
-            match self.iter.next() {
-                Some(Ok(ByteInSequence::SequenceStart)) => {
+        if self.join_midway {
+            self.join_midway = false;
+
+            match self.iter.skip_to_sync_marker() {
+                Some(skipped) => {
+                    self.skipped_emoji += skipped;
                     self.past_sequence_start = true;
                 }
-                Some(Ok(_)) => {
+                None => {
                     self.encountered_error = true;
-                    return Some(Err(FromEmojiError::MissingSequenceStart));
+                    return None;
                 }
-                Some(Err(err)) => {
-                    self.encountered_error = true;
-                    return Some(Err(err));
+            }
+        }
+
+        if !self.past_sequence_start {
+            match self.iter.expect_sequence_start() {
+                Ok(()) => {
+                    self.past_sequence_start = true;
                 }
-                None => {
+                Err(err) if err.kind == FromEmojiErrorKind::NotEnoughEmoji => {
                     self.encountered_error = true;
-                    return Some(Err(FromEmojiError::NotEnoughEmoji));
+                    return Some(Err(err));
                 }
+                Err(err) => return self.recover(err),
             }
         }
 
+        self.iter.skip_sync_markers();
+
         if self.iter.reached_end_of_sequence() {
             self.iter.next(); // pop off the SequenceEnd value
             self.reached_sequence_end = true;
+
+            // the checksum comes after the end of the sequence, this is the first chance we get to
+            // check it
+            if self.iter.checksum_expected() {
+                if let Some(Err(err)) = self.iter.next() {
+                    return Some(Err(err));
+                }
+            }
+
             return None;
         }
 
         match A::construct_from_emoji(&mut self.iter) {
             Ok(value) => return Some(Ok(value)),
-            Err(err) => {
-                self.encountered_error = true;
-                return Some(Err(err));
-            }
+            Err(err) => return self.recover(err),
         };
     }
 }
@@ -369,11 +1272,11 @@ where
         match byte_stream.next() {
             Some(Ok(ByteInSequence::Byte(0))) => Ok(false),
             Some(Ok(ByteInSequence::Byte(1))) => Ok(true),
-            Some(Ok(ByteInSequence::Byte(x))) => Err(FromEmojiError::UnexpectedInput(format!("Received unexpected byte when trying to demojfuscate bool, expected 0 or 1 but received {}", x))),
-            Some(Ok(ByteInSequence::SequenceStart)) => Err(FromEmojiError::UnexpectedSequenceStart("When demojfuscating bool".to_string())),
-            Some(Ok(ByteInSequence::SequenceEnd)) => Err(FromEmojiError::UnexpectedSequenceEnd),
+            Some(Ok(found)) => Err(byte_stream
+                .unexpected("0 or 1", Some(found))
+                .in_type("bool")),
             Some(Err(err)) => Err(err),
-            None => Err(FromEmojiError::NotEnoughEmoji)
+            None => Err(byte_stream.unexpected("0 or 1", None).in_type("bool")),
         }
     }
 }
@@ -389,10 +1292,9 @@ where
             Err(err) => Err(err),
             Ok(bytes) => match char::from_u32(u32::from_be_bytes(bytes)) {
                 Some(char) => Ok(char),
-                None => Err(FromEmojiError::UnexpectedInput(format!(
-                    "Can't create char from u32: {}",
-                    u32::from_be_bytes(bytes)
-                ))),
+                None => Err(byte_stream
+                    .error_here(FromEmojiErrorKind::InvalidChar(u32::from_be_bytes(bytes)))
+                    .in_type("char")),
             },
         }
     }
@@ -405,12 +1307,9 @@ where
     fn construct_from_emoji(byte_stream: &mut DecodeEmojiToBytes<I>) -> Result<u8, FromEmojiError> {
         match byte_stream.next() {
             Some(Ok(ByteInSequence::Byte(byte))) => Ok(byte),
-            Some(Ok(ByteInSequence::SequenceStart)) => Err(
-                FromEmojiError::UnexpectedSequenceStart("When demojfuscating u8".to_string()),
-            ),
-            Some(Ok(ByteInSequence::SequenceEnd)) => Err(FromEmojiError::UnexpectedSequenceEnd),
+            Some(Ok(found)) => Err(byte_stream.unexpected("a byte", Some(found))),
             Some(Err(err)) => Err(err),
-            None => Err(FromEmojiError::NotEnoughEmoji),
+            None => Err(byte_stream.unexpected("a byte", None)),
         }
     }
 }
@@ -553,37 +1452,31 @@ where
         byte_stream: &mut DecodeEmojiToBytes<I>,
     ) -> Result<String, FromEmojiError> {
         // verify that first item is SequenceStart
-        match byte_stream.next() {
-            Some(Ok(ByteInSequence::SequenceStart)) => {}
-            Some(Err(err)) => return Err(err),
-            _ => return Err(FromEmojiError::MissingSequenceStart),
-        };
+        if let Err(err) = byte_stream.expect_sequence_start() {
+            return Err(err.in_type("String"));
+        }
 
-        // collect bytes until we hit SequenceEnd. The SequenceEnd element is dropped by map_while
-        let byte_vec_or_err: Result<Vec<u8>, FromEmojiError> = byte_stream
-            .by_ref()
-            .map_while(|pb| match pb {
-                Ok(ByteInSequence::Byte(b)) => Some(Ok(b)),
-                Ok(ByteInSequence::SequenceEnd) => None,
-                Ok(ByteInSequence::SequenceStart) => {
-                    Some(Err(FromEmojiError::UnexpectedSequenceStart(
-                        "When demojfuscating String".to_string(),
-                    )))
-                }
-                Err(err) => Some(Err(err)),
-            })
-            .collect();
+        // collect bytes until we hit SequenceEnd
+        let mut byte_vec = Vec::new();
 
-        let byte_vec = match byte_vec_or_err {
-            Err(err) => return Err(err),
-            Ok(byte_vec) => byte_vec,
-        };
+        loop {
+            match byte_stream.next() {
+                Some(Ok(ByteInSequence::Byte(b))) => byte_vec.push(b),
+                Some(Ok(ByteInSequence::SequenceEnd)) | None => break,
+                Some(Ok(found)) => {
+                    return Err(byte_stream
+                        .unexpected("a byte or the end of the sequence", Some(found))
+                        .in_type("String"))
+                }
+                Some(Err(err)) => return Err(err),
+            }
+        }
 
         let string = match String::from_utf8(byte_vec) {
             Err(_) => {
-                return Err(FromEmojiError::UnexpectedInput(
-                    "Failed to deserialize to UTF-8 string".to_string(),
-                ))
+                return Err(byte_stream
+                    .error_here(FromEmojiErrorKind::InvalidString)
+                    .in_type("String"))
             }
             Ok(string) => string,
         };
@@ -604,7 +1497,7 @@ where
 
         for element_index in 0..array_vec.capacity() {
             match A::construct_from_emoji(byte_stream) {
-                Err(err) => return Err(err),
+                Err(err) => return Err(err.within("array", PathSegment::Index(element_index))),
                 Ok(x) => {
                     array_vec.insert(element_index, x);
                 }
@@ -613,7 +1506,8 @@ where
 
         return match array_vec.into_inner() {
             Ok(array) => Ok(array),
-            Err(_) => Err(FromEmojiError::NotEnoughEmoji), // this should be impossible
+            // this should be impossible
+            Err(_) => Err(byte_stream.error_here(FromEmojiErrorKind::NotEnoughEmoji)),
         };
     }
 }
@@ -626,11 +1520,8 @@ where
     fn construct_from_emoji(
         byte_stream: &mut DecodeEmojiToBytes<I>,
     ) -> Result<Vec<A>, FromEmojiError> {
-        match byte_stream.next() {
-            Some(Ok(ByteInSequence::SequenceStart)) => {}
-            Some(Ok(_)) => return Err(FromEmojiError::MissingSequenceStart),
-            Some(Err(err)) => return Err(err),
-            None => return Err(FromEmojiError::NotEnoughEmoji),
+        if let Err(err) = byte_stream.expect_sequence_start() {
+            return Err(err.in_type("Vec"));
         }
 
         let mut vec = Vec::new();
@@ -645,12 +1536,48 @@ where
                 Ok(element) => {
                     vec.push(element);
                 }
-                Err(err) => return Err(err),
+                Err(err) => return Err(err.within("Vec", PathSegment::Index(vec.len()))),
             };
         }
     }
 }
 
+// emoji can't be borrowed from, so the value is always owned
+impl<'a, I> ConstructFromEmoji<Cow<'a, str>, I> for Cow<'a, str>
+where
+    I: Iterator<Item = u8>,
+{
+    fn construct_from_emoji(
+        byte_stream: &mut DecodeEmojiToBytes<I>,
+    ) -> Result<Cow<'a, str>, FromEmojiError> {
+        String::construct_from_emoji(byte_stream).map(Cow::Owned)
+    }
+}
+
+impl<'a, I, A> ConstructFromEmoji<Cow<'a, [A]>, I> for Cow<'a, [A]>
+where
+    I: Iterator<Item = u8>,
+    A: Clone + ConstructFromEmoji<A, I>,
+{
+    fn construct_from_emoji(
+        byte_stream: &mut DecodeEmojiToBytes<I>,
+    ) -> Result<Cow<'a, [A]>, FromEmojiError> {
+        Vec::construct_from_emoji(byte_stream).map(Cow::Owned)
+    }
+}
+
+impl<I, A> ConstructFromEmoji<Box<A>, I> for Box<A>
+where
+    I: Iterator<Item = u8>,
+    A: ConstructFromEmoji<A, I>,
+{
+    fn construct_from_emoji(
+        byte_stream: &mut DecodeEmojiToBytes<I>,
+    ) -> Result<Box<A>, FromEmojiError> {
+        A::construct_from_emoji(byte_stream).map(Box::new)
+    }
+}
+
 impl<I, A> ConstructFromEmoji<Option<A>, I> for Option<A>
 where
     I: Iterator<Item = u8>,
@@ -663,7 +1590,9 @@ where
             Err(err) => Err(err),
             Ok(0) => Ok(None),
             Ok(1) => A::construct_from_emoji(byte_stream).map(Some),
-            Ok(n) => Err(FromEmojiError::UnexpectedInput(format!("Error parsing Option, expected first byte to be 0 for None or 1 for Some, instead got: {}", n)))
+            Ok(n) => Err(byte_stream
+                .unexpected("0 for None or 1 for Some", Some(ByteInSequence::Byte(n)))
+                .in_type("Option")),
         }
     }
 }
@@ -677,35 +1606,22 @@ where
     fn construct_from_emoji(
         byte_stream: &mut DecodeEmojiToBytes<I>,
     ) -> Result<Result<A, B>, FromEmojiError> {
-        let constructor_discriminator = match u8::construct_from_emoji(byte_stream) {
-            Err(err) => return Err(err),
-            Ok(n) => n,
+        let tag = match byte_stream.read_variant_tag() {
+            Err(err) => return Err(err.in_type("Result")),
+            Ok(tag) => tag,
         };
 
-        let m_ok_data = match Option::construct_from_emoji(byte_stream) {
-            Err(err) => return Err(err),
-            Ok(x) => x,
-        };
-
-        let m_err_data = match Option::construct_from_emoji(byte_stream) {
-            Err(err) => return Err(err),
-            Ok(x) => x,
-        };
-
-        return match (constructor_discriminator, m_ok_data, m_err_data) {
-            // Ok
-            (0, None, None) => Err(FromEmojiError::UnexpectedInput("Error parsing Result, no data found when parsing Ok branch".to_string())),
-            (0, None, Some(_)) => Err(FromEmojiError::UnexpectedInput("Error parsing Result, only Err data found when parsing Ok branch".to_string())),
-            (0, Some(ok_data), None) => Ok(Ok(ok_data)),
-            (0, Some(_), Some(_)) => Err(FromEmojiError::UnexpectedInput("Error parsing Result, both Ok and Err data found when parsing Ok branch".to_string())),
-            // Err
-            (1, None, None) => Err(FromEmojiError::UnexpectedInput("Error parsing Result, no data found when parsing Err branch".to_string())),
-            (1, None, Some(err_data)) => Ok(Err(err_data)),
-            (1, Some(_), None) => Err(FromEmojiError::UnexpectedInput("Error parsing Result, only Ok data found when parsing Err branch".to_string())),
-            (1, Some(_), Some(_)) => Err(FromEmojiError::UnexpectedInput("Error parsing Result, both Ok and Err data found when parsing Err branch".to_string())),
-            // None of the above
-            (n, _, _) => Err(FromEmojiError::UnexpectedInput(format!("Error parsing Result, expected first byte to be 0 for Ok or 1 for Err, instead got: {}", n)))
-        };
+        match tag {
+            0 => A::construct_from_emoji(byte_stream)
+                .map(Ok)
+                .map_err(|err| err.within("Result", PathSegment::Variant("Ok"))),
+            1 => B::construct_from_emoji(byte_stream)
+                .map(Err)
+                .map_err(|err| err.within("Result", PathSegment::Variant("Err"))),
+            tag => Err(byte_stream
+                .error_here(FromEmojiErrorKind::UnknownVariant(tag))
+                .in_type("Result")),
+        }
     }
 }
 
@@ -718,7 +1634,7 @@ where
         byte_stream: &mut DecodeEmojiToBytes<I>,
     ) -> Result<(A,), FromEmojiError> {
         return match A::construct_from_emoji(byte_stream) {
-            Err(err) => return Err(err),
+            Err(err) => return Err(err.within("tuple", PathSegment::Position(0))),
             Ok(result) => Ok((result,)),
         };
     }
@@ -738,7 +1654,12 @@ macro_rules! tuple_impls {
                 ) -> Result<($($name),+), FromEmojiError> {
                     $(
                     let [<$name:lower>] = match $name::construct_from_emoji(byte_stream) {
-                        Err(err) => return Err(err),
+                        // the type parameters are the letters of the alphabet in order, so the
+                        // letter says where in the tuple the value is
+                        Err(err) => return Err(err.within(
+                            "tuple",
+                            PathSegment::Position(usize::from(stringify!($name).as_bytes()[0] - b'A')),
+                        )),
                         Ok(result) => {
                             result
                         }
@@ -770,21 +1691,21 @@ where
         byte_stream: &mut DecodeEmojiToBytes<Iter>,
     ) -> Result<(A, B, C), FromEmojiError> {
         let a = match A::construct_from_emoji(byte_stream) {
-            Err(err) => return Err(err),
+            Err(err) => return Err(err.within("tuple", PathSegment::Position(0))),
             Ok(result) => {
                 result
             }
         };
 
         let b = match B::construct_from_emoji(byte_stream) {
-            Err(err) => return Err(err),
+            Err(err) => return Err(err.within("tuple", PathSegment::Position(1))),
             Ok(result) => {
                 result
             }
         };
 
         let c = match C::construct_from_emoji(byte_stream) {
-            Err(err) => return Err(err),
+            Err(err) => return Err(err.within("tuple", PathSegment::Position(2))),
             Ok(result) => {
                 result
             }
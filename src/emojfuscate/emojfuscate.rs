@@ -1,14 +1,28 @@
+use super::alphabet::Alphabet;
+use super::bulk::EmojiPacker;
+use super::checksum::{Checksum, CHECKSUM_LENGTH};
+use super::compression::Compress;
 use super::constants::{
-    usize_to_emoji, ByteInSequence, BITS_IN_A_BYTE, BITS_PER_EMOJI, MAX_EMOJI_VALUE,
-    START_EMOJI_VALUE, STOP_EMOJI_VALUE,
+    ByteInSequence, BITS_IN_A_BYTE, BITS_PER_EMOJI, CHECKSUM_EMOJI_VALUE, MAX_EMOJI_VALUE,
+    START_EMOJI_VALUE, STOP_EMOJI_VALUE, SYNC_EMOJI_VALUE,
+};
+use super::error_correction::{self, ErrorCorrection, ERROR_CORRECTION_LENGTH, ESCAPE_SYMBOL};
+use super::fixed_size::{packed_emoji_len, EmojfuscateFixedSize};
+use super::header::{
+    Header, FLAG_CHECKSUM, FLAG_COMPRESSION, FLAG_ERROR_CORRECTION, HEADER_LENGTH,
 };
 
 use crate::util::iterator_wrapper::IteratorWrapper;
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+use arrayvec::{ArrayString, ArrayVec};
 use core::array::IntoIter;
+use core::iter::{empty, once, Chain, Empty, FlatMap, Flatten, Map, Once};
+use core::marker::PhantomData;
 use paste::paste;
-use std::collections::VecDeque;
-use std::iter::{empty, once, Chain, Empty, FlatMap, Flatten, Map, Once};
-use std::vec::Vec;
 use uuid::Uuid;
 
 /// A trait reprenting things that can be encoded as emoji, either as a lazy stream of bytes or
@@ -17,6 +31,28 @@ use uuid::Uuid;
 /// For the most part you shouldn't need to make your own implementations of this. Most types in
 /// the standard library already has an implementation and implementations can be derived for your
 /// custom types using the derive macro.
+///
+/// The derive supports types that have themselves in their fields, like an `Expr` with a
+/// `Box<Expr>`, but not types that are recursive through another type. The iterator of such a type
+/// would contain itself, so encoding a value of it doesn't compile:
+///
+/// ```compile_fail,E0275
+/// use emojfuscate::Emojfuscate;
+///
+/// #[derive(Emojfuscate)]
+/// enum Expr {
+///     Lit(u8),
+///     Block(Box<Statement>),
+/// }
+///
+/// #[derive(Emojfuscate)]
+/// enum Statement {
+///     Eval(Expr),
+///     Empty,
+/// }
+///
+/// Expr::Block(Box::new(Statement::Eval(Expr::Lit(1)))).emojfuscate();
+/// ```
 pub trait Emojfuscate<I>
 where
     I: Iterator<Item = ByteInSequence>,
@@ -29,6 +65,68 @@ where
     {
         return self.emojfuscate_stream().collect();
     }
+
+    /// The number of emoji that `emojfuscate` produces. For values that always turn into the same
+    /// number of bytes, like `u64`, `Uuid` or tuples of them, this doesn't need to encode anything.
+    fn emoji_len(self) -> usize
+    where
+        Self: Sized,
+    {
+        let stream = self.emojfuscate_stream();
+
+        match stream.size_hint() {
+            (lower, Some(upper)) if lower == upper => lower,
+            _ => stream.count(),
+        }
+    }
+
+    /// The number of bytes of UTF-8 that `emojfuscate` produces, e.g. to check that a value fits
+    /// in a message. Not all emoji are equally long, so the value is encoded to find out, but
+    /// without building the string.
+    fn encoded_utf8_len(self) -> usize
+    where
+        Self: Sized,
+    {
+        self.emojfuscate_stream().map(char::len_utf8).sum()
+    }
+
+    /// Encode a value of a fixed size type without allocating. `CAP` has to be the type's
+    /// `EmojfuscateFixedSize::MAX_UTF8_LEN`, which is checked at compile time.
+    fn emojfuscate_array<const CAP: usize>(self) -> ArrayString<CAP>
+    where
+        Self: Sized + EmojfuscateFixedSize,
+    {
+        const {
+            assert!(
+                CAP == Self::MAX_UTF8_LEN,
+                "the capacity has to be EmojfuscateFixedSize::MAX_UTF8_LEN"
+            )
+        };
+
+        let mut encoded = ArrayString::new();
+        let mut packer = EmojiPacker::new();
+
+        // fixed size types never start or stop sequences
+        for byte_in_sequence in self.emojfuscate_stream().into_inner() {
+            if let ByteInSequence::Byte(b) = byte_in_sequence {
+                packer.pack(&[b], &mut encoded);
+            }
+        }
+
+        packer.pad(&mut encoded);
+        encoded
+    }
+
+    /// Encode the value with parity emoji so that it can be repaired if some of the emoji get
+    /// mangled, see `EncodeBytesAsEmoji::with_error_correction`.
+    fn emojfuscate_with_error_correction(self, error_correction: ErrorCorrection) -> String
+    where
+        Self: Sized,
+    {
+        self.emojfuscate_stream()
+            .with_error_correction(error_correction)
+            .collect()
+    }
 }
 
 /// This is the same thing as Emojfuscate but it only applies to Iterators of bytes, it's an
@@ -49,19 +147,77 @@ where
             .add_stop_emoji()
     }
 
-    fn emojfuscate_byte_stream_no_start_or_stop(self) -> EncodeBytesAsEmoji<I>;
+    fn emojfuscate_byte_stream_no_start_or_stop(self) -> EncodeBytesAsEmoji<I, KnownLength>;
 }
 
 /// This is a representation of a stream of data that is being converted into emoji. Calling
 /// `emojfuscate_stream` on something
-pub struct EncodeBytesAsEmoji<I>
+///
+/// `L` says whether the number of emoji is known in advance, see `KnownLength`.
+pub struct EncodeBytesAsEmoji<I, L = UnknownLength>
 where
     I: Iterator<Item = ByteInSequence>,
 {
     iter: I,
     input_data: usize,
     defined_bits: u16,
-    queued_emoji: VecDeque<char>,
+    queued_values: VecDeque<u16>,
+    alphabet: &'static Alphabet,
+    emit_header: bool,
+    checksum: Option<Checksum>,
+    error_correction: Option<ErrorCorrection>,
+    unencoded_symbols: Vec<u16>,
+    encoded_values: VecDeque<u16>,
+    compressed: bool,
+    waiting_for_input: bool,
+    // the underlying iterator only produces bytes, which makes the number of emoji predictable
+    only_bytes: bool,
+    length: PhantomData<L>,
+}
+
+/// Marks encoders of nothing but bytes, without error correction or compression, so that the
+/// number of emoji follows from the number of bytes. They're an `ExactSizeIterator` when the
+/// underlying iterator is one. Only `emojfuscate_byte_stream_no_start_or_stop` makes them.
+pub enum KnownLength {}
+
+/// Marks encoders where the number of emoji can depend on the data, e.g. because of error
+/// correction, so `size_hint` is only a bound.
+pub enum UnknownLength {}
+
+/// The bytes of a field of an evolvable struct, see `EncodeBytesAsEmoji::evolvable_field`.
+pub type EvolvableField<I> = Chain<
+    Chain<
+        Chain<Once<ByteInSequence>, Map<core::str::Bytes<'static>, fn(u8) -> ByteInSequence>>,
+        Once<ByteInSequence>,
+    >,
+    Chain<Chain<Once<ByteInSequence>, I>, Once<ByteInSequence>>,
+>;
+
+/// The bytes of a value that have been put in a `Box`, see `EncodeBytesAsEmoji::boxed`.
+pub type BoxedEmojiBytes<'a> = Box<dyn Iterator<Item = ByteInSequence> + 'a>;
+
+/// The bytes of the tag of an enum variant, see `EncodeBytesAsEmoji::variant_tag`.
+pub type VariantTag = arrayvec::IntoIter<ByteInSequence, 5>;
+
+/// The bytes of the fields of an enum variant, if it's the chosen one, see
+/// `EncodeBytesAsEmoji::chain_variant`.
+pub type VariantFields<I> = Flatten<core::option::IntoIter<I>>;
+
+impl EncodeBytesAsEmoji<VariantTag> {
+    /// The tag that says which variant of an enum was chosen. It takes as many bytes as it needs,
+    /// 7 bits per byte starting with the lowest ones, and the highest bit is set on all of the
+    /// bytes but the last. So the first 128 variants of an enum only take a byte.
+    pub fn variant_tag(mut tag: u32) -> Self {
+        let mut bytes = ArrayVec::new();
+
+        while tag >= 0x80 {
+            bytes.push(ByteInSequence::Byte(tag as u8 | 0x80));
+            tag >>= 7;
+        }
+
+        bytes.push(ByteInSequence::Byte(tag as u8));
+        EncodeBytesAsEmoji::from_bytes(bytes.into_iter())
+    }
 }
 
 impl<I> EncodeBytesAsEmoji<I>
@@ -73,10 +229,152 @@ where
             iter,
             input_data: 0,
             defined_bits: 0,
-            queued_emoji: VecDeque::with_capacity(3),
+            queued_values: VecDeque::new(),
+            alphabet: Alphabet::default_alphabet(),
+            emit_header: false,
+            checksum: None,
+            error_correction: None,
+            unencoded_symbols: Vec::new(),
+            encoded_values: VecDeque::new(),
+            compressed: false,
+            waiting_for_input: false,
+            only_bytes: false,
+            length: PhantomData,
+        }
+    }
+
+    /// For iterators that never produce anything but `ByteInSequence::Byte`, which lets
+    /// `size_hint` be exact.
+    fn from_bytes(iter: I) -> Self {
+        Self {
+            only_bytes: true,
+            ..Self::new(iter)
+        }
+    }
+}
+
+impl<I, L> EncodeBytesAsEmoji<I, L>
+where
+    I: Iterator<Item = ByteInSequence>,
+{
+    /// Encode the data with a different emoji table than the default one. The same alphabet needs
+    /// to be used when demojfuscating the data again.
+    pub fn with_alphabet(self, alphabet: &'static Alphabet) -> Self {
+        Self { alphabet, ..self }
+    }
+
+    /// Start the emoji with a header that announces the format version and alphabet that was used
+    /// to produce them. Use this for data that is stored for a long time, so it can still be
+    /// decoded (or at least be rejected with a helpful error) after the format has changed.
+    pub fn with_header(self) -> Self {
+        Self {
+            emit_header: true,
+            ..self
+        }
+    }
+
+    /// End the emoji with a checksum so that emoji that were lost or mixed up along the way are
+    /// detected before any data is demojfuscated. The checksum covers all of the emoji, including
+    /// the header if there is one.
+    pub fn with_checksum(self) -> Self {
+        Self {
+            checksum: Some(Checksum::new()),
+            ..self
+        }
+    }
+
+    /// Add Reed-Solomon parity emoji so that the decoder can repair emoji that were changed or
+    /// became unreadable along the way. The parameters are written in the header if there is one,
+    /// otherwise the decoder needs to be given the same `ErrorCorrection`.
+    pub fn with_error_correction(
+        self,
+        error_correction: ErrorCorrection,
+    ) -> EncodeBytesAsEmoji<I> {
+        EncodeBytesAsEmoji {
+            error_correction: Some(error_correction),
+            ..self.with_length()
+        }
+    }
+
+    /// Compress the data before it's turned into emoji. This pays off for large strings and byte
+    /// vectors with a lot of repetition. The compression only needs a few kilobytes of memory, no
+    /// matter how much data is compressed, so it works for infinite streams as well.
+    ///
+    /// The compression applies to everything that has been added to the stream so far, so add any
+    /// start/stop emoji or chain more data before calling this.
+    pub fn with_compression(self) -> EncodeBytesAsEmoji<Compress<I>> {
+        EncodeBytesAsEmoji {
+            compressed: true,
+            ..self.map_iter(Compress::new)
+        }
+    }
+
+    /// The same encoder with a different `KnownLength` or `UnknownLength` marker, which can't be
+    /// changed with the struct update syntax.
+    fn with_length<L2>(self) -> EncodeBytesAsEmoji<I, L2> {
+        EncodeBytesAsEmoji {
+            iter: self.iter,
+            input_data: self.input_data,
+            defined_bits: self.defined_bits,
+            queued_values: self.queued_values,
+            alphabet: self.alphabet,
+            emit_header: self.emit_header,
+            checksum: self.checksum,
+            error_correction: self.error_correction,
+            unencoded_symbols: self.unencoded_symbols,
+            encoded_values: self.encoded_values,
+            compressed: self.compressed,
+            waiting_for_input: self.waiting_for_input,
+            only_bytes: self.only_bytes,
+            length: PhantomData,
         }
     }
 
+    /// Replace the underlying iterator while keeping the state of the encoder.
+    fn map_iter<I2, F>(self, f: F) -> EncodeBytesAsEmoji<I2>
+    where
+        I2: Iterator<Item = ByteInSequence>,
+        F: FnOnce(I) -> I2,
+    {
+        EncodeBytesAsEmoji {
+            iter: f(self.iter),
+            input_data: self.input_data,
+            defined_bits: self.defined_bits,
+            queued_values: self.queued_values,
+            alphabet: self.alphabet,
+            emit_header: self.emit_header,
+            checksum: self.checksum,
+            error_correction: self.error_correction,
+            unencoded_symbols: self.unencoded_symbols,
+            encoded_values: self.encoded_values,
+            compressed: self.compressed,
+            waiting_for_input: self.waiting_for_input,
+            only_bytes: false,
+            length: PhantomData,
+        }
+    }
+
+    pub fn get_mut(&mut self) -> &mut I {
+        &mut self.iter
+    }
+
+    /// The bytes that are going to be encoded, without the encoder around them.
+    pub fn into_inner(self) -> I {
+        self.iter
+    }
+
+    /// The same as `next`, except that running out of bytes means that more might come later
+    /// rather than that the data has ended. The last few bits, the checksum and the last error
+    /// correction block are held back instead of being written, so that encoding can pick up
+    /// where it left off once the underlying iterator has more bytes. Call `next` once the data
+    /// really has ended.
+    pub fn next_available(&mut self) -> Option<char> {
+        self.waiting_for_input = true;
+        let next = self.next();
+        self.waiting_for_input = false;
+        next
+    }
+
     /// When types have a size that is unknown at compile time it can be come ambiguous where one
     /// ends and one starts. E.g. if the tuple `("hello","world")` was just a series of bytes, how
     /// could we tell it apart from `("helloworld","")`?
@@ -84,38 +382,74 @@ where
     /// This function along with add_stop_emoji adds special emoji that signify the start and stop
     /// of a sequence of data whose length is unknown at compile time.
     pub fn add_start_emoji(self) -> EncodeBytesAsEmoji<Chain<Once<ByteInSequence>, I>> {
+        self.map_iter(|iter| once(ByteInSequence::SequenceStart).chain(iter))
+    }
+
+    pub fn add_stop_emoji(self) -> EncodeBytesAsEmoji<Chain<I, Once<ByteInSequence>>> {
+        self.map_iter(|iter| iter.chain(once(ByteInSequence::SequenceEnd)))
+    }
+
+    /// Put `name` in front of the value and the value in a sequence of its own. This is how the
+    /// fields of structs with `#[emojfuscate(evolvable)]` are encoded, so that a decoder can find
+    /// the fields it knows by their names and skip the ones it doesn't know.
+    pub fn evolvable_field(self, name: &'static str) -> EncodeBytesAsEmoji<EvolvableField<I>> {
+        name.emojfuscate_stream()
+            .chain_emoji_bytes(self.add_start_emoji().add_stop_emoji())
+    }
+
+    /// Put the underlying iterator in a `Box`, so that its type doesn't have to be spelled out.
+    /// The derive macros use this for fields with `#[emojfuscate(with = module)]`, where the type
+    /// is up to the module.
+    pub fn boxed<'a>(self) -> EncodeBytesAsEmoji<BoxedEmojiBytes<'a>>
+    where
+        I: 'a,
+    {
+        let only_bytes = self.only_bytes;
+
         EncodeBytesAsEmoji {
-            iter: once(ByteInSequence::SequenceStart).chain(self.iter),
-            input_data: self.input_data,
-            defined_bits: self.defined_bits,
-            queued_emoji: self.queued_emoji,
+            only_bytes,
+            ..self.map_iter(|iter| Box::new(iter) as BoxedEmojiBytes<'a>)
         }
     }
 
-    pub fn add_stop_emoji(self) -> EncodeBytesAsEmoji<Chain<I, Once<ByteInSequence>>> {
+    /// Add the fields of an enum variant if it's the chosen one. The derived `Emojfuscate` for
+    /// enums chains the fields of all of the variants this way so that every variant has the same
+    /// type, but only the chosen one is `Some` and ends up in the emoji.
+    pub fn chain_variant<I2>(
+        self,
+        variant: Option<EncodeBytesAsEmoji<I2>>,
+    ) -> EncodeBytesAsEmoji<Chain<I, VariantFields<I2>>>
+    where
+        I2: Iterator<Item = ByteInSequence>,
+    {
+        let only_bytes = match variant {
+            Some(ref variant) => self.only_bytes && variant.only_bytes,
+            None => self.only_bytes,
+        };
+
+        let variant = variant.map(|variant| variant.iter).into_iter().flatten();
+
         EncodeBytesAsEmoji {
-            iter: self.iter.chain(once(ByteInSequence::SequenceEnd)),
-            input_data: self.input_data,
-            defined_bits: self.defined_bits,
-            queued_emoji: self.queued_emoji,
+            only_bytes,
+            ..self.map_iter(|iter| iter.chain(variant))
         }
     }
 
     /// This is used to combine multiple streams of emoji into one. E.g. the Emojfuscate
     /// implementation for `(A, B)` for is just calling `emojfuscate_stream` on the two values in
     /// the tuple and then combining them:
-    pub fn chain_emoji_bytes<I2>(
+    pub fn chain_emoji_bytes<I2, L2>(
         self,
-        other: EncodeBytesAsEmoji<I2>,
+        other: EncodeBytesAsEmoji<I2, L2>,
     ) -> EncodeBytesAsEmoji<Chain<I, I2>>
     where
         I2: Iterator<Item = ByteInSequence>,
     {
+        let only_bytes = self.only_bytes && other.only_bytes;
+
         EncodeBytesAsEmoji {
-            iter: self.iter.chain(other.iter),
-            input_data: self.input_data,
-            defined_bits: self.defined_bits,
-            queued_emoji: self.queued_emoji,
+            only_bytes,
+            ..self.map_iter(|iter| iter.chain(other.iter))
         }
     }
 
@@ -141,39 +475,43 @@ where
     pub fn bypass_future_trait_implementation_compiler_error(
         self,
     ) -> EncodeBytesAsEmoji<IteratorWrapper<I>> {
+        let only_bytes = self.only_bytes;
+
         EncodeBytesAsEmoji {
-            iter: IteratorWrapper { iter: self.iter },
-            input_data: self.input_data,
-            defined_bits: self.defined_bits,
-            queued_emoji: self.queued_emoji,
+            only_bytes,
+            ..self.map_iter(|iter| IteratorWrapper { iter })
         }
     }
 }
 
-impl<I> Iterator for EncodeBytesAsEmoji<I>
+impl<I, L> EncodeBytesAsEmoji<I, L>
 where
     I: Iterator<Item = ByteInSequence>,
 {
-    type Item = char;
-    fn next(&mut self) -> Option<char> {
-        match self.queued_emoji.pop_front() {
-            Some(emoji) => return Some(emoji),
-            None => {}
+    /// Pack the bytes from the underlying iterator into 10 bit values and translate the start and
+    /// stop of sequences into their special values.
+    fn next_emoji_value(&mut self) -> Option<u16> {
+        if let Some(value) = self.queued_values.pop_front() {
+            return Some(value);
         }
 
         loop {
             let mb = self.iter.next();
             let b = match mb {
                 Some(ByteInSequence::Byte(b)) => b,
+                // the bits that don't fill up an emoji yet are kept for when there's more data
+                None if self.waiting_for_input => return None,
                 None => break,
                 Some(ByteInSequence::SequenceStart) => {
-                    let start_emoji = usize_to_emoji(usize::try_from(START_EMOJI_VALUE).unwrap());
-                    self.queued_emoji.push_back(start_emoji);
+                    self.queued_values.push_back(START_EMOJI_VALUE);
                     break;
                 }
                 Some(ByteInSequence::SequenceEnd) => {
-                    let stop_emoji = usize_to_emoji(usize::try_from(STOP_EMOJI_VALUE).unwrap());
-                    self.queued_emoji.push_back(stop_emoji);
+                    self.queued_values.push_back(STOP_EMOJI_VALUE);
+                    break;
+                }
+                Some(ByteInSequence::SyncMarker) => {
+                    self.queued_values.push_back(SYNC_EMOJI_VALUE);
                     break;
                 }
             };
@@ -192,35 +530,234 @@ where
             self.input_data = self.input_data ^ (emoji_index << bits_used);
             self.defined_bits -= BITS_PER_EMOJI;
 
-            return Some(usize_to_emoji(emoji_index));
+            return Some(emoji_index as u16);
         }
 
         // If we don't have enough bytes for another emoji we encode the difference in a special
         // emoji and stash away the remaining information so it will be returned on the next next()
         if self.defined_bits > 0 {
             let padding = BITS_PER_EMOJI - self.defined_bits;
-            let truncate_bits_emoji =
-                usize_to_emoji(usize::try_from(MAX_EMOJI_VALUE + padding).unwrap());
 
             self.defined_bits = 0;
-            let final_emoji = usize_to_emoji(self.input_data << padding);
+            let final_value = (self.input_data << padding) as u16;
 
             self.input_data = 0;
 
             // push to the front so they get in before the 'stop emoji' if it's set
-            self.queued_emoji.push_front(final_emoji);
-            self.queued_emoji.push_front(truncate_bits_emoji);
+            self.queued_values.push_front(final_value);
+            self.queued_values.push_front(MAX_EMOJI_VALUE + padding);
+        }
+
+        self.queued_values.pop_front()
+    }
+}
+
+impl<I, L> EncodeBytesAsEmoji<I, L>
+where
+    I: Iterator<Item = ByteInSequence>,
+{
+    /// The values of the emoji in the message, followed by the checksum if there is one.
+    fn next_message_value(&mut self) -> Option<u16> {
+        let value = match self.next_emoji_value() {
+            Some(value) => value,
+            None if self.waiting_for_input => return None,
+            None => {
+                // Once all the data has been encoded we put the checksum at the end. It's taken so
+                // that it's only written once and so that it doesn't include itself.
+                let checksum = self.checksum.take()?;
+                self.queued_values.extend(checksum.to_emoji_values());
+                CHECKSUM_EMOJI_VALUE
+            }
+        };
+
+        if let Some(checksum) = &mut self.checksum {
+            checksum.update(value);
+        }
+
+        Some(value)
+    }
+
+    /// Fill up a block with message values and add the parity symbols for it. Values that don't fit
+    /// in 10 bits are escaped, so one value can end up split across two blocks.
+    fn encode_block(&mut self, error_correction: ErrorCorrection) {
+        let data_symbols = usize::from(error_correction.data_symbols());
+
+        while self.unencoded_symbols.len() < data_symbols {
+            match self.next_message_value() {
+                Some(value) if value < ESCAPE_SYMBOL => self.unencoded_symbols.push(value),
+                Some(value) => {
+                    self.unencoded_symbols.push(ESCAPE_SYMBOL);
+                    self.unencoded_symbols.push(value - ESCAPE_SYMBOL);
+                }
+                None => break,
+            }
+        }
+
+        // only the last block can be shorter than the others
+        if self.unencoded_symbols.is_empty()
+            || (self.waiting_for_input && self.unencoded_symbols.len() < data_symbols)
+        {
+            return;
+        }
+
+        let block_length = core::cmp::min(data_symbols, self.unencoded_symbols.len());
+        let data: Vec<u16> = self.unencoded_symbols.drain(..block_length).collect();
+        let parity =
+            error_correction::parity(&data, usize::from(error_correction.parity_symbols()));
+
+        self.encoded_values.extend(data);
+        self.encoded_values.extend(parity);
+    }
+}
+
+impl<I, L> Iterator for EncodeBytesAsEmoji<I, L>
+where
+    I: Iterator<Item = ByteInSequence>,
+{
+    type Item = char;
+    fn next(&mut self) -> Option<char> {
+        if self.emit_header {
+            self.emit_header = false;
+
+            let mut flags = 0;
+            if self.checksum.is_some() {
+                flags |= FLAG_CHECKSUM;
+            }
+            if self.error_correction.is_some() {
+                flags |= FLAG_ERROR_CORRECTION;
+            }
+            if self.compressed {
+                flags |= FLAG_COMPRESSION;
+            }
+
+            let header = Header::new(self.alphabet, flags);
+            self.encoded_values.extend(header.to_emoji_values());
+
+            if let Some(error_correction) = self.error_correction {
+                self.encoded_values
+                    .extend(error_correction.to_emoji_values());
+            }
+
+            if let Some(checksum) = &mut self.checksum {
+                for value in self.encoded_values.iter() {
+                    checksum.update(*value);
+                }
+            }
+        }
+
+        if self.encoded_values.is_empty() {
+            match self.error_correction {
+                Some(error_correction) => self.encode_block(error_correction),
+                None => {
+                    let value = self.next_message_value()?;
+                    self.encoded_values.push_back(value);
+                }
+            }
+        }
+
+        let value = self.encoded_values.pop_front()?;
+        Some(self.alphabet.value_to_emoji(value))
+    }
+
+    /// Exact when the underlying iterator knows how many bytes are left and nothing but bytes are
+    /// encoded, without compression or error correction. Otherwise it's a bound.
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (message_lower, message_upper) = self.message_values_hint();
+
+        let (lower, upper) = match self.error_correction {
+            None => (message_lower, message_upper),
+            Some(error_correction) => {
+                let data_symbols = usize::from(error_correction.data_symbols());
+                let parity_symbols = usize::from(error_correction.parity_symbols());
+                let with_parity =
+                    |symbols: usize| symbols + symbols.div_ceil(data_symbols) * parity_symbols;
+
+                // values that don't fit in 10 bits are escaped into two symbols, which depends on
+                // the data
+                let unencoded = self.unencoded_symbols.len();
+                let upper = message_upper
+                    .and_then(|upper| upper.checked_mul(2))
+                    .and_then(|upper| upper.checked_add(unencoded))
+                    .map(with_parity);
+
+                (with_parity(unencoded + message_lower), upper)
+            }
+        };
+
+        let mut known = self.encoded_values.len();
+        if self.emit_header {
+            known += HEADER_LENGTH;
+
+            if self.error_correction.is_some() {
+                known += ERROR_CORRECTION_LENGTH;
+            }
+        }
+
+        (
+            lower.saturating_add(known),
+            upper.and_then(|upper| upper.checked_add(known)),
+        )
+    }
+}
+
+/// Encoders of bytes from an iterator that knows how many of them are left, with or without a
+/// header and checksum. Error correction and compression make the number of emoji depend on the
+/// data, so encoders that have them aren't an `ExactSizeIterator`:
+///
+/// ```compile_fail
+/// use emojfuscate::{EmojfuscateByteStream, ErrorCorrection};
+///
+/// let emoji = [1u8, 2, 3].into_iter().emojfuscate_byte_stream_no_start_or_stop();
+/// emoji.with_error_correction(ErrorCorrection::default()).len();
+/// ```
+impl<I> ExactSizeIterator for EncodeBytesAsEmoji<I, KnownLength> where
+    I: ExactSizeIterator<Item = ByteInSequence>
+{
+}
+
+impl<I, L> EncodeBytesAsEmoji<I, L>
+where
+    I: Iterator<Item = ByteInSequence>,
+{
+    /// How many more values `next_message_value` is going to return.
+    fn message_values_hint(&self) -> (usize, Option<usize>) {
+        let mut queued = self.queued_values.len();
+        if self.checksum.is_some() {
+            queued += 1 + CHECKSUM_LENGTH;
+        }
+
+        let (items_lower, items_upper) = self.iter.size_hint();
+        let defined_bits = usize::from(self.defined_bits);
+        let bits_per_emoji = usize::from(BITS_PER_EMOJI);
+        let bits_per_byte = usize::from(BITS_IN_A_BYTE);
+
+        if self.only_bytes && items_upper == Some(items_lower) {
+            let values = queued + packed_emoji_len(defined_bits + items_lower * bits_per_byte);
+
+            return (values, Some(values));
         }
 
-        return self.queued_emoji.pop_front();
+        let lower_bits = items_lower
+            .saturating_mul(bits_per_byte)
+            .saturating_add(defined_bits);
+
+        // a byte is at most one emoji, anything else is one emoji that can have two emoji of
+        // padding in front of it
+        let values_per_item = if self.only_bytes { 1 } else { 3 };
+        let upper = items_upper
+            .and_then(|items| items.checked_mul(values_per_item))
+            .and_then(|values| values.checked_add(queued + 2));
+
+        (queued + lower_bits / bits_per_emoji, upper)
     }
 }
 
 impl<I: Iterator<Item = u8>> EmojfuscateByteStream<Map<I, fn(u8) -> ByteInSequence>> for I {
     fn emojfuscate_byte_stream_no_start_or_stop(
         self,
-    ) -> EncodeBytesAsEmoji<Map<I, fn(u8) -> ByteInSequence>> {
-        EncodeBytesAsEmoji::new(self.map(ByteInSequence::Byte as fn(u8) -> ByteInSequence))
+    ) -> EncodeBytesAsEmoji<Map<I, fn(u8) -> ByteInSequence>, KnownLength> {
+        EncodeBytesAsEmoji::from_bytes(self.map(ByteInSequence::Byte as fn(u8) -> ByteInSequence))
+            .with_length()
     }
 }
 
@@ -238,13 +775,13 @@ impl<I: Iterator<Item = ByteInSequence>>
 
 impl Emojfuscate<Empty<ByteInSequence>> for () {
     fn emojfuscate_stream(self) -> EncodeBytesAsEmoji<Empty<ByteInSequence>> {
-        EncodeBytesAsEmoji::new(empty::<ByteInSequence>())
+        EncodeBytesAsEmoji::from_bytes(empty::<ByteInSequence>())
     }
 }
 
 impl Emojfuscate<Empty<ByteInSequence>> for &() {
     fn emojfuscate_stream(self) -> EncodeBytesAsEmoji<Empty<ByteInSequence>> {
-        EncodeBytesAsEmoji::new(empty::<ByteInSequence>())
+        EncodeBytesAsEmoji::from_bytes(empty::<ByteInSequence>())
     }
 }
 
@@ -274,152 +811,152 @@ impl Emojfuscate<IntoIter<ByteInSequence, 4>> for &char {
 
 impl Emojfuscate<Once<ByteInSequence>> for u8 {
     fn emojfuscate_stream(self) -> EncodeBytesAsEmoji<Once<ByteInSequence>> {
-        EncodeBytesAsEmoji::new(std::iter::once(ByteInSequence::Byte(self)))
+        EncodeBytesAsEmoji::from_bytes(core::iter::once(ByteInSequence::Byte(self)))
     }
 }
 
 impl Emojfuscate<Once<ByteInSequence>> for &u8 {
     fn emojfuscate_stream(self) -> EncodeBytesAsEmoji<Once<ByteInSequence>> {
-        EncodeBytesAsEmoji::new(std::iter::once(ByteInSequence::Byte(self.clone())))
+        EncodeBytesAsEmoji::from_bytes(core::iter::once(ByteInSequence::Byte(*self)))
     }
 }
 
 impl Emojfuscate<IntoIter<ByteInSequence, 2>> for u16 {
     fn emojfuscate_stream(self) -> EncodeBytesAsEmoji<IntoIter<ByteInSequence, 2>> {
-        EncodeBytesAsEmoji::new(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
+        EncodeBytesAsEmoji::from_bytes(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
     }
 }
 
 impl Emojfuscate<IntoIter<ByteInSequence, 2>> for &u16 {
     fn emojfuscate_stream(self) -> EncodeBytesAsEmoji<IntoIter<ByteInSequence, 2>> {
-        EncodeBytesAsEmoji::new(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
+        EncodeBytesAsEmoji::from_bytes(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
     }
 }
 
 impl Emojfuscate<IntoIter<ByteInSequence, 4>> for u32 {
     fn emojfuscate_stream(self) -> EncodeBytesAsEmoji<IntoIter<ByteInSequence, 4>> {
-        EncodeBytesAsEmoji::new(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
+        EncodeBytesAsEmoji::from_bytes(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
     }
 }
 
 impl Emojfuscate<IntoIter<ByteInSequence, 4>> for &u32 {
     fn emojfuscate_stream(self) -> EncodeBytesAsEmoji<IntoIter<ByteInSequence, 4>> {
-        EncodeBytesAsEmoji::new(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
+        EncodeBytesAsEmoji::from_bytes(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
     }
 }
 
 impl Emojfuscate<IntoIter<ByteInSequence, 8>> for u64 {
     fn emojfuscate_stream(self) -> EncodeBytesAsEmoji<IntoIter<ByteInSequence, 8>> {
-        EncodeBytesAsEmoji::new(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
+        EncodeBytesAsEmoji::from_bytes(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
     }
 }
 
 impl Emojfuscate<IntoIter<ByteInSequence, 8>> for &u64 {
     fn emojfuscate_stream(self) -> EncodeBytesAsEmoji<IntoIter<ByteInSequence, 8>> {
-        EncodeBytesAsEmoji::new(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
+        EncodeBytesAsEmoji::from_bytes(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
     }
 }
 
 impl Emojfuscate<IntoIter<ByteInSequence, 16>> for u128 {
     fn emojfuscate_stream(self) -> EncodeBytesAsEmoji<IntoIter<ByteInSequence, 16>> {
-        EncodeBytesAsEmoji::new(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
+        EncodeBytesAsEmoji::from_bytes(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
     }
 }
 
 impl Emojfuscate<IntoIter<ByteInSequence, 16>> for &u128 {
     fn emojfuscate_stream(self) -> EncodeBytesAsEmoji<IntoIter<ByteInSequence, 16>> {
-        EncodeBytesAsEmoji::new(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
+        EncodeBytesAsEmoji::from_bytes(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
     }
 }
 
 impl Emojfuscate<IntoIter<ByteInSequence, 1>> for i8 {
     fn emojfuscate_stream(self) -> EncodeBytesAsEmoji<IntoIter<ByteInSequence, 1>> {
-        EncodeBytesAsEmoji::new(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
+        EncodeBytesAsEmoji::from_bytes(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
     }
 }
 
 impl Emojfuscate<IntoIter<ByteInSequence, 1>> for &i8 {
     fn emojfuscate_stream(self) -> EncodeBytesAsEmoji<IntoIter<ByteInSequence, 1>> {
-        EncodeBytesAsEmoji::new(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
+        EncodeBytesAsEmoji::from_bytes(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
     }
 }
 
 impl Emojfuscate<IntoIter<ByteInSequence, 2>> for i16 {
     fn emojfuscate_stream(self) -> EncodeBytesAsEmoji<IntoIter<ByteInSequence, 2>> {
-        EncodeBytesAsEmoji::new(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
+        EncodeBytesAsEmoji::from_bytes(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
     }
 }
 
 impl Emojfuscate<IntoIter<ByteInSequence, 2>> for &i16 {
     fn emojfuscate_stream(self) -> EncodeBytesAsEmoji<IntoIter<ByteInSequence, 2>> {
-        EncodeBytesAsEmoji::new(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
+        EncodeBytesAsEmoji::from_bytes(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
     }
 }
 
 impl Emojfuscate<IntoIter<ByteInSequence, 4>> for i32 {
     fn emojfuscate_stream(self) -> EncodeBytesAsEmoji<IntoIter<ByteInSequence, 4>> {
-        EncodeBytesAsEmoji::new(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
+        EncodeBytesAsEmoji::from_bytes(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
     }
 }
 
 impl Emojfuscate<IntoIter<ByteInSequence, 4>> for &i32 {
     fn emojfuscate_stream(self) -> EncodeBytesAsEmoji<IntoIter<ByteInSequence, 4>> {
-        EncodeBytesAsEmoji::new(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
+        EncodeBytesAsEmoji::from_bytes(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
     }
 }
 
 impl Emojfuscate<IntoIter<ByteInSequence, 8>> for i64 {
     fn emojfuscate_stream(self) -> EncodeBytesAsEmoji<IntoIter<ByteInSequence, 8>> {
-        EncodeBytesAsEmoji::new(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
+        EncodeBytesAsEmoji::from_bytes(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
     }
 }
 
 impl Emojfuscate<IntoIter<ByteInSequence, 8>> for &i64 {
     fn emojfuscate_stream(self) -> EncodeBytesAsEmoji<IntoIter<ByteInSequence, 8>> {
-        EncodeBytesAsEmoji::new(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
+        EncodeBytesAsEmoji::from_bytes(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
     }
 }
 
 impl Emojfuscate<IntoIter<ByteInSequence, 16>> for i128 {
     fn emojfuscate_stream(self) -> EncodeBytesAsEmoji<IntoIter<ByteInSequence, 16>> {
-        EncodeBytesAsEmoji::new(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
+        EncodeBytesAsEmoji::from_bytes(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
     }
 }
 
 impl Emojfuscate<IntoIter<ByteInSequence, 16>> for &i128 {
     fn emojfuscate_stream(self) -> EncodeBytesAsEmoji<IntoIter<ByteInSequence, 16>> {
-        EncodeBytesAsEmoji::new(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
+        EncodeBytesAsEmoji::from_bytes(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
     }
 }
 
 impl Emojfuscate<IntoIter<ByteInSequence, 4>> for f32 {
     fn emojfuscate_stream(self) -> EncodeBytesAsEmoji<IntoIter<ByteInSequence, 4>> {
-        EncodeBytesAsEmoji::new(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
+        EncodeBytesAsEmoji::from_bytes(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
     }
 }
 
 impl Emojfuscate<IntoIter<ByteInSequence, 4>> for &f32 {
     fn emojfuscate_stream(self) -> EncodeBytesAsEmoji<IntoIter<ByteInSequence, 4>> {
-        EncodeBytesAsEmoji::new(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
+        EncodeBytesAsEmoji::from_bytes(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
     }
 }
 
 impl Emojfuscate<IntoIter<ByteInSequence, 8>> for f64 {
     fn emojfuscate_stream(self) -> EncodeBytesAsEmoji<IntoIter<ByteInSequence, 8>> {
-        EncodeBytesAsEmoji::new(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
+        EncodeBytesAsEmoji::from_bytes(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
     }
 }
 
 impl Emojfuscate<IntoIter<ByteInSequence, 8>> for &f64 {
     fn emojfuscate_stream(self) -> EncodeBytesAsEmoji<IntoIter<ByteInSequence, 8>> {
-        EncodeBytesAsEmoji::new(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
+        EncodeBytesAsEmoji::from_bytes(self.to_be_bytes().map(ByteInSequence::Byte).into_iter())
     }
 }
 
 impl<'a>
     Emojfuscate<
         Chain<
-            Chain<Once<ByteInSequence>, Map<std::str::Bytes<'a>, fn(u8) -> ByteInSequence>>,
+            Chain<Once<ByteInSequence>, Map<core::str::Bytes<'a>, fn(u8) -> ByteInSequence>>,
             Once<ByteInSequence>,
         >,
     > for &'a str
@@ -428,7 +965,7 @@ impl<'a>
         self,
     ) -> EncodeBytesAsEmoji<
         Chain<
-            Chain<Once<ByteInSequence>, Map<std::str::Bytes<'a>, fn(u8) -> ByteInSequence>>,
+            Chain<Once<ByteInSequence>, Map<core::str::Bytes<'a>, fn(u8) -> ByteInSequence>>,
             Once<ByteInSequence>,
         >,
     > {
@@ -439,7 +976,7 @@ impl<'a>
 impl
     Emojfuscate<
         Chain<
-            Chain<Once<ByteInSequence>, Map<std::vec::IntoIter<u8>, fn(u8) -> ByteInSequence>>,
+            Chain<Once<ByteInSequence>, Map<alloc::vec::IntoIter<u8>, fn(u8) -> ByteInSequence>>,
             Once<ByteInSequence>,
         >,
     > for String
@@ -448,7 +985,7 @@ impl
         self,
     ) -> EncodeBytesAsEmoji<
         Chain<
-            Chain<Once<ByteInSequence>, Map<std::vec::IntoIter<u8>, fn(u8) -> ByteInSequence>>,
+            Chain<Once<ByteInSequence>, Map<alloc::vec::IntoIter<u8>, fn(u8) -> ByteInSequence>>,
             Once<ByteInSequence>,
         >,
     > {
@@ -461,7 +998,7 @@ impl<'a>
         Chain<
             Chain<
                 Once<ByteInSequence>,
-                Map<std::iter::Copied<std::slice::Iter<'a, u8>>, fn(u8) -> ByteInSequence>,
+                Map<core::iter::Copied<core::slice::Iter<'a, u8>>, fn(u8) -> ByteInSequence>,
             >,
             Once<ByteInSequence>,
         >,
@@ -473,7 +1010,7 @@ impl<'a>
         Chain<
             Chain<
                 Once<ByteInSequence>,
-                Map<std::iter::Copied<std::slice::Iter<'a, u8>>, fn(u8) -> ByteInSequence>,
+                Map<core::iter::Copied<core::slice::Iter<'a, u8>>, fn(u8) -> ByteInSequence>,
             >,
             Once<ByteInSequence>,
         >,
@@ -482,29 +1019,53 @@ impl<'a>
     }
 }
 
-impl Emojfuscate<Map<std::array::IntoIter<u8, 16>, fn(u8) -> ByteInSequence>> for Uuid {
+// emoji can't borrow from a Cow, so borrowed strings are copied to have the same type as owned
+// ones
+impl<'a>
+    Emojfuscate<
+        Chain<
+            Chain<Once<ByteInSequence>, Map<alloc::vec::IntoIter<u8>, fn(u8) -> ByteInSequence>>,
+            Once<ByteInSequence>,
+        >,
+    > for Cow<'a, str>
+{
+    fn emojfuscate_stream(
+        self,
+    ) -> EncodeBytesAsEmoji<
+        Chain<
+            Chain<Once<ByteInSequence>, Map<alloc::vec::IntoIter<u8>, fn(u8) -> ByteInSequence>>,
+            Once<ByteInSequence>,
+        >,
+    > {
+        self.into_owned().emojfuscate_stream()
+    }
+}
+
+impl Emojfuscate<Map<core::array::IntoIter<u8, 16>, fn(u8) -> ByteInSequence>> for Uuid {
     fn emojfuscate_stream(
         self,
-    ) -> EncodeBytesAsEmoji<Map<std::array::IntoIter<u8, 16>, fn(u8) -> ByteInSequence>> {
+    ) -> EncodeBytesAsEmoji<Map<core::array::IntoIter<u8, 16>, fn(u8) -> ByteInSequence>> {
         return self
             .into_bytes()
             .into_iter()
-            .emojfuscate_byte_stream_no_start_or_stop();
+            .emojfuscate_byte_stream_no_start_or_stop()
+            .with_length();
     }
 }
 
-impl Emojfuscate<Map<std::array::IntoIter<u8, 16>, fn(u8) -> ByteInSequence>> for &Uuid {
+impl Emojfuscate<Map<core::array::IntoIter<u8, 16>, fn(u8) -> ByteInSequence>> for &Uuid {
     fn emojfuscate_stream(
         self,
-    ) -> EncodeBytesAsEmoji<Map<std::array::IntoIter<u8, 16>, fn(u8) -> ByteInSequence>> {
+    ) -> EncodeBytesAsEmoji<Map<core::array::IntoIter<u8, 16>, fn(u8) -> ByteInSequence>> {
         return self
             .into_bytes()
             .into_iter()
-            .emojfuscate_byte_stream_no_start_or_stop();
+            .emojfuscate_byte_stream_no_start_or_stop()
+            .with_length();
     }
 }
 
-impl<A, IA, const S: usize> Emojfuscate<FlatMap<std::array::IntoIter<A, S>, IA, fn(A) -> IA>>
+impl<A, IA, const S: usize> Emojfuscate<FlatMap<core::array::IntoIter<A, S>, IA, fn(A) -> IA>>
     for [A; S]
 where
     A: Emojfuscate<IA>,
@@ -512,7 +1073,7 @@ where
 {
     fn emojfuscate_stream(
         self,
-    ) -> EncodeBytesAsEmoji<FlatMap<std::array::IntoIter<A, S>, IA, fn(A) -> IA>> {
+    ) -> EncodeBytesAsEmoji<FlatMap<core::array::IntoIter<A, S>, IA, fn(A) -> IA>> {
         let iterator = self
             .into_iter()
             .flat_map(get_emojfuscate_iter as fn(A) -> IA);
@@ -532,7 +1093,7 @@ where
 impl<A, IA>
     Emojfuscate<
         Chain<
-            Chain<Once<ByteInSequence>, FlatMap<std::vec::IntoIter<A>, IA, fn(A) -> IA>>,
+            Chain<Once<ByteInSequence>, FlatMap<alloc::vec::IntoIter<A>, IA, fn(A) -> IA>>,
             Once<ByteInSequence>,
         >,
     > for Vec<A>
@@ -544,7 +1105,7 @@ where
         self,
     ) -> EncodeBytesAsEmoji<
         Chain<
-            Chain<Once<ByteInSequence>, FlatMap<std::vec::IntoIter<A>, IA, fn(A) -> IA>>,
+            Chain<Once<ByteInSequence>, FlatMap<alloc::vec::IntoIter<A>, IA, fn(A) -> IA>>,
             Once<ByteInSequence>,
         >,
     > {
@@ -552,6 +1113,67 @@ where
     }
 }
 
+// laid out like a Vec
+impl<'a, A, IA>
+    Emojfuscate<
+        Chain<
+            Chain<Once<ByteInSequence>, FlatMap<core::slice::Iter<'a, A>, IA, fn(&'a A) -> IA>>,
+            Once<ByteInSequence>,
+        >,
+    > for &'a [A]
+where
+    &'a A: Emojfuscate<IA>,
+    IA: Iterator<Item = ByteInSequence>,
+{
+    fn emojfuscate_stream(
+        self,
+    ) -> EncodeBytesAsEmoji<
+        Chain<
+            Chain<Once<ByteInSequence>, FlatMap<core::slice::Iter<'a, A>, IA, fn(&'a A) -> IA>>,
+            Once<ByteInSequence>,
+        >,
+    > {
+        self.iter().emojfuscate_stream()
+    }
+}
+
+// like `Cow<str>`, borrowed slices are copied
+impl<'a, A, IA>
+    Emojfuscate<
+        Chain<
+            Chain<Once<ByteInSequence>, FlatMap<alloc::vec::IntoIter<A>, IA, fn(A) -> IA>>,
+            Once<ByteInSequence>,
+        >,
+    > for Cow<'a, [A]>
+where
+    A: Clone + Emojfuscate<IA>,
+    IA: Iterator<Item = ByteInSequence>,
+{
+    fn emojfuscate_stream(
+        self,
+    ) -> EncodeBytesAsEmoji<
+        Chain<
+            Chain<Once<ByteInSequence>, FlatMap<alloc::vec::IntoIter<A>, IA, fn(A) -> IA>>,
+            Once<ByteInSequence>,
+        >,
+    > {
+        self.into_owned().emojfuscate_stream()
+    }
+}
+
+// laid out like the value in the box, the wrapper keeps it apart from the impl for iterators
+impl<A, I> Emojfuscate<IteratorWrapper<I>> for Box<A>
+where
+    A: Emojfuscate<I>,
+    I: Iterator<Item = ByteInSequence>,
+{
+    fn emojfuscate_stream(self) -> EncodeBytesAsEmoji<IteratorWrapper<I>> {
+        (*self)
+            .emojfuscate_stream()
+            .bypass_future_trait_implementation_compiler_error()
+    }
+}
+
 impl<I, A, IA>
     Emojfuscate<
         Chain<Chain<Once<ByteInSequence>, FlatMap<I, IA, fn(A) -> IA>>, Once<ByteInSequence>>,
@@ -571,7 +1193,98 @@ where
     }
 }
 
-impl<A, IA> Emojfuscate<Chain<Once<ByteInSequence>, Flatten<std::option::IntoIter<IA>>>>
+/// Streams are normally unusable after the first bad value, since there's no telling where the
+/// next value starts. Sync markers fix that: they're put between values so that a decoder can skip
+/// ahead to the next one after an error, or start decoding a stream that is already underway. See
+/// `DemojfuscateIterator::recover_from_errors` and `DemojfuscateIterator::join_midway`.
+pub trait EmojfuscateWithSyncMarkers<A, IA>
+where
+    Self: Iterator<Item = A> + Sized,
+    A: Emojfuscate<IA>,
+    IA: Iterator<Item = ByteInSequence>,
+{
+    /// The same as `emojfuscate_stream`, but with a sync marker before every `interval`th value.
+    /// Panics if `interval` is zero.
+    fn emojfuscate_stream_with_sync_markers(
+        self,
+        interval: usize,
+    ) -> EncodeBytesAsEmoji<SyncMarkers<Self, A, IA>> {
+        assert!(interval > 0, "the sync marker interval can't be zero");
+
+        EncodeBytesAsEmoji::new(SyncMarkers {
+            iter: Some(self),
+            started: false,
+            interval,
+            // the first value gets a sync marker as well
+            values_since_sync_marker: interval,
+            current: None,
+        })
+    }
+}
+
+impl<I, A, IA> EmojfuscateWithSyncMarkers<A, IA> for I
+where
+    I: Iterator<Item = A>,
+    A: Emojfuscate<IA>,
+    IA: Iterator<Item = ByteInSequence>,
+{
+}
+
+/// The bytes of the values in a stream with a sync marker before every `interval`th value. Like
+/// any other stream it starts and ends with the start and stop of a sequence.
+pub struct SyncMarkers<I, A, IA>
+where
+    I: Iterator<Item = A>,
+    A: Emojfuscate<IA>,
+    IA: Iterator<Item = ByteInSequence>,
+{
+    // this is taken once the stream has ended
+    iter: Option<I>,
+    started: bool,
+    interval: usize,
+    values_since_sync_marker: usize,
+    current: Option<IA>,
+}
+
+impl<I, A, IA> Iterator for SyncMarkers<I, A, IA>
+where
+    I: Iterator<Item = A>,
+    A: Emojfuscate<IA>,
+    IA: Iterator<Item = ByteInSequence>,
+{
+    type Item = ByteInSequence;
+    fn next(&mut self) -> Option<ByteInSequence> {
+        if !self.started {
+            self.started = true;
+            return Some(ByteInSequence::SequenceStart);
+        }
+
+        loop {
+            if let Some(b) = self.current.as_mut().and_then(Iterator::next) {
+                return Some(b);
+            }
+
+            let value = match self.iter.as_mut()?.next() {
+                Some(value) => value,
+                None => {
+                    self.iter = None;
+                    return Some(ByteInSequence::SequenceEnd);
+                }
+            };
+
+            self.current = Some(get_emojfuscate_iter(value));
+
+            if self.values_since_sync_marker == self.interval {
+                self.values_since_sync_marker = 1;
+                return Some(ByteInSequence::SyncMarker);
+            }
+
+            self.values_since_sync_marker += 1;
+        }
+    }
+}
+
+impl<A, IA> Emojfuscate<Chain<Once<ByteInSequence>, Flatten<core::option::IntoIter<IA>>>>
     for Option<A>
 where
     A: Emojfuscate<IA>,
@@ -579,7 +1292,7 @@ where
 {
     fn emojfuscate_stream(
         self,
-    ) -> EncodeBytesAsEmoji<Chain<Once<ByteInSequence>, Flatten<std::option::IntoIter<IA>>>> {
+    ) -> EncodeBytesAsEmoji<Chain<Once<ByteInSequence>, Flatten<core::option::IntoIter<IA>>>> {
         let constructor_discriminator = match self {
             None => 0u8,
             Some(_) => 1,
@@ -596,23 +1309,25 @@ where
     }
 }
 
-impl<A, B, IA, IB> Emojfuscate<Chain<Chain<Once<ByteInSequence>, IA>, IB>> for Result<A, B>
+// laid out like a derived enum with the variants `Ok(A)` and `Err(B)`
+impl<A, B, IA, IB>
+    Emojfuscate<Chain<Chain<VariantTag, VariantFields<IA>>, VariantFields<IB>>> for Result<A, B>
 where
-    Option<A>: Emojfuscate<IA>,
-    Option<B>: Emojfuscate<IB>,
+    A: Emojfuscate<IA>,
+    B: Emojfuscate<IB>,
     IA: Iterator<Item = ByteInSequence>,
     IB: Iterator<Item = ByteInSequence>,
 {
-    fn emojfuscate_stream(self) -> EncodeBytesAsEmoji<Chain<Chain<Once<ByteInSequence>, IA>, IB>> {
+    fn emojfuscate_stream(
+        self,
+    ) -> EncodeBytesAsEmoji<Chain<Chain<VariantTag, VariantFields<IA>>, VariantFields<IB>>> {
         match self {
-            Ok(x) => 0u8
-                .emojfuscate_stream()
-                .chain_emoji_bytes(Some(x).emojfuscate_stream())
-                .chain_emoji_bytes(None::<B>.emojfuscate_stream()),
-            Err(x) => 1u8
-                .emojfuscate_stream()
-                .chain_emoji_bytes(None::<A>.emojfuscate_stream())
-                .chain_emoji_bytes(Some(x).emojfuscate_stream()),
+            Ok(x) => EncodeBytesAsEmoji::variant_tag(0)
+                .chain_variant(Some(x.emojfuscate_stream()))
+                .chain_variant(None),
+            Err(x) => EncodeBytesAsEmoji::variant_tag(1)
+                .chain_variant(None)
+                .chain_variant(Some(x.emojfuscate_stream())),
         }
     }
 }
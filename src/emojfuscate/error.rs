@@ -0,0 +1,250 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use super::constants::ByteInSequence;
+use super::error_correction::ErrorCorrectionError;
+
+/// Something that went wrong when demojfuscating, along with where it went wrong.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FromEmojiError {
+    pub kind: FromEmojiErrorKind,
+    /// The number of emoji before the one where decoding failed. White space isn't counted.
+    pub emoji_offset: usize,
+    /// The number of bytes of input before the emoji where decoding failed.
+    pub byte_offset: usize,
+    /// What the decoder was looking for, if it was looking for something in particular.
+    pub expected: Option<&'static str>,
+    /// What the decoder found instead.
+    pub found: Option<Token>,
+    /// The type that was being constructed when the error happened. For nested types this is the
+    /// outermost one, `path` says where in it the error happened.
+    pub type_name: Option<&'static str>,
+    pub path: Vec<PathSegment>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum FromEmojiErrorKind {
+    InvalidUtf8,
+    InputIsNotAnEmoji(char),
+    NotEnoughEmoji,
+    UnexpectedInput,
+    MissingSequenceStart,
+    UnexpectedSequenceStart,
+    UnexpectedSequenceEnd,
+    UnsupportedVersion(u16),
+    UnsupportedHeaderFlags(u16),
+    AlphabetMismatch,
+    MissingHeader,
+    MissingChecksum,
+    ChecksumMismatch,
+    TooManyErrors,
+    UnexpectedSyncMarker,
+    InvalidChar(u32),
+    InvalidString,
+    InvalidCompressedData,
+    InvalidEscapedValue(u16),
+    InvalidErrorCorrection(ErrorCorrectionError),
+    /// The tag of an enum variant that the type doesn't have.
+    UnknownVariant(u32),
+    /// A field of an evolvable struct that has no default was not in the emoji.
+    MissingField(&'static str),
+    /// Values of recursive types were nested deeper than the limit, see
+    /// `DecodeEmojiToBytes::with_nesting_limit`.
+    TooDeeplyNested,
+    /// An error from a serde `Deserialize` implementation.
+    Custom(String),
+}
+
+/// The smallest pieces of decoded data, this is what the `expected` and `found` of an error refer
+/// to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Token {
+    Byte(u8),
+    SequenceStart,
+    SequenceEnd,
+    SyncMarker,
+    /// An emoji that doesn't hold data, e.g. the header emoji.
+    Emoji(u16),
+    EndOfInput,
+}
+
+/// One step on the way from the outermost type to the value that couldn't be constructed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PathSegment {
+    /// A named field, e.g. `.name`
+    Field(&'static str),
+    /// A field of a tuple or a tuple struct, e.g. `.0`
+    Position(usize),
+    /// An element of a `Vec` or an array, e.g. `[3]`
+    Index(usize),
+    /// An enum variant, e.g. `::Circle`
+    Variant(&'static str),
+}
+
+impl FromEmojiError {
+    pub fn new(kind: FromEmojiErrorKind) -> Self {
+        FromEmojiError {
+            kind,
+            emoji_offset: 0,
+            byte_offset: 0,
+            expected: None,
+            found: None,
+            type_name: None,
+            path: Vec::new(),
+        }
+    }
+
+    pub fn expected(self, expected: &'static str, found: Token) -> Self {
+        FromEmojiError {
+            expected: Some(expected),
+            found: Some(found),
+            ..self
+        }
+    }
+
+    /// Record that the error happened while constructing a `type_name` value.
+    pub fn in_type(self, type_name: &'static str) -> Self {
+        FromEmojiError {
+            type_name: Some(type_name),
+            ..self
+        }
+    }
+
+    /// Record that the error happened inside `segment` of a `type_name` value. This is called on
+    /// the way out of nested types, so the segment goes in front of the ones that are already
+    /// there.
+    pub fn within(mut self, type_name: &'static str, segment: PathSegment) -> Self {
+        self.path.insert(0, segment);
+        self.in_type(type_name)
+    }
+
+    /// Where in the value the error happened, e.g. `Person.name` or `Vec[3].0`.
+    pub fn path_string(&self) -> String {
+        let mut path = self.type_name.unwrap_or_default().to_string();
+
+        for segment in &self.path {
+            path.push_str(&segment.to_string());
+        }
+
+        path
+    }
+}
+
+impl From<FromEmojiErrorKind> for FromEmojiError {
+    fn from(kind: FromEmojiErrorKind) -> Self {
+        FromEmojiError::new(kind)
+    }
+}
+
+impl From<Option<ByteInSequence>> for Token {
+    fn from(byte_in_sequence: Option<ByteInSequence>) -> Self {
+        match byte_in_sequence {
+            Some(ByteInSequence::Byte(b)) => Token::Byte(b),
+            Some(ByteInSequence::SequenceStart) => Token::SequenceStart,
+            Some(ByteInSequence::SequenceEnd) => Token::SequenceEnd,
+            Some(ByteInSequence::SyncMarker) => Token::SyncMarker,
+            None => Token::EndOfInput,
+        }
+    }
+}
+
+impl fmt::Display for FromEmojiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+
+        if self.type_name.is_some() || !self.path.is_empty() {
+            write!(f, " in {}", self.path_string())?;
+        }
+
+        write!(
+            f,
+            " at emoji {} (byte {})",
+            self.emoji_offset, self.byte_offset
+        )?;
+
+        match (self.expected, self.found) {
+            (Some(expected), Some(found)) => write!(f, ": expected {}, found {}", expected, found),
+            (Some(expected), None) => write!(f, ": expected {}", expected),
+            (None, Some(found)) => write!(f, ": found {}", found),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+impl core::error::Error for FromEmojiError {}
+
+impl fmt::Display for FromEmojiErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FromEmojiErrorKind::InvalidUtf8 => write!(f, "invalid UTF-8"),
+            FromEmojiErrorKind::InputIsNotAnEmoji(c) => {
+                write!(f, "{:?} is not an emoji in the alphabet", c)
+            }
+            FromEmojiErrorKind::NotEnoughEmoji => write!(f, "not enough emoji"),
+            FromEmojiErrorKind::UnexpectedInput => write!(f, "unexpected input"),
+            FromEmojiErrorKind::MissingSequenceStart => write!(f, "missing start of sequence"),
+            FromEmojiErrorKind::UnexpectedSequenceStart => {
+                write!(f, "unexpected start of sequence")
+            }
+            FromEmojiErrorKind::UnexpectedSequenceEnd => write!(f, "unexpected end of sequence"),
+            FromEmojiErrorKind::UnsupportedVersion(version) => {
+                write!(f, "unsupported format version {}", version)
+            }
+            FromEmojiErrorKind::UnsupportedHeaderFlags(flags) => {
+                write!(f, "unsupported header flags {:#b}", flags)
+            }
+            FromEmojiErrorKind::AlphabetMismatch => {
+                write!(f, "the emoji were made with a different alphabet")
+            }
+            FromEmojiErrorKind::MissingHeader => write!(f, "missing header"),
+            FromEmojiErrorKind::MissingChecksum => write!(f, "missing checksum"),
+            FromEmojiErrorKind::ChecksumMismatch => write!(f, "checksum mismatch"),
+            FromEmojiErrorKind::TooManyErrors => write!(f, "too many errors to repair"),
+            FromEmojiErrorKind::UnexpectedSyncMarker => write!(f, "unexpected sync marker"),
+            FromEmojiErrorKind::InvalidChar(n) => write!(f, "{:#x} is not a valid char", n),
+            FromEmojiErrorKind::InvalidString => write!(f, "string is not valid UTF-8"),
+            FromEmojiErrorKind::InvalidCompressedData => {
+                write!(f, "compressed data refers to data that doesn't exist")
+            }
+            FromEmojiErrorKind::InvalidEscapedValue(value) => {
+                write!(f, "escaped value {} is outside of the alphabet", value)
+            }
+            FromEmojiErrorKind::InvalidErrorCorrection(err) => {
+                write!(
+                    f,
+                    "invalid error correction parameters in header: {:?}",
+                    err
+                )
+            }
+            FromEmojiErrorKind::UnknownVariant(tag) => write!(f, "unknown variant tag {}", tag),
+            FromEmojiErrorKind::MissingField(field) => write!(f, "missing field {}", field),
+            FromEmojiErrorKind::TooDeeplyNested => write!(f, "values are nested too deeply"),
+            FromEmojiErrorKind::Custom(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Byte(b) => write!(f, "byte {}", b),
+            Token::SequenceStart => write!(f, "start of sequence"),
+            Token::SequenceEnd => write!(f, "end of sequence"),
+            Token::SyncMarker => write!(f, "sync marker"),
+            Token::Emoji(value) => write!(f, "emoji with value {}", value),
+            Token::EndOfInput => write!(f, "end of input"),
+        }
+    }
+}
+
+impl fmt::Display for PathSegment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PathSegment::Field(name) => write!(f, ".{}", name),
+            PathSegment::Position(position) => write!(f, ".{}", position),
+            PathSegment::Index(index) => write!(f, "[{}]", index),
+            PathSegment::Variant(name) => write!(f, "::{}", name),
+        }
+    }
+}
@@ -0,0 +1,359 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use super::constants::MAX_EMOJI_VALUE;
+
+/// The number of emoji after the header that hold the error correction parameters: the number of
+/// data symbols and the number of parity symbols per block.
+pub const ERROR_CORRECTION_LENGTH: usize = 2;
+
+/// Reed-Solomon codes over a field with 1024 elements can't have blocks longer than this.
+pub const MAX_BLOCK_LENGTH: usize = MAX_EMOJI_VALUE as usize - 1;
+
+/// The symbols in a block can only hold 10 bits, so the values of the special emoji (and of the
+/// escape symbol itself) are written as the escape symbol followed by the value minus the escape
+/// symbol.
+pub const ESCAPE_SYMBOL: u16 = MAX_EMOJI_VALUE - 1;
+
+// x^10 + x^3 + 1
+const PRIMITIVE_POLYNOMIAL: usize = 0b100_0000_1001;
+
+/// Powers of the generator of GF(2^10). The table is twice as long as the field so that adding two
+/// logarithms never needs to wrap around.
+const EXP: [u16; 2 * MAX_BLOCK_LENGTH] = {
+    let mut table = [0u16; 2 * MAX_BLOCK_LENGTH];
+    let mut x = 1usize;
+    let mut index = 0;
+
+    while index < 2 * MAX_BLOCK_LENGTH {
+        table[index] = x as u16;
+        x <<= 1;
+        if x & MAX_EMOJI_VALUE as usize != 0 {
+            x ^= PRIMITIVE_POLYNOMIAL;
+        }
+        index += 1;
+    }
+
+    table
+};
+
+const LOG: [u16; MAX_EMOJI_VALUE as usize] = {
+    let mut table = [0u16; MAX_EMOJI_VALUE as usize];
+    let mut index = 0;
+
+    while index < MAX_BLOCK_LENGTH {
+        table[EXP[index] as usize] = index as u16;
+        index += 1;
+    }
+
+    table
+};
+
+/// Settings for Reed-Solomon forward error correction. The emoji are split into blocks of
+/// `data_symbols` emoji and every block gets `parity_symbols` extra emoji. Each block can then be
+/// repaired as long as twice the number of wrong emoji plus the number of unreadable emoji (e.g.
+/// ones that aren't part of the alphabet) is at most `parity_symbols`.
+///
+/// Emoji that are lost altogether can't be repaired, since there is no way to tell where they were.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ErrorCorrection {
+    data_symbols: u16,
+    parity_symbols: u16,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ErrorCorrectionError {
+    NoDataSymbols,
+    NoParitySymbols,
+    BlockTooLong { max: usize, found: usize },
+}
+
+impl ErrorCorrection {
+    pub fn new(
+        data_symbols: u16,
+        parity_symbols: u16,
+    ) -> Result<ErrorCorrection, ErrorCorrectionError> {
+        if data_symbols == 0 {
+            return Err(ErrorCorrectionError::NoDataSymbols);
+        }
+
+        if parity_symbols == 0 {
+            return Err(ErrorCorrectionError::NoParitySymbols);
+        }
+
+        let block_length = usize::from(data_symbols) + usize::from(parity_symbols);
+        if block_length > MAX_BLOCK_LENGTH {
+            return Err(ErrorCorrectionError::BlockTooLong {
+                max: MAX_BLOCK_LENGTH,
+                found: block_length,
+            });
+        }
+
+        Ok(ErrorCorrection {
+            data_symbols,
+            parity_symbols,
+        })
+    }
+
+    pub fn data_symbols(&self) -> u16 {
+        self.data_symbols
+    }
+
+    pub fn parity_symbols(&self) -> u16 {
+        self.parity_symbols
+    }
+
+    /// The number of wrong emoji that can be repaired in every block.
+    pub fn correctable_errors(&self) -> u16 {
+        self.parity_symbols / 2
+    }
+
+    pub fn to_emoji_values(self) -> [u16; ERROR_CORRECTION_LENGTH] {
+        [self.data_symbols, self.parity_symbols]
+    }
+}
+
+impl Default for ErrorCorrection {
+    /// Blocks of 64 emoji that can each have up to 4 wrong emoji.
+    fn default() -> Self {
+        ErrorCorrection {
+            data_symbols: 64,
+            parity_symbols: 8,
+        }
+    }
+}
+
+/// Calculate the parity symbols that follow `data` in a block.
+pub fn parity(data: &[u16], parity_symbols: usize) -> Vec<u16> {
+    let generator = generator_polynomial(parity_symbols);
+    let mut remainder = data.to_vec();
+    remainder.resize(data.len() + parity_symbols, 0);
+
+    for index in 0..data.len() {
+        let coefficient = remainder[index];
+        if coefficient == 0 {
+            continue;
+        }
+
+        for (offset, g) in generator.iter().enumerate().skip(1) {
+            remainder[index + offset] ^= mul(*g, coefficient);
+        }
+    }
+
+    remainder.split_off(data.len())
+}
+
+/// Repair a block in place. `erasures` are the positions of symbols that are known to be wrong.
+/// Returns the number of symbols that were repaired, or `None` if there were too many errors.
+pub fn correct(block: &mut [u16], parity_symbols: usize, erasures: &[usize]) -> Option<usize> {
+    if erasures.len() > parity_symbols || block.len() <= parity_symbols {
+        return None;
+    }
+
+    for position in erasures {
+        block[*position] = 0;
+    }
+
+    let syndromes = syndromes(block, parity_symbols);
+    if syndromes.iter().all(|s| *s == 0) {
+        return Some(erasures.len());
+    }
+
+    let forney_syndromes = forney_syndromes(&syndromes, erasures, block.len());
+    let mut error_locator = error_locator(&forney_syndromes, parity_symbols, erasures.len())?;
+    error_locator.reverse();
+
+    let mut errata = erasures.to_vec();
+    errata.extend(find_errors(&error_locator, block.len())?);
+
+    correct_errata(block, &syndromes, &errata)?;
+
+    if syndromes_are_zero(block, parity_symbols) {
+        Some(errata.len())
+    } else {
+        None
+    }
+}
+
+fn mul(a: u16, b: u16) -> u16 {
+    if a == 0 || b == 0 {
+        return 0;
+    }
+
+    EXP[usize::from(LOG[usize::from(a)]) + usize::from(LOG[usize::from(b)])]
+}
+
+fn div(a: u16, b: u16) -> u16 {
+    if a == 0 {
+        return 0;
+    }
+
+    EXP[usize::from(LOG[usize::from(a)]) + MAX_BLOCK_LENGTH - usize::from(LOG[usize::from(b)])]
+}
+
+fn inverse(a: u16) -> u16 {
+    EXP[MAX_BLOCK_LENGTH - usize::from(LOG[usize::from(a)])]
+}
+
+fn alpha_pow(power: usize) -> u16 {
+    EXP[power % MAX_BLOCK_LENGTH]
+}
+
+// Polynomials are stored with the coefficient of the highest power first.
+
+fn poly_scale(p: &[u16], x: u16) -> Vec<u16> {
+    p.iter().map(|c| mul(*c, x)).collect()
+}
+
+fn poly_add(p: &[u16], q: &[u16]) -> Vec<u16> {
+    let length = core::cmp::max(p.len(), q.len());
+    let mut sum = vec![0; length];
+
+    for (index, c) in p.iter().enumerate() {
+        sum[index + length - p.len()] = *c;
+    }
+
+    for (index, c) in q.iter().enumerate() {
+        sum[index + length - q.len()] ^= *c;
+    }
+
+    sum
+}
+
+fn poly_mul(p: &[u16], q: &[u16]) -> Vec<u16> {
+    let mut product = vec![0; p.len() + q.len() - 1];
+
+    for (i, a) in p.iter().enumerate() {
+        for (j, b) in q.iter().enumerate() {
+            product[i + j] ^= mul(*a, *b);
+        }
+    }
+
+    product
+}
+
+fn poly_eval(p: &[u16], x: u16) -> u16 {
+    p.iter().fold(0, |y, c| mul(y, x) ^ *c)
+}
+
+fn generator_polynomial(parity_symbols: usize) -> Vec<u16> {
+    (0..parity_symbols).fold(vec![1], |g, i| poly_mul(&g, &[1, alpha_pow(i)]))
+}
+
+/// The block evaluated at the roots of the generator, with a zero in front to make the indices line
+/// up with the error locator.
+fn syndromes(block: &[u16], parity_symbols: usize) -> Vec<u16> {
+    let mut syndromes = vec![0; parity_symbols + 1];
+
+    for (index, syndrome) in syndromes.iter_mut().skip(1).enumerate() {
+        *syndrome = poly_eval(block, alpha_pow(index));
+    }
+
+    syndromes
+}
+
+fn syndromes_are_zero(block: &[u16], parity_symbols: usize) -> bool {
+    (0..parity_symbols).all(|index| poly_eval(block, alpha_pow(index)) == 0)
+}
+
+/// Remove the effect of the known erasures from the syndromes, so that Berlekamp-Massey only has
+/// to find the unknown errors.
+fn forney_syndromes(syndromes: &[u16], erasures: &[usize], block_length: usize) -> Vec<u16> {
+    let mut forney_syndromes = syndromes[1..].to_vec();
+
+    for position in erasures {
+        let x = alpha_pow(block_length - 1 - position);
+
+        for index in 0..forney_syndromes.len() - 1 {
+            forney_syndromes[index] = mul(forney_syndromes[index], x) ^ forney_syndromes[index + 1];
+        }
+    }
+
+    forney_syndromes
+}
+
+/// Berlekamp-Massey
+fn error_locator(syndromes: &[u16], parity_symbols: usize, erasures: usize) -> Option<Vec<u16>> {
+    let mut locator = vec![1];
+    let mut old_locator = vec![1];
+
+    for k in 0..parity_symbols - erasures {
+        let mut delta = syndromes[k];
+
+        for j in 1..core::cmp::min(locator.len(), k + 1) {
+            delta ^= mul(locator[locator.len() - 1 - j], syndromes[k - j]);
+        }
+
+        old_locator.push(0);
+
+        if delta != 0 {
+            if old_locator.len() > locator.len() {
+                let new_locator = poly_scale(&old_locator, delta);
+                old_locator = poly_scale(&locator, inverse(delta));
+                locator = new_locator;
+            }
+
+            locator = poly_add(&locator, &poly_scale(&old_locator, delta));
+        }
+    }
+
+    let leading_zeros = locator.iter().take_while(|c| **c == 0).count();
+    locator.drain(..leading_zeros);
+
+    let errors = locator.len().saturating_sub(1);
+    if errors * 2 + erasures > parity_symbols {
+        return None;
+    }
+
+    Some(locator)
+}
+
+/// Chien search, `error_locator` has the lowest power first.
+fn find_errors(error_locator: &[u16], block_length: usize) -> Option<Vec<usize>> {
+    let positions: Vec<usize> = (0..block_length)
+        .filter(|i| poly_eval(error_locator, alpha_pow(*i)) == 0)
+        .map(|i| block_length - 1 - i)
+        .collect();
+
+    if positions.len() != error_locator.len() - 1 {
+        return None;
+    }
+
+    Some(positions)
+}
+
+/// Forney's algorithm
+fn correct_errata(block: &mut [u16], syndromes: &[u16], positions: &[usize]) -> Option<()> {
+    let powers: Vec<usize> = positions.iter().map(|p| block.len() - 1 - p).collect();
+
+    let errata_locator = powers.iter().fold(vec![1], |locator, power| {
+        poly_mul(&locator, &poly_add(&[1], &[alpha_pow(*power), 0]))
+    });
+
+    let mut reversed_syndromes = syndromes.to_vec();
+    reversed_syndromes.reverse();
+
+    let product = poly_mul(&reversed_syndromes, &errata_locator);
+    let evaluator = product[product.len() - errata_locator.len()..].to_vec();
+
+    let xs: Vec<u16> = powers.iter().map(|power| alpha_pow(*power)).collect();
+
+    for (i, x) in xs.iter().enumerate() {
+        let x_inverse = inverse(*x);
+
+        let locator_derivative = xs
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| *j != i)
+            .fold(1, |product, (_, xj)| mul(product, 1 ^ mul(x_inverse, *xj)));
+
+        if locator_derivative == 0 {
+            return None;
+        }
+
+        let y = mul(*x, poly_eval(&evaluator, x_inverse));
+        block[positions[i]] ^= div(y, locator_derivative);
+    }
+
+    Some(())
+}
@@ -0,0 +1,115 @@
+use alloc::boxed::Box;
+use uuid::Uuid;
+
+use super::constants::{BITS_IN_A_BYTE, BITS_PER_EMOJI};
+
+/// The number of emoji that `bits` bits are packed into. The bits that don't fill up the last emoji
+/// are padded, which takes two emoji.
+pub const fn packed_emoji_len(bits: usize) -> usize {
+    let padding = if bits.is_multiple_of(BITS_PER_EMOJI as usize) {
+        0
+    } else {
+        2
+    };
+
+    bits / BITS_PER_EMOJI as usize + padding
+}
+
+/// Types that are always emojfuscated into the same number of bytes, so the size of the emoji is
+/// known at compile time. This makes it possible to encode them without allocating with
+/// `Emojfuscate::emojfuscate_array`.
+///
+/// It's implemented for numbers, `char`, `bool`, `Uuid` and arrays and tuples of fixed size types,
+/// and it can be derived for structs whose fields all have a fixed size:
+///
+/// ```
+/// use emojfuscate::{ArrayString, Emojfuscate, EmojfuscateFixedSize};
+///
+/// #[derive(Emojfuscate, EmojfuscateFixedSize)]
+/// struct Id {
+///     shard: u16,
+///     id: u64,
+/// }
+///
+/// assert_eq!(Id::ENCODED_BYTES, 10);
+/// assert_eq!(Id::EMOJI_LEN, 8);
+///
+/// let id = Id { shard: 3, id: 1234 };
+/// let emojified: ArrayString<{ Id::MAX_UTF8_LEN }> = id.emojfuscate_array();
+/// assert_eq!(emojified.chars().count(), Id::EMOJI_LEN);
+/// ```
+pub trait EmojfuscateFixedSize {
+    /// The number of bytes that values of the type are turned into before they are packed into
+    /// emoji.
+    const ENCODED_BYTES: usize;
+
+    /// The number of emoji that values of the type are emojfuscated into.
+    const EMOJI_LEN: usize = packed_emoji_len(Self::ENCODED_BYTES * BITS_IN_A_BYTE as usize);
+
+    /// The most bytes of UTF-8 the emoji can take up, no emoji is longer than four bytes. This is
+    /// the capacity of the `ArrayString` from `emojfuscate_array`.
+    const MAX_UTF8_LEN: usize = Self::EMOJI_LEN * 4;
+}
+
+impl<A: EmojfuscateFixedSize> EmojfuscateFixedSize for &A {
+    const ENCODED_BYTES: usize = A::ENCODED_BYTES;
+}
+
+impl<A: EmojfuscateFixedSize> EmojfuscateFixedSize for Box<A> {
+    const ENCODED_BYTES: usize = A::ENCODED_BYTES;
+}
+
+impl EmojfuscateFixedSize for () {
+    const ENCODED_BYTES: usize = 0;
+}
+
+impl EmojfuscateFixedSize for bool {
+    const ENCODED_BYTES: usize = 1;
+}
+
+// chars are encoded as their code point
+impl EmojfuscateFixedSize for char {
+    const ENCODED_BYTES: usize = 4;
+}
+
+impl EmojfuscateFixedSize for Uuid {
+    const ENCODED_BYTES: usize = 16;
+}
+
+impl<A: EmojfuscateFixedSize, const S: usize> EmojfuscateFixedSize for [A; S] {
+    const ENCODED_BYTES: usize = A::ENCODED_BYTES * S;
+}
+
+macro_rules! impl_fixed_size_for_numbers {
+    ($($type:ident)*) => {
+        $(
+            impl EmojfuscateFixedSize for $type {
+                const ENCODED_BYTES: usize = core::mem::size_of::<$type>();
+            }
+        )*
+    }
+}
+
+impl_fixed_size_for_numbers!(u8 u16 u32 u64 u128 i8 i16 i32 i64 i128 f32 f64);
+
+// Implements it for the tuple of all the types and then for the tuple without the first one, and so
+// on down to a tuple of one
+macro_rules! impl_fixed_size_for_tuples {
+    () => {};
+    ($first_type:ident $($type:ident)*) => {
+        impl<$first_type, $($type),*> EmojfuscateFixedSize for ($first_type, $($type,)*)
+        where
+            $first_type: EmojfuscateFixedSize,
+            $($type: EmojfuscateFixedSize),*
+        {
+            const ENCODED_BYTES: usize = $first_type::ENCODED_BYTES $(+ $type::ENCODED_BYTES)*;
+        }
+
+        impl_fixed_size_for_tuples!($($type)*);
+    }
+}
+
+// Implementations for tuples up to 24 elements, like `Emojfuscate`
+impl_fixed_size_for_tuples!(
+    A1 A2 A3 A4 A5 A6 A7 A8 A9 A10 A11 A12 A13 A14 A15 A16 A17 A18 A19 A20 A21 A22 A23 A24
+);
@@ -0,0 +1,52 @@
+use super::alphabet::Alphabet;
+use super::constants::{FORMAT_VERSION, HEADER_EMOJI_VALUE};
+
+/// The number of emoji in a header: the header emoji followed by the version, the flags and the
+/// alphabet fingerprint.
+pub const HEADER_LENGTH: usize = 4;
+
+/// The message ends with a checksum.
+pub const FLAG_CHECKSUM: u16 = 1 << 0;
+
+/// The message is split into blocks with parity emoji. The header is followed by two more emoji
+/// with the number of data and parity emoji in each block.
+pub const FLAG_ERROR_CORRECTION: u16 = 1 << 1;
+
+/// The data was compressed before it was turned into emoji.
+pub const FLAG_COMPRESSION: u16 = 1 << 2;
+
+/// The flags that this version of the crate knows how to decode.
+pub const SUPPORTED_FLAGS: u16 = FLAG_CHECKSUM | FLAG_ERROR_CORRECTION | FLAG_COMPRESSION;
+
+/// A header can optionally be put in front of the emoji to announce how they were produced. This
+/// makes it possible to store emojfuscated data for a long time and still know how to decode it
+/// after the format has changed.
+///
+/// The header is a special emoji that isn't used for anything else, followed by three emoji whose
+/// 10 bit values are the format version, a set of flags for optional features and a fingerprint
+/// of the alphabet that the data was encoded with.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Header {
+    pub version: u16,
+    pub flags: u16,
+    pub alphabet_fingerprint: u16,
+}
+
+impl Header {
+    pub fn new(alphabet: &Alphabet, flags: u16) -> Header {
+        Header {
+            version: FORMAT_VERSION,
+            flags,
+            alphabet_fingerprint: alphabet.fingerprint(),
+        }
+    }
+
+    pub fn to_emoji_values(self) -> [u16; HEADER_LENGTH] {
+        [
+            HEADER_EMOJI_VALUE,
+            self.version,
+            self.flags,
+            self.alphabet_fingerprint,
+        ]
+    }
+}
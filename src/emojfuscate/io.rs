@@ -0,0 +1,182 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::string::String;
+
+use super::alphabet::Alphabet;
+use super::bulk::EmojiPacker;
+use super::constants::ByteInSequence;
+use super::demojfuscate::{DecodeEmojiToBytes, IsEmojiRepresentation};
+
+/// Turns the bytes that are written to it into emoji and writes them as UTF-8 to `W`. This
+/// produces the same emoji as `EmojfuscateByteStream::emojfuscate_byte_stream_no_start_or_stop`.
+///
+/// An emoji holds 10 bits, so up to a byte of data can be waiting for more bits to fill up an
+/// emoji. `flush` writes it with padding and so does dropping the writer, so nothing is lost at
+/// the end of an `io::copy`. The data can keep going after a flush.
+pub struct EmojiWriter<W: Write> {
+    inner: W,
+    packer: EmojiPacker,
+}
+
+impl<W: Write> EmojiWriter<W> {
+    pub fn new(inner: W) -> Self {
+        EmojiWriter {
+            inner,
+            packer: EmojiPacker::new(),
+        }
+    }
+
+    /// Write the emoji from a different emoji table than the default one.
+    pub fn with_alphabet(mut self, alphabet: &'static Alphabet) -> Self {
+        self.packer.set_alphabet(alphabet);
+        self
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+}
+
+impl<W: Write> Write for EmojiWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut encoded = String::new();
+        self.packer.pack(buf, &mut encoded);
+        self.inner.write_all(encoded.as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let mut encoded = String::with_capacity(8);
+        self.packer.pad(&mut encoded);
+        self.inner.write_all(encoded.as_bytes())?;
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Drop for EmojiWriter<W> {
+    fn drop(&mut self) {
+        // like BufWriter, errors are ignored here, call flush to find out about them
+        let _ = self.flush();
+    }
+}
+
+/// Reads UTF-8 encoded emoji from `R` and turns them back into bytes, i.e. it undoes
+/// `EmojiWriter`. Emoji that can't be decoded are reported as `io::ErrorKind::InvalidData`, with
+/// the `FromEmojiError` as the inner error.
+pub struct EmojiReader<R: Read> {
+    decoder: DecodeEmojiToBytes<ReadBytes<R>>,
+}
+
+/// The bytes of a reader as an iterator. An iterator can't fail, so errors are kept until the
+/// `EmojiReader` can return them.
+struct ReadBytes<R: Read> {
+    reader: BufReader<R>,
+    error: Option<io::Error>,
+}
+
+impl<R: Read> Iterator for ReadBytes<R> {
+    type Item = u8;
+    fn next(&mut self) -> Option<u8> {
+        loop {
+            match self.reader.fill_buf() {
+                Ok([]) => return None,
+                Ok(buf) => {
+                    let b = buf[0];
+                    self.reader.consume(1);
+                    return Some(b);
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    self.error = Some(err);
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+impl<R: Read> EmojiReader<R> {
+    pub fn new(inner: R) -> Self {
+        let bytes = ReadBytes {
+            reader: BufReader::new(inner),
+            error: None,
+        };
+
+        EmojiReader {
+            decoder: bytes.demojfuscate_byte_stream(),
+        }
+    }
+
+    /// Read emoji from a different emoji table than the default one.
+    pub fn with_alphabet(self, alphabet: &'static Alphabet) -> Self {
+        EmojiReader {
+            decoder: self.decoder.with_alphabet(alphabet),
+        }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        self.decoder.get_ref().reader.get_ref()
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        self.decoder.get_mut().reader.get_mut()
+    }
+
+    fn take_error(&mut self) -> Option<io::Error> {
+        self.decoder.get_mut().error.take()
+    }
+}
+
+impl<R: Read> Read for EmojiReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Some(err) = self.take_error() {
+            return Err(err);
+        }
+
+        let mut read = 0;
+
+        while read < buf.len() {
+            // don't wait for more input when there already is something to return
+            if read > 0 && self.decoder.get_ref().reader.buffer().is_empty() {
+                break;
+            }
+
+            let err = match self.decoder.next() {
+                Some(Ok(ByteInSequence::Byte(b))) => {
+                    buf[read] = b;
+                    read += 1;
+                    continue;
+                }
+                Some(Ok(found)) => self.decoder.unexpected("a byte", Some(found)),
+                Some(Err(err)) => err,
+                None => break,
+            };
+
+            // the emoji most likely ended early because the reader failed
+            let err = self
+                .take_error()
+                .unwrap_or_else(|| io::Error::new(io::ErrorKind::InvalidData, err));
+
+            if read == 0 {
+                return Err(err);
+            }
+
+            // the bytes before the error are returned first
+            self.decoder.get_mut().error = Some(err);
+            break;
+        }
+
+        // errors from the reader are returned on the next call, after the bytes that were read
+        // before it failed
+        if read == 0 {
+            if let Some(err) = self.take_error() {
+                return Err(err);
+            }
+        }
+
+        Ok(read)
+    }
+}
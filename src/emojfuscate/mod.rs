@@ -1,13 +1,98 @@
+// The encoder and the decoder only need `alloc`, so they can be used without `std`. The readers
+// and writers (and the async versions of everything) need `std`.
+#![no_std]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+#[cfg(feature = "alloc")]
+mod alphabet;
+#[cfg(feature = "async")]
+mod asynchronous;
+#[cfg(feature = "alloc")]
+mod bulk;
+#[cfg(feature = "alloc")]
+mod checksum;
+#[cfg(feature = "alloc")]
+mod compression;
+// only the types and constants that are exported are used without alloc
+#[cfg_attr(not(feature = "alloc"), allow(dead_code))]
 mod constants;
+#[cfg(feature = "alloc")]
 mod demojfuscate;
+#[cfg(feature = "alloc")]
 mod emojfuscate;
+#[cfg(feature = "alloc")]
+mod error;
+#[cfg(feature = "alloc")]
+mod error_correction;
+#[cfg(feature = "alloc")]
+mod fixed_size;
+#[cfg(feature = "alloc")]
+mod header;
+#[cfg(feature = "std")]
+mod io;
+#[cfg(feature = "alloc")]
+mod schema;
+#[cfg(feature = "serde")]
+mod serde_format;
+#[cfg(feature = "serde")]
+mod serde_value;
+#[cfg(feature = "alloc")]
 mod util {
     pub mod iterator_wrapper;
 }
+#[cfg(feature = "alloc")]
+mod value;
 
-pub use constants::ByteInSequence;
+#[cfg(feature = "alloc")]
+pub use alphabet::{Alphabet, AlphabetError};
+#[cfg(feature = "alloc")]
+pub use arrayvec::ArrayString;
+#[cfg(feature = "async")]
+pub use asynchronous::{
+    AsyncEmojiReader, AsyncEmojiWriter, DemojfuscateAsync, DemojfuscateStream, EmojfuscateAsync,
+    EncodeBytesAsEmojiStream,
+};
+#[cfg(feature = "alloc")]
+pub use bulk::{
+    decode_str_into, decode_str_into_with_alphabet, encode_slice_into,
+    encode_slice_into_with_alphabet,
+};
+pub use constants::{ByteInSequence, ALPHABET_SIZE, DEFAULT_NESTING_LIMIT, FORMAT_VERSION};
+#[cfg(feature = "alloc")]
 pub use demojfuscate::{
-    ConstructFromEmoji, DecodeEmojiToBytes, Demojfuscate, FromEmojiError, IsEmojiRepresentation,
+    ConstructFromEmoji, DecodeEmojiToBytes, Demojfuscate, EmojiVisitor, IsEmojiRepresentation,
 };
-pub use emojfuscate::{Emojfuscate, EmojfuscateByteStream, EncodeBytesAsEmoji};
+#[cfg(feature = "alloc")]
+pub use emojfuscate::{
+    BoxedEmojiBytes, Emojfuscate, EmojfuscateByteStream, EmojfuscateWithSyncMarkers,
+    EncodeBytesAsEmoji, EvolvableField, KnownLength, SyncMarkers, UnknownLength, VariantFields,
+    VariantTag,
+};
+#[cfg(feature = "alloc")]
 pub use emojfuscate_derive::*;
+#[cfg(feature = "alloc")]
+pub use error::{FromEmojiError, FromEmojiErrorKind, PathSegment, Token};
+#[cfg(feature = "alloc")]
+pub use error_correction::{ErrorCorrection, ErrorCorrectionError};
+#[cfg(feature = "alloc")]
+pub use fixed_size::EmojfuscateFixedSize;
+#[cfg(feature = "alloc")]
+pub use header::Header;
+#[cfg(feature = "std")]
+pub use io::{EmojiReader, EmojiWriter};
+#[cfg(feature = "alloc")]
+pub use schema::{EmojiSchema, Field, Fields, Schema, Variant};
+#[cfg(feature = "serde")]
+pub use serde_format::{
+    from_emoji, to_emoji, to_emoji_stream, EmojiDeserializer, EmojiSerializer, ToEmojiError,
+};
+#[cfg(feature = "serde")]
+pub use serde_value::{
+    from_emoji_value, from_tagged_emoji, to_emoji_value, to_tagged_emoji, to_tagged_emoji_stream,
+};
+#[cfg(feature = "alloc")]
+pub use value::EmojiValue;
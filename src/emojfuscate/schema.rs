@@ -0,0 +1,624 @@
+use alloc::borrow::{Cow, ToOwned};
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use uuid::Uuid;
+
+use super::constants::ByteInSequence;
+use super::demojfuscate::{ConstructFromEmoji, DecodeEmojiToBytes, IsEmojiRepresentation};
+use super::error::{FromEmojiError, FromEmojiErrorKind, PathSegment};
+use super::value::EmojiValue;
+
+/// Types that can describe how their values are laid out in the emoji. It can be derived along
+/// with `Emojfuscate` and `ConstructFromEmoji`, and the description follows the derived layout,
+/// e.g. the fields of a struct come in alphabetical order:
+///
+/// ```
+/// use emojfuscate::{EmojiSchema, Field, Fields, Schema};
+///
+/// #[derive(EmojiSchema)]
+/// struct Person {
+///     name: String,
+///     age: u8,
+/// }
+///
+/// assert_eq!(
+///     Person::emoji_schema(),
+///     Schema::Struct {
+///         name: "Person",
+///         fields: Fields::Named(vec![
+///             Field { name: "age", schema: Schema::U8 },
+///             Field { name: "name", schema: Schema::String },
+///         ]),
+///     }
+/// );
+/// assert_eq!(Person::emoji_schema().to_string(), "Person { age: u8, name: String }");
+/// ```
+pub trait EmojiSchema {
+    fn emoji_schema() -> Schema;
+}
+
+/// A description of how values of a type are laid out in the emoji, e.g. for documentation, for
+/// generating code in other languages or for looking at emoji without the type, see `decode`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Schema {
+    Unit,
+    Bool,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    F32,
+    F64,
+    Char,
+    /// UTF-8 between the start and stop emoji.
+    String,
+    Uuid,
+    /// A 0 byte for `None` or a 1 byte followed by the value.
+    Option(Box<Schema>),
+    /// Any number of values between the start and stop emoji, e.g. a `Vec`.
+    Sequence(Box<Schema>),
+    /// A fixed number of values, one after the other.
+    Array(Box<Schema>, usize),
+    Tuple(Vec<Schema>),
+    Struct {
+        name: &'static str,
+        fields: Fields,
+    },
+    /// The tag of the chosen variant, see `EncodeBytesAsEmoji::variant_tag`, followed by only the
+    /// fields of that variant.
+    Enum {
+        name: &'static str,
+        variants: Vec<Variant>,
+    },
+    /// The struct or enum with this name that the value is inside of, for the fields of recursive
+    /// types, e.g. the `Box<Expr>` in `enum Expr { Lit(i64), Neg(Box<Expr>) }`. Reading a schema
+    /// with one that isn't inside of a struct or an enum with the name panics.
+    Recursive(&'static str),
+}
+
+/// The fields of a struct or an enum variant, in the order they are in the emoji.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Fields {
+    Named(Vec<Field>),
+    Unnamed(Vec<Schema>),
+    Unit,
+    /// The fields of a struct with `#[emojfuscate(evolvable)]`, between the start and stop emoji.
+    /// Each field is its name as a string followed by its value in a sequence of its own. Fields
+    /// that were added later may be missing and there may be fields that aren't listed.
+    Evolvable(Vec<Field>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Field {
+    pub name: &'static str,
+    pub schema: Schema,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Variant {
+    pub name: &'static str,
+    pub tag: u32,
+    pub fields: Fields,
+}
+
+impl Schema {
+    /// Demojfuscate a value of this schema into an `EmojiValue`, without the type that the emoji
+    /// were made from. Structs with named fields become maps from the field names to the values,
+    /// other structs and tuples become lists, and enum variants become their name, or a map from
+    /// their name to their fields if they have any. Like `Demojfuscate::demojfuscate`, the checksum
+    /// is verified first if there is one.
+    ///
+    /// This also checks that the emoji are a valid value of the type.
+    pub fn decode<I, X>(&self, emoji: X) -> Result<EmojiValue, FromEmojiError>
+    where
+        I: Iterator<Item = u8>,
+        X: IsEmojiRepresentation<I>,
+    {
+        let mut byte_stream = emoji.demojfuscate_byte_stream();
+        byte_stream.verify_checksum()?;
+        self.read_value(&mut byte_stream)
+    }
+
+    /// Read a value of this schema from `byte_stream`, see `decode`.
+    pub fn read_value<I>(
+        &self,
+        byte_stream: &mut DecodeEmojiToBytes<I>,
+    ) -> Result<EmojiValue, FromEmojiError>
+    where
+        I: Iterator<Item = u8>,
+    {
+        self.read_value_inside(byte_stream, &mut Vec::new())
+    }
+
+    // `outer` are the structs and enums that the value is inside of, the innermost one last, which
+    // is where `Schema::Recursive` is looked up
+    fn read_value_inside<'s, I>(
+        &'s self,
+        byte_stream: &mut DecodeEmojiToBytes<I>,
+        outer: &mut Vec<&'s Schema>,
+    ) -> Result<EmojiValue, FromEmojiError>
+    where
+        I: Iterator<Item = u8>,
+    {
+        match self {
+            Schema::Option(schema) => match u8::construct_from_emoji(byte_stream)? {
+                0 => Ok(EmojiValue::Null),
+                1 => schema.read_value_inside(byte_stream, outer),
+                n => Err(byte_stream
+                    .unexpected("0 for None or 1 for Some", Some(ByteInSequence::Byte(n)))
+                    .in_type("Option")),
+            },
+            Schema::Sequence(schema) => schema.read_sequence(byte_stream, outer),
+            Schema::Array(schema, len) => schema.read_array(*len, byte_stream, outer),
+            Schema::Tuple(schemas) => Schema::read_tuple(schemas, byte_stream, outer),
+            Schema::Struct { name, fields } => {
+                outer.push(self);
+                let value = fields.read_value(name, byte_stream, outer);
+                outer.pop();
+                value
+            }
+            Schema::Enum { name, variants } => self.read_enum(name, variants, byte_stream, outer),
+            Schema::Recursive(name) => {
+                let is_named = |schema: &&Schema| match schema {
+                    Schema::Struct { name: n, .. } | Schema::Enum { name: n, .. } => n == name,
+                    _ => false,
+                };
+
+                let Some(schema) = outer.iter().rev().copied().find(is_named) else {
+                    panic!("the schema refers to `{}` but isn't inside of it", name);
+                };
+
+                byte_stream
+                    .construct_nested(|byte_stream| schema.read_value_inside(byte_stream, outer))
+            }
+            scalar => scalar.read_scalar(byte_stream),
+        }
+    }
+
+    // kept apart from read_value_inside so that its frame stays small, it's on the stack once for
+    // every level of nesting
+    fn read_scalar<I>(
+        &self,
+        byte_stream: &mut DecodeEmojiToBytes<I>,
+    ) -> Result<EmojiValue, FromEmojiError>
+    where
+        I: Iterator<Item = u8>,
+    {
+        fn read<A, I>(byte_stream: &mut DecodeEmojiToBytes<I>) -> Result<A, FromEmojiError>
+        where
+            A: ConstructFromEmoji<A, I>,
+            I: Iterator<Item = u8>,
+        {
+            A::construct_from_emoji(byte_stream)
+        }
+
+        match self {
+            Schema::Unit => Ok(EmojiValue::Null),
+            Schema::Bool => read(byte_stream).map(EmojiValue::Bool),
+            Schema::U8 => read::<u8, I>(byte_stream).map(|n| EmojiValue::UInt(n.into())),
+            Schema::U16 => read::<u16, I>(byte_stream).map(|n| EmojiValue::UInt(n.into())),
+            Schema::U32 => read::<u32, I>(byte_stream).map(|n| EmojiValue::UInt(n.into())),
+            Schema::U64 => read(byte_stream).map(EmojiValue::UInt),
+            Schema::U128 => read::<u128, I>(byte_stream).map(|n| match u64::try_from(n) {
+                Ok(n) => EmojiValue::UInt(n),
+                Err(_) => EmojiValue::String(n.to_string()),
+            }),
+            Schema::I8 => read::<i8, I>(byte_stream).map(|n| EmojiValue::Int(n.into())),
+            Schema::I16 => read::<i16, I>(byte_stream).map(|n| EmojiValue::Int(n.into())),
+            Schema::I32 => read::<i32, I>(byte_stream).map(|n| EmojiValue::Int(n.into())),
+            Schema::I64 => read(byte_stream).map(EmojiValue::Int),
+            Schema::I128 => read::<i128, I>(byte_stream).map(|n| match i64::try_from(n) {
+                Ok(n) => EmojiValue::Int(n),
+                Err(_) => EmojiValue::String(n.to_string()),
+            }),
+            Schema::F32 => read::<f32, I>(byte_stream).map(|n| EmojiValue::Float(n.into())),
+            Schema::F64 => read(byte_stream).map(EmojiValue::Float),
+            Schema::Char => read::<char, I>(byte_stream).map(|c| EmojiValue::String(c.into())),
+            Schema::String => read(byte_stream).map(EmojiValue::String),
+            Schema::Uuid => {
+                read::<Uuid, I>(byte_stream).map(|uuid| EmojiValue::String(uuid.to_string()))
+            }
+            _ => unreachable!("{} isn't a scalar", self),
+        }
+    }
+
+    fn read_enum<'s, I>(
+        &'s self,
+        name: &'static str,
+        variants: &'s [Variant],
+        byte_stream: &mut DecodeEmojiToBytes<I>,
+        outer: &mut Vec<&'s Schema>,
+    ) -> Result<EmojiValue, FromEmojiError>
+    where
+        I: Iterator<Item = u8>,
+    {
+        let tag = byte_stream
+            .read_variant_tag()
+            .map_err(|err| err.in_type(name))?;
+
+        let Some(variant) = variants.iter().find(|v| v.tag == tag) else {
+            return Err(byte_stream
+                .error_here(FromEmojiErrorKind::UnknownVariant(tag))
+                .in_type(name));
+        };
+
+        if variant.fields == Fields::Unit {
+            return Ok(EmojiValue::String(variant.name.into()));
+        }
+
+        outer.push(self);
+        let value = variant.fields.read_value(name, byte_stream, outer);
+        outer.pop();
+        let value = value.map_err(|err| err.within(name, PathSegment::Variant(variant.name)))?;
+
+        Ok(EmojiValue::Map(Vec::from([(
+            EmojiValue::String(variant.name.into()),
+            value,
+        )])))
+    }
+
+    fn read_sequence<'s, I>(
+        &'s self,
+        byte_stream: &mut DecodeEmojiToBytes<I>,
+        outer: &mut Vec<&'s Schema>,
+    ) -> Result<EmojiValue, FromEmojiError>
+    where
+        I: Iterator<Item = u8>,
+    {
+        if let Err(err) = byte_stream.expect_sequence_start() {
+            return Err(err.in_type("Vec"));
+        }
+
+        let mut values = Vec::new();
+
+        while !byte_stream.reached_end_of_sequence() {
+            match self.read_value_inside(byte_stream, outer) {
+                Ok(value) => values.push(value),
+                Err(err) => return Err(err.within("Vec", PathSegment::Index(values.len()))),
+            }
+        }
+
+        byte_stream.next(); // pop off the SequenceEnd value
+        Ok(EmojiValue::List(values))
+    }
+
+    fn read_array<'s, I>(
+        &'s self,
+        len: usize,
+        byte_stream: &mut DecodeEmojiToBytes<I>,
+        outer: &mut Vec<&'s Schema>,
+    ) -> Result<EmojiValue, FromEmojiError>
+    where
+        I: Iterator<Item = u8>,
+    {
+        let mut values = Vec::with_capacity(len);
+
+        for i in 0..len {
+            let value = self
+                .read_value_inside(byte_stream, outer)
+                .map_err(|err| err.within("array", PathSegment::Index(i)))?;
+            values.push(value);
+        }
+
+        Ok(EmojiValue::List(values))
+    }
+
+    fn read_tuple<'s, I>(
+        schemas: &'s [Schema],
+        byte_stream: &mut DecodeEmojiToBytes<I>,
+        outer: &mut Vec<&'s Schema>,
+    ) -> Result<EmojiValue, FromEmojiError>
+    where
+        I: Iterator<Item = u8>,
+    {
+        let mut values = Vec::with_capacity(schemas.len());
+
+        for (i, schema) in schemas.iter().enumerate() {
+            let value = schema
+                .read_value_inside(byte_stream, outer)
+                .map_err(|err| err.within("tuple", PathSegment::Position(i)))?;
+            values.push(value);
+        }
+
+        Ok(EmojiValue::List(values))
+    }
+}
+
+impl Fields {
+    fn read_value<'s, I>(
+        &'s self,
+        type_name: &'static str,
+        byte_stream: &mut DecodeEmojiToBytes<I>,
+        outer: &mut Vec<&'s Schema>,
+    ) -> Result<EmojiValue, FromEmojiError>
+    where
+        I: Iterator<Item = u8>,
+    {
+        match self {
+            Fields::Named(fields) => {
+                let mut entries = Vec::with_capacity(fields.len());
+
+                for field in fields {
+                    let value = field
+                        .schema
+                        .read_value_inside(byte_stream, outer)
+                        .map_err(|err| err.within(type_name, PathSegment::Field(field.name)))?;
+                    entries.push((EmojiValue::String(field.name.into()), value));
+                }
+
+                Ok(EmojiValue::Map(entries))
+            }
+            // like a newtype struct in serde
+            Fields::Unnamed(schemas) if schemas.len() == 1 => schemas[0]
+                .read_value_inside(byte_stream, outer)
+                .map_err(|err| err.within(type_name, PathSegment::Position(0))),
+            Fields::Unnamed(schemas) => {
+                let mut values = Vec::with_capacity(schemas.len());
+
+                for (i, schema) in schemas.iter().enumerate() {
+                    let value = schema
+                        .read_value_inside(byte_stream, outer)
+                        .map_err(|err| err.within(type_name, PathSegment::Position(i)))?;
+                    values.push(value);
+                }
+
+                Ok(EmojiValue::List(values))
+            }
+            Fields::Unit => Ok(EmojiValue::Null),
+            Fields::Evolvable(fields) => {
+                Fields::read_evolvable(fields, type_name, byte_stream, outer)
+            }
+        }
+    }
+
+    // fields that aren't in the schema are skipped, since there is no telling what they are
+    fn read_evolvable<'s, I>(
+        fields: &'s [Field],
+        type_name: &'static str,
+        byte_stream: &mut DecodeEmojiToBytes<I>,
+        outer: &mut Vec<&'s Schema>,
+    ) -> Result<EmojiValue, FromEmojiError>
+    where
+        I: Iterator<Item = u8>,
+    {
+        let mut entries = Vec::new();
+
+        if let Err(err) = byte_stream.expect_sequence_start() {
+            return Err(err.in_type(type_name));
+        }
+
+        while let Some(field_name) = byte_stream
+            .next_field_name()
+            .map_err(|err| err.in_type(type_name))?
+        {
+            let field = match fields.iter().find(|field| field.name == field_name) {
+                Some(field) => field,
+                None => {
+                    byte_stream
+                        .skip_value()
+                        .map_err(|err| err.in_type(type_name))?;
+                    continue;
+                }
+            };
+
+            let value = byte_stream
+                .construct_field_with(|byte_stream| {
+                    field.schema.read_value_inside(byte_stream, outer)
+                })
+                .map_err(|err| err.within(type_name, PathSegment::Field(field.name)))?;
+
+            entries.push((EmojiValue::String(field_name), value));
+        }
+
+        Ok(EmojiValue::Map(entries))
+    }
+}
+
+/// Writes the schema like a Rust type, with the fields in the order they are in the emoji and the
+/// tag of each enum variant, e.g. `Animal { Cat { likes_cuddles: bool, name: String } = 0, Dog(u32)
+/// = 1 }`. The fields of evolvable structs end with `..`, since there may be others.
+impl fmt::Display for Schema {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Schema::Unit => write!(f, "()"),
+            Schema::Bool => write!(f, "bool"),
+            Schema::U8 => write!(f, "u8"),
+            Schema::U16 => write!(f, "u16"),
+            Schema::U32 => write!(f, "u32"),
+            Schema::U64 => write!(f, "u64"),
+            Schema::U128 => write!(f, "u128"),
+            Schema::I8 => write!(f, "i8"),
+            Schema::I16 => write!(f, "i16"),
+            Schema::I32 => write!(f, "i32"),
+            Schema::I64 => write!(f, "i64"),
+            Schema::I128 => write!(f, "i128"),
+            Schema::F32 => write!(f, "f32"),
+            Schema::F64 => write!(f, "f64"),
+            Schema::Char => write!(f, "char"),
+            Schema::String => write!(f, "String"),
+            Schema::Uuid => write!(f, "Uuid"),
+            Schema::Option(schema) => write!(f, "Option<{}>", schema),
+            Schema::Sequence(schema) => write!(f, "Vec<{}>", schema),
+            Schema::Array(schema, len) => write!(f, "[{}; {}]", schema, len),
+            Schema::Tuple(schemas) if schemas.len() == 1 => write!(f, "({},)", schemas[0]),
+            Schema::Tuple(schemas) => {
+                write!(f, "(")?;
+                write_separated(f, schemas)?;
+                write!(f, ")")
+            }
+            Schema::Struct { name, fields } => write!(f, "{}{}", name, fields),
+            Schema::Enum { name, variants } => {
+                write!(f, "{} {{ ", name)?;
+                write_separated(f, variants)?;
+                write!(f, " }}")
+            }
+            Schema::Recursive(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl fmt::Display for Fields {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fields::Named(fields) => {
+                write!(f, " {{ ")?;
+                write_separated(f, fields)?;
+                write!(f, " }}")
+            }
+            Fields::Unnamed(schemas) => {
+                write!(f, "(")?;
+                write_separated(f, schemas)?;
+                write!(f, ")")
+            }
+            Fields::Unit => Ok(()),
+            Fields::Evolvable(fields) => {
+                write!(f, " {{ ")?;
+                write_separated(f, fields)?;
+                write!(f, ", .. }}")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.name, self.schema)
+    }
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{} = {}", self.name, self.fields, self.tag)
+    }
+}
+
+fn write_separated<A: fmt::Display>(f: &mut fmt::Formatter, items: &[A]) -> fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+
+        write!(f, "{}", item)?;
+    }
+
+    Ok(())
+}
+
+impl<A: EmojiSchema + ?Sized> EmojiSchema for &A {
+    fn emoji_schema() -> Schema {
+        A::emoji_schema()
+    }
+}
+
+impl<A: EmojiSchema + ?Sized> EmojiSchema for Box<A> {
+    fn emoji_schema() -> Schema {
+        A::emoji_schema()
+    }
+}
+
+impl EmojiSchema for str {
+    fn emoji_schema() -> Schema {
+        Schema::String
+    }
+}
+
+impl<A: EmojiSchema + ToOwned + ?Sized> EmojiSchema for Cow<'_, A> {
+    fn emoji_schema() -> Schema {
+        A::emoji_schema()
+    }
+}
+
+impl<A: EmojiSchema> EmojiSchema for [A] {
+    fn emoji_schema() -> Schema {
+        Schema::Sequence(Box::new(A::emoji_schema()))
+    }
+}
+
+impl<A: EmojiSchema> EmojiSchema for Option<A> {
+    fn emoji_schema() -> Schema {
+        Schema::Option(Box::new(A::emoji_schema()))
+    }
+}
+
+impl<A: EmojiSchema> EmojiSchema for Vec<A> {
+    fn emoji_schema() -> Schema {
+        Schema::Sequence(Box::new(A::emoji_schema()))
+    }
+}
+
+impl<A: EmojiSchema, const S: usize> EmojiSchema for [A; S] {
+    fn emoji_schema() -> Schema {
+        Schema::Array(Box::new(A::emoji_schema()), S)
+    }
+}
+
+// laid out like a derived enum with the variants `Ok(A)` and `Err(B)`
+impl<A: EmojiSchema, B: EmojiSchema> EmojiSchema for Result<A, B> {
+    fn emoji_schema() -> Schema {
+        Schema::Enum {
+            name: "Result",
+            variants: Vec::from([
+                Variant {
+                    name: "Ok",
+                    tag: 0,
+                    fields: Fields::Unnamed(Vec::from([A::emoji_schema()])),
+                },
+                Variant {
+                    name: "Err",
+                    tag: 1,
+                    fields: Fields::Unnamed(Vec::from([B::emoji_schema()])),
+                },
+            ]),
+        }
+    }
+}
+
+macro_rules! impl_emoji_schema {
+    ($($type:ty => $schema:ident),*) => {
+        $(
+            impl EmojiSchema for $type {
+                fn emoji_schema() -> Schema {
+                    Schema::$schema
+                }
+            }
+        )*
+    }
+}
+
+impl_emoji_schema!(
+    () => Unit, bool => Bool, u8 => U8, u16 => U16, u32 => U32, u64 => U64, u128 => U128,
+    i8 => I8, i16 => I16, i32 => I32, i64 => I64, i128 => I128, f32 => F32, f64 => F64,
+    char => Char, String => String, Uuid => Uuid
+);
+
+// Implements it for the tuple of all the types and then for the tuple without the first one, and so
+// on down to a tuple of one
+macro_rules! impl_emoji_schema_for_tuples {
+    () => {};
+    ($first_type:ident $($type:ident)*) => {
+        impl<$first_type, $($type),*> EmojiSchema for ($first_type, $($type,)*)
+        where
+            $first_type: EmojiSchema,
+            $($type: EmojiSchema),*
+        {
+            fn emoji_schema() -> Schema {
+                Schema::Tuple(Vec::from([$first_type::emoji_schema() $(, $type::emoji_schema())*]))
+            }
+        }
+
+        impl_emoji_schema_for_tuples!($($type)*);
+    }
+}
+
+// Implementations for tuples up to 24 elements, like `Emojfuscate`
+impl_emoji_schema_for_tuples!(
+    A1 A2 A3 A4 A5 A6 A7 A8 A9 A10 A11 A12 A13 A14 A15 A16 A17 A18 A19 A20 A21 A22 A23 A24
+);
@@ -0,0 +1,883 @@
+use alloc::string::{String, ToString};
+use alloc::vec::{self, Vec};
+use core::fmt;
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::ser::{self, Serialize};
+
+use super::constants::ByteInSequence;
+use super::demojfuscate::{ConstructFromEmoji, DecodeEmojiToBytes, IsEmojiRepresentation};
+use super::emojfuscate::EncodeBytesAsEmoji;
+use super::error::{FromEmojiError, FromEmojiErrorKind, PathSegment};
+
+/// Emojfuscate any value that implements `serde::Serialize`. The emoji are the same as the ones
+/// that `#[derive(Emojfuscate)]` produces for the same type, except for enums, see
+/// `EmojiSerializer`.
+pub fn to_emoji<T>(value: &T) -> Result<String, ToEmojiError>
+where
+    T: ?Sized + Serialize,
+{
+    to_emoji_stream(value).map(Iterator::collect)
+}
+
+/// Like `to_emoji`, but the emoji are returned as a stream so that a header, a checksum, error
+/// correction etc. can be added to them first.
+pub fn to_emoji_stream<T>(
+    value: &T,
+) -> Result<EncodeBytesAsEmoji<vec::IntoIter<ByteInSequence>>, ToEmojiError>
+where
+    T: ?Sized + Serialize,
+{
+    let mut serializer = EmojiSerializer::new();
+    value.serialize(&mut serializer)?;
+    Ok(serializer.into_stream())
+}
+
+/// Demojfuscate any value that implements `serde::Deserialize`, from emoji that were made either
+/// by `to_emoji` or by `#[derive(Emojfuscate)]`. Like `Demojfuscate::demojfuscate`, the checksum
+/// is verified first if there is one.
+pub fn from_emoji<T, I, X>(emoji: X) -> Result<T, FromEmojiError>
+where
+    T: DeserializeOwned,
+    I: Iterator<Item = u8>,
+    X: IsEmojiRepresentation<I>,
+{
+    let mut byte_stream = emoji.demojfuscate_byte_stream();
+    byte_stream.verify_checksum()?;
+    T::deserialize(&mut EmojiDeserializer::new(byte_stream))
+}
+
+/// Something that went wrong in a `serde::Serialize` implementation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ToEmojiError {
+    pub message: String,
+}
+
+impl fmt::Display for ToEmojiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl core::error::Error for ToEmojiError {}
+
+impl ser::Error for ToEmojiError {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        ToEmojiError {
+            message: message.to_string(),
+        }
+    }
+}
+
+impl de::Error for FromEmojiError {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        FromEmojiError::new(FromEmojiErrorKind::Custom(message.to_string()))
+    }
+}
+
+/// A `serde::Serializer` that lays out the data the same way as `#[derive(Emojfuscate)]`:
+/// numbers are big endian, `bool` is a byte, `char` is a `u32`, strings, byte slices, sequences
+/// and maps are wrapped in the start and stop emoji, `Option` is a 0 or 1 byte followed by the
+/// value, and the fields of structs come in alphabetical order.
+///
+/// Enums are the tag of the variant, see `EncodeBytesAsEmoji::variant_tag`, followed by only the
+/// fields of that variant, like derived enums. The tag is the index of the variant, serde doesn't
+/// know about explicit tags.
+#[derive(Default)]
+pub struct EmojiSerializer {
+    bytes: Vec<ByteInSequence>,
+}
+
+impl EmojiSerializer {
+    pub fn new() -> Self {
+        EmojiSerializer { bytes: Vec::new() }
+    }
+
+    /// The emoji for everything that has been serialized so far.
+    pub fn into_stream(self) -> EncodeBytesAsEmoji<vec::IntoIter<ByteInSequence>> {
+        EncodeBytesAsEmoji::new(self.bytes.into_iter())
+    }
+
+    fn push_bytes(&mut self, bytes: &[u8]) {
+        self.bytes
+            .extend(bytes.iter().copied().map(ByteInSequence::Byte));
+    }
+
+    fn push_sequence(&mut self, bytes: &[u8]) {
+        self.bytes.push(ByteInSequence::SequenceStart);
+        self.push_bytes(bytes);
+        self.bytes.push(ByteInSequence::SequenceEnd);
+    }
+
+    fn push_variant_tag(&mut self, tag: u32) {
+        self.bytes
+            .extend(EncodeBytesAsEmoji::variant_tag(tag).into_inner());
+    }
+}
+
+macro_rules! serialize_big_endian {
+    ($($method:ident: $type:ty),*) => {
+        $(
+            fn $method(self, value: $type) -> Result<(), ToEmojiError> {
+                self.push_bytes(&value.to_be_bytes());
+                Ok(())
+            }
+        )*
+    };
+}
+
+impl<'a> ser::Serializer for &'a mut EmojiSerializer {
+    type Ok = ();
+    type Error = ToEmojiError;
+    type SerializeSeq = CompoundSerializer<'a>;
+    type SerializeTuple = CompoundSerializer<'a>;
+    type SerializeTupleStruct = CompoundSerializer<'a>;
+    type SerializeTupleVariant = CompoundSerializer<'a>;
+    type SerializeMap = CompoundSerializer<'a>;
+    type SerializeStruct = StructSerializer<'a>;
+    type SerializeStructVariant = StructSerializer<'a>;
+
+    serialize_big_endian!(
+        serialize_i8: i8,
+        serialize_i16: i16,
+        serialize_i32: i32,
+        serialize_i64: i64,
+        serialize_i128: i128,
+        serialize_u8: u8,
+        serialize_u16: u16,
+        serialize_u32: u32,
+        serialize_u64: u64,
+        serialize_u128: u128,
+        serialize_f32: f32,
+        serialize_f64: f64
+    );
+
+    fn serialize_bool(self, value: bool) -> Result<(), ToEmojiError> {
+        self.serialize_u8(value as u8)
+    }
+
+    fn serialize_char(self, value: char) -> Result<(), ToEmojiError> {
+        self.serialize_u32(value as u32)
+    }
+
+    fn serialize_str(self, value: &str) -> Result<(), ToEmojiError> {
+        self.push_sequence(value.as_bytes());
+        Ok(())
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<(), ToEmojiError> {
+        self.push_sequence(value);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<(), ToEmojiError> {
+        self.serialize_u8(0)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<(), ToEmojiError> {
+        self.serialize_u8(1)?;
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), ToEmojiError> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), ToEmojiError> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<(), ToEmojiError> {
+        self.push_variant_tag(variant_index);
+        Ok(())
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), ToEmojiError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<(), ToEmojiError> {
+        self.push_variant_tag(variant_index);
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<CompoundSerializer<'a>, ToEmojiError> {
+        self.bytes.push(ByteInSequence::SequenceStart);
+        Ok(CompoundSerializer::new(self, true))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<CompoundSerializer<'a>, ToEmojiError> {
+        Ok(CompoundSerializer::new(self, false))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<CompoundSerializer<'a>, ToEmojiError> {
+        Ok(CompoundSerializer::new(self, false))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<CompoundSerializer<'a>, ToEmojiError> {
+        self.push_variant_tag(variant_index);
+        Ok(CompoundSerializer::new(self, false))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<CompoundSerializer<'a>, ToEmojiError> {
+        self.bytes.push(ByteInSequence::SequenceStart);
+        Ok(CompoundSerializer::new(self, true))
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<StructSerializer<'a>, ToEmojiError> {
+        Ok(StructSerializer::new(self, len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        len: usize,
+    ) -> Result<StructSerializer<'a>, ToEmojiError> {
+        self.push_variant_tag(variant_index);
+        Ok(StructSerializer::new(self, len))
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/// Serializes the elements of sequences, tuples and maps one after the other. Sequences and maps
+/// are ended with the stop emoji, tuples don't need it since their length is known.
+pub struct CompoundSerializer<'a> {
+    serializer: &'a mut EmojiSerializer,
+    is_sequence: bool,
+}
+
+impl<'a> CompoundSerializer<'a> {
+    fn new(serializer: &'a mut EmojiSerializer, is_sequence: bool) -> Self {
+        CompoundSerializer {
+            serializer,
+            is_sequence,
+        }
+    }
+
+    fn element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), ToEmojiError> {
+        value.serialize(&mut *self.serializer)
+    }
+
+    fn end(self) -> Result<(), ToEmojiError> {
+        if self.is_sequence {
+            self.serializer.bytes.push(ByteInSequence::SequenceEnd);
+        }
+
+        Ok(())
+    }
+}
+
+impl ser::SerializeSeq for CompoundSerializer<'_> {
+    type Ok = ();
+    type Error = ToEmojiError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), ToEmojiError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), ToEmojiError> {
+        CompoundSerializer::end(self)
+    }
+}
+
+impl ser::SerializeTuple for CompoundSerializer<'_> {
+    type Ok = ();
+    type Error = ToEmojiError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), ToEmojiError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), ToEmojiError> {
+        CompoundSerializer::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for CompoundSerializer<'_> {
+    type Ok = ();
+    type Error = ToEmojiError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), ToEmojiError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), ToEmojiError> {
+        CompoundSerializer::end(self)
+    }
+}
+
+impl ser::SerializeTupleVariant for CompoundSerializer<'_> {
+    type Ok = ();
+    type Error = ToEmojiError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), ToEmojiError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), ToEmojiError> {
+        CompoundSerializer::end(self)
+    }
+}
+
+impl ser::SerializeMap for CompoundSerializer<'_> {
+    type Ok = ();
+    type Error = ToEmojiError;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), ToEmojiError> {
+        self.element(key)
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), ToEmojiError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), ToEmojiError> {
+        CompoundSerializer::end(self)
+    }
+}
+
+/// Serde hands over the fields of a struct in declaration order, so they are serialized on their
+/// own and put in alphabetical order at the end.
+pub struct StructSerializer<'a> {
+    serializer: &'a mut EmojiSerializer,
+    fields: Vec<(&'static str, Vec<ByteInSequence>)>,
+}
+
+impl<'a> StructSerializer<'a> {
+    fn new(serializer: &'a mut EmojiSerializer, len: usize) -> Self {
+        StructSerializer {
+            serializer,
+            fields: Vec::with_capacity(len),
+        }
+    }
+
+    fn field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), ToEmojiError> {
+        let mut field = EmojiSerializer::new();
+        value.serialize(&mut field)?;
+        self.fields.push((key, field.bytes));
+        Ok(())
+    }
+
+    fn end(mut self) -> Result<(), ToEmojiError> {
+        self.fields.sort_by_key(|(key, _)| *key);
+
+        for (_, bytes) in self.fields {
+            self.serializer.bytes.extend(bytes);
+        }
+
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for StructSerializer<'_> {
+    type Ok = ();
+    type Error = ToEmojiError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), ToEmojiError> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), ToEmojiError> {
+        StructSerializer::end(self)
+    }
+}
+
+impl ser::SerializeStructVariant for StructSerializer<'_> {
+    type Ok = ();
+    type Error = ToEmojiError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), ToEmojiError> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<(), ToEmojiError> {
+        StructSerializer::end(self)
+    }
+}
+
+/// A `serde::Deserializer` for the layout described at `EmojiSerializer`. The emoji don't say
+/// what type of value they hold, so `deserialize_any` isn't supported, the type that is being
+/// deserialized has to ask for what it expects.
+pub struct EmojiDeserializer<I>
+where
+    I: Iterator<Item = u8>,
+{
+    byte_stream: DecodeEmojiToBytes<I>,
+}
+
+impl<I> EmojiDeserializer<I>
+where
+    I: Iterator<Item = u8>,
+{
+    pub fn new(byte_stream: DecodeEmojiToBytes<I>) -> Self {
+        EmojiDeserializer { byte_stream }
+    }
+
+    pub fn into_inner(self) -> DecodeEmojiToBytes<I> {
+        self.byte_stream
+    }
+
+    fn construct<A: ConstructFromEmoji<A, I>>(&mut self) -> Result<A, FromEmojiError> {
+        A::construct_from_emoji(&mut self.byte_stream)
+    }
+
+    /// Errors from `Deserialize` implementations don't know where they happened, so they are
+    /// placed at the emoji that is currently being decoded.
+    fn locate<T>(&self, result: Result<T, FromEmojiError>) -> Result<T, FromEmojiError> {
+        result.map_err(|err| match err.kind {
+            FromEmojiErrorKind::Custom(_) if err.emoji_offset == 0 && err.byte_offset == 0 => {
+                let here = self
+                    .byte_stream
+                    .error_here(FromEmojiErrorKind::UnexpectedInput);
+
+                FromEmojiError {
+                    emoji_offset: here.emoji_offset,
+                    byte_offset: here.byte_offset,
+                    ..err
+                }
+            }
+            _ => err,
+        })
+    }
+
+    fn expect_sequence_end(&mut self) -> Result<(), FromEmojiError> {
+        const EXPECTED: &str = "the end of the sequence";
+
+        match self.byte_stream.next() {
+            Some(Ok(ByteInSequence::SequenceEnd)) => Ok(()),
+            Some(Ok(found)) => Err(self.byte_stream.unexpected(EXPECTED, Some(found))),
+            Some(Err(err)) => Err(err),
+            None => Err(self.byte_stream.unexpected(EXPECTED, None)),
+        }
+    }
+
+    fn unsupported(&self, message: &str) -> FromEmojiError {
+        self.byte_stream
+            .error_here(FromEmojiErrorKind::Custom(message.to_string()))
+    }
+}
+
+macro_rules! deserialize_constructed {
+    ($($method:ident: $type:ty => $visit:ident),*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FromEmojiError> {
+                let value = self.construct::<$type>()?;
+                self.locate(visitor.$visit(value))
+            }
+        )*
+    };
+}
+
+impl<'de, I> de::Deserializer<'de> for &mut EmojiDeserializer<I>
+where
+    I: Iterator<Item = u8>,
+{
+    type Error = FromEmojiError;
+
+    deserialize_constructed!(
+        deserialize_bool: bool => visit_bool,
+        deserialize_i8: i8 => visit_i8,
+        deserialize_i16: i16 => visit_i16,
+        deserialize_i32: i32 => visit_i32,
+        deserialize_i64: i64 => visit_i64,
+        deserialize_i128: i128 => visit_i128,
+        deserialize_u8: u8 => visit_u8,
+        deserialize_u16: u16 => visit_u16,
+        deserialize_u32: u32 => visit_u32,
+        deserialize_u64: u64 => visit_u64,
+        deserialize_u128: u128 => visit_u128,
+        deserialize_f32: f32 => visit_f32,
+        deserialize_f64: f64 => visit_f64,
+        deserialize_char: char => visit_char,
+        deserialize_str: String => visit_string,
+        deserialize_string: String => visit_string,
+        deserialize_bytes: Vec<u8> => visit_byte_buf,
+        deserialize_byte_buf: Vec<u8> => visit_byte_buf
+    );
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, FromEmojiError> {
+        Err(self
+            .unsupported("the emoji don't say what type of value they hold, see `to_tagged_emoji`"))
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(
+        self,
+        _visitor: V,
+    ) -> Result<V::Value, FromEmojiError> {
+        Err(self.unsupported("values can't be skipped without knowing their type"))
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(
+        self,
+        _visitor: V,
+    ) -> Result<V::Value, FromEmojiError> {
+        Err(self.unsupported("the names of fields and variants aren't part of the emoji"))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FromEmojiError> {
+        match self.construct::<u8>()? {
+            0 => self.locate(visitor.visit_none()),
+            1 => visitor.visit_some(&mut *self),
+            n => Err(self
+                .byte_stream
+                .unexpected("0 for None or 1 for Some", Some(ByteInSequence::Byte(n)))
+                .in_type("Option")),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FromEmojiError> {
+        self.locate(visitor.visit_unit())
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, FromEmojiError> {
+        self.locate(visitor.visit_unit())
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, FromEmojiError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FromEmojiError> {
+        self.byte_stream.expect_sequence_start()?;
+        let value = visitor.visit_seq(ElementAccess::new(self, "sequence", None));
+        let value = self.locate(value)?;
+        self.expect_sequence_end()?;
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, FromEmojiError> {
+        let value = visitor.visit_seq(ElementAccess::new(self, "tuple", Some(len)));
+        self.locate(value)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, FromEmojiError> {
+        let value = visitor.visit_seq(ElementAccess::new(self, name, Some(len)));
+        self.locate(value)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FromEmojiError> {
+        self.byte_stream.expect_sequence_start()?;
+        let value = visitor.visit_map(ElementAccess::new(self, "map", None));
+        let value = self.locate(value)?;
+        self.expect_sequence_end()?;
+        Ok(value)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, FromEmojiError> {
+        let mut sorted_fields = fields.to_vec();
+        sorted_fields.sort_unstable();
+
+        let value = visitor.visit_map(FieldAccess {
+            deserializer: &mut *self,
+            type_name: name,
+            fields: sorted_fields.into_iter(),
+            current_field: "",
+        });
+
+        self.locate(value)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, FromEmojiError> {
+        let tag = self.byte_stream.read_variant_tag()?;
+
+        let Some(variant) = variants.get(tag as usize) else {
+            return Err(self
+                .byte_stream
+                .error_here(FromEmojiErrorKind::UnknownVariant(tag))
+                .in_type(name));
+        };
+
+        let value = visitor.visit_enum(VariantAccess {
+            deserializer: &mut *self,
+            type_name: name,
+            tag,
+        });
+
+        self.locate(value)
+            .map_err(|err| err.within(name, PathSegment::Variant(variant)))
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/// Hands out the elements of sequences, tuples and maps. Sequences and maps go on until the stop
+/// emoji, tuples have a known length.
+struct ElementAccess<'a, I>
+where
+    I: Iterator<Item = u8>,
+{
+    deserializer: &'a mut EmojiDeserializer<I>,
+    type_name: &'static str,
+    index: usize,
+    len: Option<usize>,
+}
+
+impl<'a, I> ElementAccess<'a, I>
+where
+    I: Iterator<Item = u8>,
+{
+    fn new(
+        deserializer: &'a mut EmojiDeserializer<I>,
+        type_name: &'static str,
+        len: Option<usize>,
+    ) -> Self {
+        ElementAccess {
+            deserializer,
+            type_name,
+            index: 0,
+            len,
+        }
+    }
+
+    fn has_next(&mut self) -> bool {
+        match self.len {
+            Some(len) => self.index < len,
+            None => !self.deserializer.byte_stream.reached_end_of_sequence(),
+        }
+    }
+
+    fn element<'de, T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+        segment: PathSegment,
+    ) -> Result<T::Value, FromEmojiError> {
+        seed.deserialize(&mut *self.deserializer)
+            .map_err(|err| err.within(self.type_name, segment))
+    }
+}
+
+impl<'de, I> de::SeqAccess<'de> for ElementAccess<'_, I>
+where
+    I: Iterator<Item = u8>,
+{
+    type Error = FromEmojiError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, FromEmojiError> {
+        if !self.has_next() {
+            return Ok(None);
+        }
+
+        let segment = match self.len {
+            Some(_) => PathSegment::Position(self.index),
+            None => PathSegment::Index(self.index),
+        };
+
+        let element = self.element(seed, segment)?;
+        self.index += 1;
+        Ok(Some(element))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.len.map(|len| len - self.index)
+    }
+}
+
+impl<'de, I> de::MapAccess<'de> for ElementAccess<'_, I>
+where
+    I: Iterator<Item = u8>,
+{
+    type Error = FromEmojiError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, FromEmojiError> {
+        if !self.has_next() {
+            return Ok(None);
+        }
+
+        self.element(seed, PathSegment::Index(self.index)).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, FromEmojiError> {
+        let value = self.element(seed, PathSegment::Index(self.index))?;
+        self.index += 1;
+        Ok(value)
+    }
+}
+
+/// Hands out the fields of a struct in alphabetical order, which is the order they were
+/// serialized in.
+struct FieldAccess<'a, I>
+where
+    I: Iterator<Item = u8>,
+{
+    deserializer: &'a mut EmojiDeserializer<I>,
+    type_name: &'static str,
+    fields: vec::IntoIter<&'static str>,
+    current_field: &'static str,
+}
+
+impl<'de, I> de::MapAccess<'de> for FieldAccess<'_, I>
+where
+    I: Iterator<Item = u8>,
+{
+    type Error = FromEmojiError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, FromEmojiError> {
+        match self.fields.next() {
+            Some(field) => {
+                self.current_field = field;
+                seed.deserialize(field.into_deserializer()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, FromEmojiError> {
+        seed.deserialize(&mut *self.deserializer)
+            .map_err(|err| err.within(self.type_name, PathSegment::Field(self.current_field)))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.fields.len())
+    }
+}
+
+/// Picks the variant of an enum by its tag and hands out its fields.
+struct VariantAccess<'a, I>
+where
+    I: Iterator<Item = u8>,
+{
+    deserializer: &'a mut EmojiDeserializer<I>,
+    type_name: &'static str,
+    tag: u32,
+}
+
+impl<'de, 'a, I> de::EnumAccess<'de> for VariantAccess<'a, I>
+where
+    I: Iterator<Item = u8>,
+{
+    type Error = FromEmojiError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self), FromEmojiError> {
+        let variant = seed.deserialize(IntoDeserializer::<FromEmojiError>::into_deserializer(
+            self.tag,
+        ))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, I> de::VariantAccess<'de> for VariantAccess<'_, I>
+where
+    I: Iterator<Item = u8>,
+{
+    type Error = FromEmojiError;
+
+    fn unit_variant(self) -> Result<(), FromEmojiError> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, FromEmojiError> {
+        seed.deserialize(self.deserializer)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, FromEmojiError> {
+        de::Deserializer::deserialize_tuple_struct(self.deserializer, self.type_name, len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, FromEmojiError> {
+        de::Deserializer::deserialize_struct(self.deserializer, self.type_name, fields, visitor)
+    }
+}
@@ -0,0 +1,649 @@
+use alloc::string::{String, ToString};
+use alloc::vec::{self, Vec};
+use core::fmt;
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use serde::de::{
+    self, Deserialize, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess,
+    Visitor,
+};
+use serde::ser::{self, Error as _, Serialize};
+
+use super::constants::ByteInSequence;
+use super::demojfuscate::{Demojfuscate, IsEmojiRepresentation};
+use super::emojfuscate::{Emojfuscate, EncodeBytesAsEmoji};
+use super::error::FromEmojiError;
+use super::serde_format::ToEmojiError;
+use super::value::EmojiValue;
+
+/// Emojfuscate any value that implements `serde::Serialize` in the self-describing format, see
+/// `EmojiValue`. The emoji can be demojfuscated into an `EmojiValue` without knowing the type.
+pub fn to_tagged_emoji<T>(value: &T) -> Result<String, ToEmojiError>
+where
+    T: ?Sized + Serialize,
+{
+    to_tagged_emoji_stream(value).map(Iterator::collect)
+}
+
+/// Like `to_tagged_emoji`, but the emoji are returned as a stream so that a header, a checksum,
+/// error correction etc. can be added to them first.
+pub fn to_tagged_emoji_stream<T>(
+    value: &T,
+) -> Result<EncodeBytesAsEmoji<vec::IntoIter<ByteInSequence>>, ToEmojiError>
+where
+    T: ?Sized + Serialize,
+{
+    to_emoji_value(value).map(Emojfuscate::emojfuscate_stream)
+}
+
+/// Demojfuscate any value that implements `serde::Deserialize` from emoji in the self-describing
+/// format.
+pub fn from_tagged_emoji<T, I, X>(emoji: X) -> Result<T, FromEmojiError>
+where
+    T: DeserializeOwned,
+    I: Iterator<Item = u8>,
+    X: IsEmojiRepresentation<I>,
+{
+    from_emoji_value(emoji.demojfuscate()?)
+}
+
+/// Turn anything that implements `serde::Serialize` into an `EmojiValue`. Structs and maps become
+/// maps, tuples and sequences become lists and enum variants become their name, or a map from their
+/// name to their fields if they have any.
+pub fn to_emoji_value<T>(value: &T) -> Result<EmojiValue, ToEmojiError>
+where
+    T: ?Sized + Serialize,
+{
+    value.serialize(ValueSerializer)
+}
+
+/// Turn an `EmojiValue` into anything that implements `serde::Deserialize`.
+pub fn from_emoji_value<T>(value: EmojiValue) -> Result<T, FromEmojiError>
+where
+    T: DeserializeOwned,
+{
+    T::deserialize(value)
+}
+
+impl Serialize for EmojiValue {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            EmojiValue::Null => serializer.serialize_unit(),
+            EmojiValue::Bool(b) => serializer.serialize_bool(*b),
+            EmojiValue::Int(n) => serializer.serialize_i64(*n),
+            EmojiValue::UInt(n) => serializer.serialize_u64(*n),
+            EmojiValue::Float(n) => serializer.serialize_f64(*n),
+            EmojiValue::String(string) => serializer.serialize_str(string),
+            EmojiValue::Bytes(bytes) => serializer.serialize_bytes(bytes),
+            EmojiValue::List(values) => serializer.collect_seq(values),
+            EmojiValue::Map(entries) => {
+                serializer.collect_map(entries.iter().map(|(key, value)| (key, value)))
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for EmojiValue {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<EmojiValue, D::Error> {
+        deserializer.deserialize_any(EmojiValueVisitor)
+    }
+}
+
+struct EmojiValueVisitor;
+
+impl<'de> Visitor<'de> for EmojiValueVisitor {
+    type Value = EmojiValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "any value")
+    }
+
+    fn visit_unit<E>(self) -> Result<EmojiValue, E> {
+        Ok(EmojiValue::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<EmojiValue, E> {
+        Ok(EmojiValue::Null)
+    }
+
+    fn visit_some<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<EmojiValue, D::Error> {
+        EmojiValue::deserialize(deserializer)
+    }
+
+    fn visit_bool<E>(self, b: bool) -> Result<EmojiValue, E> {
+        Ok(EmojiValue::Bool(b))
+    }
+
+    fn visit_i64<E>(self, n: i64) -> Result<EmojiValue, E> {
+        Ok(EmojiValue::Int(n))
+    }
+
+    fn visit_u64<E>(self, n: u64) -> Result<EmojiValue, E> {
+        Ok(EmojiValue::UInt(n))
+    }
+
+    fn visit_f64<E>(self, n: f64) -> Result<EmojiValue, E> {
+        Ok(EmojiValue::Float(n))
+    }
+
+    fn visit_str<E>(self, string: &str) -> Result<EmojiValue, E> {
+        Ok(EmojiValue::String(string.to_string()))
+    }
+
+    fn visit_string<E>(self, string: String) -> Result<EmojiValue, E> {
+        Ok(EmojiValue::String(string))
+    }
+
+    fn visit_bytes<E>(self, bytes: &[u8]) -> Result<EmojiValue, E> {
+        Ok(EmojiValue::Bytes(bytes.to_vec()))
+    }
+
+    fn visit_byte_buf<E>(self, bytes: Vec<u8>) -> Result<EmojiValue, E> {
+        Ok(EmojiValue::Bytes(bytes))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<EmojiValue, A::Error> {
+        let mut values = Vec::new();
+
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+
+        Ok(EmojiValue::List(values))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<EmojiValue, A::Error> {
+        let mut entries = Vec::new();
+
+        while let Some(entry) = map.next_entry()? {
+            entries.push(entry);
+        }
+
+        Ok(EmojiValue::Map(entries))
+    }
+}
+
+/// A `serde::Serializer` that makes an `EmojiValue`.
+struct ValueSerializer;
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = EmojiValue;
+    type Error = ToEmojiError;
+    type SerializeSeq = ListSerializer;
+    type SerializeTuple = ListSerializer;
+    type SerializeTupleStruct = ListSerializer;
+    type SerializeTupleVariant = ListSerializer;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = MapSerializer;
+    type SerializeStructVariant = MapSerializer;
+
+    fn serialize_bool(self, value: bool) -> Result<EmojiValue, ToEmojiError> {
+        Ok(EmojiValue::Bool(value))
+    }
+
+    fn serialize_i8(self, value: i8) -> Result<EmojiValue, ToEmojiError> {
+        self.serialize_i64(value.into())
+    }
+
+    fn serialize_i16(self, value: i16) -> Result<EmojiValue, ToEmojiError> {
+        self.serialize_i64(value.into())
+    }
+
+    fn serialize_i32(self, value: i32) -> Result<EmojiValue, ToEmojiError> {
+        self.serialize_i64(value.into())
+    }
+
+    fn serialize_i64(self, value: i64) -> Result<EmojiValue, ToEmojiError> {
+        Ok(EmojiValue::Int(value))
+    }
+
+    fn serialize_i128(self, value: i128) -> Result<EmojiValue, ToEmojiError> {
+        i64::try_from(value)
+            .map(EmojiValue::Int)
+            .map_err(|_| ToEmojiError::custom("the number doesn't fit in 64 bits"))
+    }
+
+    fn serialize_u8(self, value: u8) -> Result<EmojiValue, ToEmojiError> {
+        self.serialize_u64(value.into())
+    }
+
+    fn serialize_u16(self, value: u16) -> Result<EmojiValue, ToEmojiError> {
+        self.serialize_u64(value.into())
+    }
+
+    fn serialize_u32(self, value: u32) -> Result<EmojiValue, ToEmojiError> {
+        self.serialize_u64(value.into())
+    }
+
+    fn serialize_u64(self, value: u64) -> Result<EmojiValue, ToEmojiError> {
+        Ok(EmojiValue::UInt(value))
+    }
+
+    fn serialize_u128(self, value: u128) -> Result<EmojiValue, ToEmojiError> {
+        u64::try_from(value)
+            .map(EmojiValue::UInt)
+            .map_err(|_| ToEmojiError::custom("the number doesn't fit in 64 bits"))
+    }
+
+    fn serialize_f32(self, value: f32) -> Result<EmojiValue, ToEmojiError> {
+        self.serialize_f64(value.into())
+    }
+
+    fn serialize_f64(self, value: f64) -> Result<EmojiValue, ToEmojiError> {
+        Ok(EmojiValue::Float(value))
+    }
+
+    fn serialize_char(self, value: char) -> Result<EmojiValue, ToEmojiError> {
+        Ok(EmojiValue::String(value.to_string()))
+    }
+
+    fn serialize_str(self, value: &str) -> Result<EmojiValue, ToEmojiError> {
+        Ok(EmojiValue::String(value.to_string()))
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<EmojiValue, ToEmojiError> {
+        Ok(EmojiValue::Bytes(value.to_vec()))
+    }
+
+    fn serialize_none(self) -> Result<EmojiValue, ToEmojiError> {
+        Ok(EmojiValue::Null)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<EmojiValue, ToEmojiError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<EmojiValue, ToEmojiError> {
+        Ok(EmojiValue::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<EmojiValue, ToEmojiError> {
+        Ok(EmojiValue::Null)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<EmojiValue, ToEmojiError> {
+        Ok(EmojiValue::String(variant.to_string()))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<EmojiValue, ToEmojiError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<EmojiValue, ToEmojiError> {
+        Ok(in_variant(Some(variant), value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<ListSerializer, ToEmojiError> {
+        Ok(ListSerializer::new(None, len.unwrap_or(0)))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<ListSerializer, ToEmojiError> {
+        Ok(ListSerializer::new(None, len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<ListSerializer, ToEmojiError> {
+        Ok(ListSerializer::new(None, len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<ListSerializer, ToEmojiError> {
+        Ok(ListSerializer::new(Some(variant), len))
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<MapSerializer, ToEmojiError> {
+        Ok(MapSerializer::new(None, len.unwrap_or(0)))
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<MapSerializer, ToEmojiError> {
+        Ok(MapSerializer::new(None, len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<MapSerializer, ToEmojiError> {
+        Ok(MapSerializer::new(Some(variant), len))
+    }
+}
+
+/// Wraps the value of an enum variant in a map from the name of the variant to the value.
+fn in_variant(variant: Option<&'static str>, value: EmojiValue) -> EmojiValue {
+    match variant {
+        Some(variant) => EmojiValue::Map(Vec::from([(
+            EmojiValue::String(variant.to_string()),
+            value,
+        )])),
+        None => value,
+    }
+}
+
+struct ListSerializer {
+    variant: Option<&'static str>,
+    values: Vec<EmojiValue>,
+}
+
+impl ListSerializer {
+    fn new(variant: Option<&'static str>, len: usize) -> Self {
+        ListSerializer {
+            variant,
+            values: Vec::with_capacity(len),
+        }
+    }
+
+    fn element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), ToEmojiError> {
+        self.values.push(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<EmojiValue, ToEmojiError> {
+        Ok(in_variant(self.variant, EmojiValue::List(self.values)))
+    }
+}
+
+impl ser::SerializeSeq for ListSerializer {
+    type Ok = EmojiValue;
+    type Error = ToEmojiError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), ToEmojiError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<EmojiValue, ToEmojiError> {
+        ListSerializer::end(self)
+    }
+}
+
+impl ser::SerializeTuple for ListSerializer {
+    type Ok = EmojiValue;
+    type Error = ToEmojiError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), ToEmojiError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<EmojiValue, ToEmojiError> {
+        ListSerializer::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for ListSerializer {
+    type Ok = EmojiValue;
+    type Error = ToEmojiError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), ToEmojiError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<EmojiValue, ToEmojiError> {
+        ListSerializer::end(self)
+    }
+}
+
+impl ser::SerializeTupleVariant for ListSerializer {
+    type Ok = EmojiValue;
+    type Error = ToEmojiError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), ToEmojiError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<EmojiValue, ToEmojiError> {
+        ListSerializer::end(self)
+    }
+}
+
+struct MapSerializer {
+    variant: Option<&'static str>,
+    entries: Vec<(EmojiValue, EmojiValue)>,
+    key: Option<EmojiValue>,
+}
+
+impl MapSerializer {
+    fn new(variant: Option<&'static str>, len: usize) -> Self {
+        MapSerializer {
+            variant,
+            entries: Vec::with_capacity(len),
+            key: None,
+        }
+    }
+
+    fn field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), ToEmojiError> {
+        let value = value.serialize(ValueSerializer)?;
+        self.entries
+            .push((EmojiValue::String(key.to_string()), value));
+        Ok(())
+    }
+
+    fn end(self) -> Result<EmojiValue, ToEmojiError> {
+        Ok(in_variant(self.variant, EmojiValue::Map(self.entries)))
+    }
+}
+
+impl ser::SerializeMap for MapSerializer {
+    type Ok = EmojiValue;
+    type Error = ToEmojiError;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), ToEmojiError> {
+        self.key = Some(key.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), ToEmojiError> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| ToEmojiError::custom("a map value was serialized without a key"))?;
+        self.entries.push((key, value.serialize(ValueSerializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<EmojiValue, ToEmojiError> {
+        MapSerializer::end(self)
+    }
+}
+
+impl ser::SerializeStruct for MapSerializer {
+    type Ok = EmojiValue;
+    type Error = ToEmojiError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), ToEmojiError> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<EmojiValue, ToEmojiError> {
+        MapSerializer::end(self)
+    }
+}
+
+impl ser::SerializeStructVariant for MapSerializer {
+    type Ok = EmojiValue;
+    type Error = ToEmojiError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), ToEmojiError> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<EmojiValue, ToEmojiError> {
+        MapSerializer::end(self)
+    }
+}
+
+impl<'de> IntoDeserializer<'de, FromEmojiError> for EmojiValue {
+    type Deserializer = EmojiValue;
+
+    fn into_deserializer(self) -> EmojiValue {
+        self
+    }
+}
+
+/// An `EmojiValue` says what kind of value it is, so unlike the normal format it can be
+/// deserialized without knowing the type, e.g. into a `serde_json::Value`.
+impl<'de> de::Deserializer<'de> for EmojiValue {
+    type Error = FromEmojiError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FromEmojiError> {
+        match self {
+            EmojiValue::Null => visitor.visit_unit(),
+            EmojiValue::Bool(b) => visitor.visit_bool(b),
+            EmojiValue::Int(n) => visitor.visit_i64(n),
+            EmojiValue::UInt(n) => visitor.visit_u64(n),
+            EmojiValue::Float(n) => visitor.visit_f64(n),
+            EmojiValue::String(string) => visitor.visit_string(string),
+            EmojiValue::Bytes(bytes) => visitor.visit_byte_buf(bytes),
+            EmojiValue::List(values) => {
+                let mut seq = SeqDeserializer::new(values.into_iter());
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            EmojiValue::Map(entries) => {
+                let mut map = MapDeserializer::new(entries.into_iter());
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, FromEmojiError> {
+        match self {
+            EmojiValue::Null => visitor.visit_none(),
+            value => visitor.visit_some(value),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, FromEmojiError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, FromEmojiError> {
+        let (variant, value) = match self {
+            EmojiValue::String(variant) => (variant, None),
+            EmojiValue::Map(entries) if entries.len() == 1 => match entries.into_iter().next() {
+                Some((EmojiValue::String(variant), value)) => (variant, Some(value)),
+                _ => return Err(de::Error::custom("expected the name of a variant")),
+            },
+            _ => {
+                return Err(de::Error::custom(
+                    "expected a variant name or a map with one entry",
+                ))
+            }
+        };
+
+        visitor
+            .visit_enum(VariantValue { variant, value })
+            .map_err(|err| err.in_type(name))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// An enum variant in an `EmojiValue`, its name along with its fields if it has any.
+struct VariantValue {
+    variant: String,
+    value: Option<EmojiValue>,
+}
+
+impl<'de> de::EnumAccess<'de> for VariantValue {
+    type Error = FromEmojiError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        mut self,
+        seed: V,
+    ) -> Result<(V::Value, Self), FromEmojiError> {
+        let variant = core::mem::take(&mut self.variant);
+        let variant = seed.deserialize(IntoDeserializer::<FromEmojiError>::into_deserializer(
+            variant,
+        ))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for VariantValue {
+    type Error = FromEmojiError;
+
+    fn unit_variant(self) -> Result<(), FromEmojiError> {
+        match self.value {
+            None | Some(EmojiValue::Null) => Ok(()),
+            Some(_) => Err(de::Error::custom("expected a variant without fields")),
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, FromEmojiError> {
+        seed.deserialize(self.value.unwrap_or(EmojiValue::Null))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, FromEmojiError> {
+        de::Deserializer::deserialize_any(self.value.unwrap_or(EmojiValue::Null), visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, FromEmojiError> {
+        de::Deserializer::deserialize_any(self.value.unwrap_or(EmojiValue::Null), visitor)
+    }
+}
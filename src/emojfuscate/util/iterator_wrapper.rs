@@ -13,4 +13,8 @@ where
     fn next(&mut self) -> Option<A> {
         self.iter.next()
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
 }
@@ -0,0 +1,202 @@
+use alloc::string::String;
+use alloc::vec::{self, Vec};
+
+use super::constants::ByteInSequence;
+use super::demojfuscate::{ConstructFromEmoji, DecodeEmojiToBytes};
+use super::emojfuscate::{Emojfuscate, EncodeBytesAsEmoji};
+use super::error::FromEmojiError;
+
+// every value in the self-describing format starts with one of these
+const TAG_NULL: u8 = 0;
+const TAG_FALSE: u8 = 1;
+const TAG_TRUE: u8 = 2;
+const TAG_INT: u8 = 3;
+const TAG_UINT: u8 = 4;
+const TAG_FLOAT: u8 = 5;
+const TAG_STRING: u8 = 6;
+const TAG_BYTES: u8 = 7;
+const TAG_LIST: u8 = 8;
+const TAG_MAP: u8 = 9;
+
+/// Any value in the self-describing format. The normal format only makes sense to someone who
+/// knows the type that the emoji were made from, in this format every value starts with a byte
+/// that says what kind of value it is, so the emoji can be decoded into an `EmojiValue` without
+/// knowing the type, e.g. to look at what's inside of them.
+///
+/// `emojfuscate` on an `EmojiValue` produces the self-describing format and so does `to_tagged_emoji`
+/// with the `serde` feature. The numbers are big endian, like in the normal format, and strings,
+/// byte strings, lists and maps are between the start and stop emoji, maps as a list of key-value
+/// pairs.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EmojiValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    String(String),
+    Bytes(Vec<u8>),
+    List(Vec<EmojiValue>),
+    Map(Vec<(EmojiValue, EmojiValue)>),
+}
+
+impl EmojiValue {
+    fn push_tagged(&self, bytes: &mut Vec<ByteInSequence>) {
+        fn push_bytes(bytes: &mut Vec<ByteInSequence>, tag: u8, data: &[u8]) {
+            bytes.push(ByteInSequence::Byte(tag));
+            bytes.extend(data.iter().copied().map(ByteInSequence::Byte));
+        }
+
+        fn push_sequence(bytes: &mut Vec<ByteInSequence>, tag: u8, data: &[u8]) {
+            bytes.push(ByteInSequence::Byte(tag));
+            bytes.push(ByteInSequence::SequenceStart);
+            bytes.extend(data.iter().copied().map(ByteInSequence::Byte));
+            bytes.push(ByteInSequence::SequenceEnd);
+        }
+
+        match self {
+            EmojiValue::Null => bytes.push(ByteInSequence::Byte(TAG_NULL)),
+            EmojiValue::Bool(false) => bytes.push(ByteInSequence::Byte(TAG_FALSE)),
+            EmojiValue::Bool(true) => bytes.push(ByteInSequence::Byte(TAG_TRUE)),
+            EmojiValue::Int(n) => push_bytes(bytes, TAG_INT, &n.to_be_bytes()),
+            EmojiValue::UInt(n) => push_bytes(bytes, TAG_UINT, &n.to_be_bytes()),
+            EmojiValue::Float(n) => push_bytes(bytes, TAG_FLOAT, &n.to_be_bytes()),
+            EmojiValue::String(string) => push_sequence(bytes, TAG_STRING, string.as_bytes()),
+            EmojiValue::Bytes(data) => push_sequence(bytes, TAG_BYTES, data),
+            EmojiValue::List(values) => {
+                bytes.push(ByteInSequence::Byte(TAG_LIST));
+                bytes.push(ByteInSequence::SequenceStart);
+
+                for value in values {
+                    value.push_tagged(bytes);
+                }
+
+                bytes.push(ByteInSequence::SequenceEnd);
+            }
+            EmojiValue::Map(entries) => {
+                bytes.push(ByteInSequence::Byte(TAG_MAP));
+                bytes.push(ByteInSequence::SequenceStart);
+
+                for (key, value) in entries {
+                    key.push_tagged(bytes);
+                    value.push_tagged(bytes);
+                }
+
+                bytes.push(ByteInSequence::SequenceEnd);
+            }
+        }
+    }
+}
+
+// the values can be nested to any depth, so they are written out before they are encoded instead
+// of being chained together like the other types
+impl Emojfuscate<vec::IntoIter<ByteInSequence>> for &EmojiValue {
+    fn emojfuscate_stream(self) -> EncodeBytesAsEmoji<vec::IntoIter<ByteInSequence>> {
+        let mut bytes = Vec::new();
+        self.push_tagged(&mut bytes);
+        EncodeBytesAsEmoji::new(bytes.into_iter())
+    }
+}
+
+impl Emojfuscate<vec::IntoIter<ByteInSequence>> for EmojiValue {
+    fn emojfuscate_stream(self) -> EncodeBytesAsEmoji<vec::IntoIter<ByteInSequence>> {
+        (&self).emojfuscate_stream()
+    }
+}
+
+impl<I> ConstructFromEmoji<EmojiValue, I> for EmojiValue
+where
+    I: Iterator<Item = u8>,
+{
+    fn construct_from_emoji(
+        byte_stream: &mut DecodeEmojiToBytes<I>,
+    ) -> Result<EmojiValue, FromEmojiError> {
+        let tag = u8::construct_from_emoji(byte_stream).map_err(|err| err.in_type("EmojiValue"))?;
+
+        let value = match tag {
+            TAG_NULL => Ok(EmojiValue::Null),
+            TAG_FALSE => Ok(EmojiValue::Bool(false)),
+            TAG_TRUE => Ok(EmojiValue::Bool(true)),
+            TAG_INT => i64::construct_from_emoji(byte_stream).map(EmojiValue::Int),
+            TAG_UINT => u64::construct_from_emoji(byte_stream).map(EmojiValue::UInt),
+            TAG_FLOAT => f64::construct_from_emoji(byte_stream).map(EmojiValue::Float),
+            TAG_STRING => String::construct_from_emoji(byte_stream).map(EmojiValue::String),
+            TAG_BYTES => Vec::<u8>::construct_from_emoji(byte_stream).map(EmojiValue::Bytes),
+            TAG_LIST => byte_stream
+                .construct_nested(Vec::<EmojiValue>::construct_from_emoji)
+                .map(EmojiValue::List),
+            TAG_MAP => byte_stream
+                .construct_nested(<Vec<(EmojiValue, EmojiValue)>>::construct_from_emoji)
+                .map(EmojiValue::Map),
+            n => {
+                return Err(byte_stream
+                    .unexpected("the tag of a value", Some(ByteInSequence::Byte(n)))
+                    .in_type("EmojiValue"))
+            }
+        };
+
+        value.map_err(|err| err.in_type("EmojiValue"))
+    }
+}
+
+#[cfg(feature = "json")]
+impl From<EmojiValue> for serde_json::Value {
+    /// Byte strings become lists of numbers and map keys that aren't strings are written as JSON,
+    /// since JSON has neither byte strings nor other keys. Floats that JSON can't represent, like
+    /// `NaN`, become `null`.
+    fn from(value: EmojiValue) -> Self {
+        use alloc::string::ToString;
+        use serde_json::{Map, Number, Value};
+
+        match value {
+            EmojiValue::Null => Value::Null,
+            EmojiValue::Bool(b) => Value::Bool(b),
+            EmojiValue::Int(n) => Value::Number(n.into()),
+            EmojiValue::UInt(n) => Value::Number(n.into()),
+            EmojiValue::Float(n) => Number::from_f64(n).map_or(Value::Null, Value::Number),
+            EmojiValue::String(string) => Value::String(string),
+            EmojiValue::Bytes(bytes) => bytes.into_iter().map(Value::from).collect(),
+            EmojiValue::List(values) => values.into_iter().map(Value::from).collect(),
+            EmojiValue::Map(entries) => {
+                let mut map = Map::new();
+
+                for (key, value) in entries {
+                    let key = match key {
+                        EmojiValue::String(key) => key,
+                        key => Value::from(key).to_string(),
+                    };
+
+                    map.insert(key, Value::from(value));
+                }
+
+                Value::Object(map)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+impl From<serde_json::Value> for EmojiValue {
+    fn from(value: serde_json::Value) -> Self {
+        use serde_json::Value;
+
+        match value {
+            Value::Null => EmojiValue::Null,
+            Value::Bool(b) => EmojiValue::Bool(b),
+            Value::Number(n) => match (n.as_u64(), n.as_i64(), n.as_f64()) {
+                (Some(n), _, _) => EmojiValue::UInt(n),
+                (None, Some(n), _) => EmojiValue::Int(n),
+                (None, None, n) => EmojiValue::Float(n.unwrap_or(f64::NAN)),
+            },
+            Value::String(string) => EmojiValue::String(string),
+            Value::Array(values) => {
+                EmojiValue::List(values.into_iter().map(EmojiValue::from).collect())
+            }
+            Value::Object(map) => EmojiValue::Map(
+                map.into_iter()
+                    .map(|(key, value)| (EmojiValue::String(key), EmojiValue::from(value)))
+                    .collect(),
+            ),
+        }
+    }
+}
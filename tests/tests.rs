@@ -1,7 +1,25 @@
 #[cfg(test)]
 mod tests {
-    use emojfuscate::{ConstructFromEmoji, Demojfuscate, Emojfuscate};
+    use emojfuscate::{
+        decode_str_into, encode_slice_into, Alphabet, AlphabetError, ArrayString, ByteInSequence,
+        ConstructFromEmoji, Demojfuscate, Emojfuscate, EmojfuscateByteStream, EmojfuscateFixedSize,
+        EmojfuscateWithSyncMarkers, EmojiReader, EmojiSchema, EmojiValue, EmojiVisitor,
+        EmojiWriter, EncodeBytesAsEmoji, ErrorCorrection, ErrorCorrectionError, Field, Fields,
+        FromEmojiError, FromEmojiErrorKind, Header, IsEmojiRepresentation, Schema, Token, Variant,
+        ALPHABET_SIZE, DEFAULT_NESTING_LIMIT, FORMAT_VERSION,
+    };
+    #[cfg(feature = "serde")]
+    use emojfuscate::{
+        from_emoji, from_tagged_emoji, to_emoji, to_emoji_value, to_tagged_emoji, PathSegment,
+    };
+    #[cfg(feature = "async")]
+    use emojfuscate::{AsyncEmojiReader, AsyncEmojiWriter, DemojfuscateAsync, EmojfuscateAsync};
+    #[cfg(feature = "async")]
+    use futures::{executor::block_on, stream, AsyncReadExt, AsyncWriteExt, StreamExt};
     use proptest::prelude::*;
+    #[cfg(feature = "serde")]
+    use serde::{Deserialize, Serialize};
+    use std::io::{Read, Write};
 
     // there's no Arbitrary instance for Uuid :(
     #[test]
@@ -51,38 +69,2086 @@ mod tests {
         );
     }
 
+    #[test]
+    fn emojfuscate_fixed_size_lengths() {
+        let original_message = uuid::uuid!("67e55044-10b1-426f-9247-bb680e5fe0c8");
+
+        // 128 bits are 12 emoji and 8 bits, which are padded
+        assert_eq!(
+            original_message.emojfuscate_stream().size_hint(),
+            (14, Some(14))
+        );
+        assert_eq!(1234u64.emojfuscate_stream().size_hint(), (8, Some(8)));
+        assert_eq!(original_message.emoji_len(), 14);
+        assert_eq!(
+            original_message.encoded_utf8_len(),
+            original_message.emojfuscate().len()
+        );
+
+        let tuple = (123u8, original_message, 'a');
+        assert_eq!(tuple.emoji_len(), tuple.emojfuscate().chars().count());
+        assert_eq!(tuple.encoded_utf8_len(), tuple.emojfuscate().len());
+
+        let array = [1u64, 2, 3];
+        assert_eq!(array.emoji_len(), array.emojfuscate().chars().count());
+    }
+
+    #[test]
+    fn emojfuscate_array_of_fixed_size_values() {
+        #[derive(Emojfuscate, EmojfuscateFixedSize, Clone)]
+        struct Id<A> {
+            shard: u16,
+            id: A,
+        }
+
+        let id = Id {
+            shard: 3,
+            id: uuid::uuid!("67e55044-10b1-426f-9247-bb680e5fe0c8"),
+        };
+
+        assert_eq!(Id::<uuid::Uuid>::ENCODED_BYTES, 18);
+        assert_eq!(Id::<uuid::Uuid>::EMOJI_LEN, id.clone().emoji_len());
+
+        let emojified: ArrayString<{ Id::<uuid::Uuid>::MAX_UTF8_LEN }> =
+            id.clone().emojfuscate_array();
+        assert_eq!(emojified.as_str(), id.emojfuscate());
+
+        let emojified: ArrayString<{ <[u16; 3]>::MAX_UTF8_LEN }> = [1u16, 2, 3].emojfuscate_array();
+        assert_eq!(emojified.as_str(), [1u16, 2, 3].emojfuscate());
+
+        let emojified: ArrayString<{ <()>::MAX_UTF8_LEN }> = ().emojfuscate_array();
+        assert_eq!(emojified.as_str(), "");
+    }
+
+    /// Check that the size hint is right all the way through the emoji.
+    fn assert_size_hints<I: Iterator<Item = char>>(mut iter: I) {
+        let mut size_hints = vec![iter.size_hint()];
+        while iter.next().is_some() {
+            size_hints.push(iter.size_hint());
+        }
+
+        for (emitted, (lower, upper)) in size_hints.iter().enumerate() {
+            let remaining = size_hints.len() - 1 - emitted;
+            assert!(
+                *lower <= remaining && upper.map_or(true, |upper| upper >= remaining),
+                "size hint {:?} with {} emoji left",
+                (lower, upper),
+                remaining
+            );
+        }
+    }
+
     #[test]
     fn emojfuscate_derive_construct_from_emoji_unit_struct() {
         #[derive(Emojfuscate, ConstructFromEmoji, Debug, PartialEq)]
-        struct UnitStruct;
+        struct UnitStruct;
+
+        let emojified = UnitStruct.emojfuscate();
+        let roundtrip_message = (&emojified).demojfuscate();
+        assert_eq!(
+            roundtrip_message,
+            Ok(UnitStruct),
+            "emojfuscated version: {}",
+            emojified
+        );
+    }
+
+    #[test]
+    fn emojfuscate_infinite_streams() {
+        let mut source = std::iter::repeat("hello"); // infinite stream of String : Iterator<Item = String>
+
+        let demojfuscated: Result<Vec<String>, emojfuscate::FromEmojiError> = (&mut source)
+            .emojfuscate_stream() // infinite stream of emoji: Iterator<Item = char>
+            .demojfuscate_stream() // infinite stream of hopefully String: Iterator<Item = Result<String, FromEmojiError>>
+            .take(3) // finite stream of hopefully String: Iterator<Item = Result<String, FromEmojiError>>
+            .collect(); // Result<Vec<String>, FromEmojiError>
+
+        assert_eq!(
+            demojfuscated,
+            Ok(vec![
+                "hello".to_string(),
+                "hello".to_string(),
+                "hello".to_string()
+            ])
+        );
+    }
+
+    fn reversed_alphabet() -> &'static Alphabet {
+        let mut emoji = Alphabet::default_alphabet().emoji().to_vec();
+        emoji.reverse();
+        Box::leak(Box::new(Alphabet::new(emoji).unwrap()))
+    }
+
+    #[test]
+    fn emojfuscate_custom_alphabet() {
+        let alphabet = reversed_alphabet();
+        let original_message = ("hello".to_string(), 1234u16);
+
+        let emojified: String = original_message
+            .clone()
+            .emojfuscate_stream()
+            .with_alphabet(alphabet)
+            .collect();
+
+        assert_ne!(emojified, original_message.clone().emojfuscate());
+
+        let roundtrip_message = (&emojified)
+            .demojfuscate_byte_stream()
+            .with_alphabet(alphabet)
+            .demojfuscate();
+
+        assert_eq!(
+            roundtrip_message,
+            Ok(original_message),
+            "emojfuscated version: {}",
+            emojified
+        );
+    }
+
+    #[test]
+    fn alphabet_rejects_duplicates() {
+        let mut emoji = Alphabet::default_alphabet().emoji().to_vec();
+        emoji[10] = emoji[20];
+
+        assert_eq!(
+            Alphabet::new(emoji).err(),
+            Some(AlphabetError::DuplicateEmoji(
+                Alphabet::default_alphabet().emoji()[20]
+            ))
+        );
+    }
+
+    #[test]
+    fn alphabet_rejects_wrong_size() {
+        let emoji = Alphabet::default_alphabet().emoji()[1..].to_vec();
+
+        assert_eq!(
+            Alphabet::new(emoji).err(),
+            Some(AlphabetError::WrongNumberOfEmoji {
+                expected: emojfuscate::ALPHABET_SIZE,
+                found: emojfuscate::ALPHABET_SIZE - 1
+            })
+        );
+    }
+
+    #[test]
+    fn alphabet_rejects_white_space() {
+        let mut emoji = Alphabet::default_alphabet().emoji().to_vec();
+        emoji[0] = ' ';
+
+        assert_eq!(
+            Alphabet::new(emoji).err(),
+            Some(AlphabetError::WhiteSpace(' '))
+        );
+    }
+
+    #[test]
+    fn alphabet_from_array_in_a_static() {
+        const EMOJI: [char; ALPHABET_SIZE] = {
+            let mut emoji = ['a'; ALPHABET_SIZE];
+            let mut index = 0;
+
+            while index < ALPHABET_SIZE {
+                emoji[index] = match char::from_u32(0x1f000 + index as u32) {
+                    Some(c) => c,
+                    None => panic!("unexpected unicode"),
+                };
+
+                index += 1;
+            }
+
+            emoji
+        };
+
+        static CONTIGUOUS: Alphabet = match Alphabet::from_array(EMOJI) {
+            Ok(alphabet) => alphabet,
+            Err(_) => panic!("invalid alphabet"),
+        };
+
+        let emojified: String = "hello"
+            .emojfuscate_stream()
+            .with_alphabet(&CONTIGUOUS)
+            .collect();
+
+        assert!(emojified.chars().all(|c| EMOJI.contains(&c)));
+
+        let demojfuscated: Result<String, _> = emojified
+            .demojfuscate_byte_stream()
+            .with_alphabet(&CONTIGUOUS)
+            .demojfuscate();
+
+        assert_eq!(demojfuscated, Ok("hello".to_string()));
+    }
+
+    #[test]
+    fn demojfuscate_rejects_invalid_utf8() {
+        let emoji = Alphabet::default_alphabet().value_to_emoji(0).to_string();
+
+        let invalid_inputs: [&[u8]; 4] = [
+            // a lone continuation byte
+            &[0b1000_0000],
+            // a four byte character that ends after two bytes
+            &emoji.as_bytes()[..2],
+            // '/' encoded with two bytes instead of one
+            &[0b1100_0000, 0b1010_1111],
+            // a surrogate
+            &[0xed, 0xa0, 0x80],
+        ];
+
+        for input in invalid_inputs {
+            let result: Result<u8, FromEmojiError> = input.iter().copied().demojfuscate();
+            assert_eq!(
+                result.map_err(|err| err.kind),
+                Err(FromEmojiErrorKind::InvalidUtf8),
+                "input: {:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn emojfuscate_with_header() {
+        let original_message = ("hello".to_string(), 1234u16);
+        let emojified: String = original_message
+            .clone()
+            .emojfuscate_stream()
+            .with_header()
+            .collect();
+
+        let mut byte_stream = (&emojified).demojfuscate_byte_stream().require_header();
+        let roundtrip_message = <(String, u16)>::construct_from_emoji(&mut byte_stream);
+
+        assert_eq!(roundtrip_message, Ok(original_message.clone()));
+        assert_eq!(
+            byte_stream.header(),
+            Some(Header {
+                version: FORMAT_VERSION,
+                flags: 0,
+                alphabet_fingerprint: Alphabet::default_alphabet().fingerprint()
+            })
+        );
+
+        // the header is checked even when it isn't required
+        assert_eq!((&emojified).demojfuscate(), Ok(original_message));
+    }
+
+    #[test]
+    fn demojfuscate_missing_header() {
+        let emojified = 1234u16.emojfuscate();
+        let roundtrip_message: Result<u16, _> = (&emojified)
+            .demojfuscate_byte_stream()
+            .require_header()
+            .demojfuscate();
+
+        assert_eq!(
+            roundtrip_message.map_err(|err| err.kind),
+            Err(FromEmojiErrorKind::MissingHeader)
+        );
+    }
+
+    #[test]
+    fn demojfuscate_unsupported_version() {
+        let alphabet = Alphabet::default_alphabet();
+        let mut emojified: String = Header {
+            version: FORMAT_VERSION + 1,
+            flags: 0,
+            alphabet_fingerprint: alphabet.fingerprint(),
+        }
+        .to_emoji_values()
+        .map(|value| alphabet.value_to_emoji(value))
+        .iter()
+        .collect();
+        emojified.push_str(&1234u16.emojfuscate());
+
+        let roundtrip_message: Result<u16, _> = (&emojified).demojfuscate();
+
+        assert_eq!(
+            roundtrip_message.map_err(|err| err.kind),
+            Err(FromEmojiErrorKind::UnsupportedVersion(FORMAT_VERSION + 1))
+        );
+    }
+
+    #[test]
+    fn demojfuscate_enum_from_before_variant_tags() {
+        // enums used to have a byte for the variant and an `Option` for every variant, so emoji
+        // from the first version of the format can't be read anymore
+        let alphabet = Alphabet::default_alphabet();
+        let mut emojified: String = Header {
+            version: 1,
+            flags: 0,
+            alphabet_fingerprint: alphabet.fingerprint(),
+        }
+        .to_emoji_values()
+        .map(|value| alphabet.value_to_emoji(value))
+        .iter()
+        .collect();
+        emojified.push_str(&(0u8, Some(5u8), None::<String>).emojfuscate());
+
+        let roundtrip_message: Result<Result<u8, String>, _> = (&emojified).demojfuscate();
+
+        assert_eq!(
+            roundtrip_message.map_err(|err| err.kind),
+            Err(FromEmojiErrorKind::UnsupportedVersion(1))
+        );
+    }
+
+    #[test]
+    fn demojfuscate_header_alphabet_mismatch() {
+        let mut emoji = Alphabet::default_alphabet().emoji().to_vec();
+        emoji.swap(600, 601);
+        let alphabet = Box::leak(Box::new(Alphabet::new(emoji).unwrap()));
+
+        let emojified: String = 1234u16
+            .emojfuscate_stream()
+            .with_alphabet(alphabet)
+            .with_header()
+            .collect();
+        let roundtrip_message: Result<u16, _> = (&emojified).demojfuscate();
+
+        assert_eq!(
+            roundtrip_message.map_err(|err| err.kind),
+            Err(FromEmojiErrorKind::AlphabetMismatch)
+        );
+    }
+
+    #[test]
+    fn emojfuscate_with_checksum() {
+        let original_message = ("hello".to_string(), 1234u16);
+        let emojified: String = original_message
+            .clone()
+            .emojfuscate_stream()
+            .with_header()
+            .with_checksum()
+            .collect();
+
+        assert_eq!((&emojified).demojfuscate(), Ok(original_message.clone()));
+
+        // without a header the decoder has to be told that there's a checksum
+        let emojified: String = original_message
+            .clone()
+            .emojfuscate_stream()
+            .with_checksum()
+            .collect();
+        let roundtrip_message = (&emojified)
+            .demojfuscate_byte_stream()
+            .expect_checksum()
+            .demojfuscate();
+
+        assert_eq!(roundtrip_message, Ok(original_message));
+    }
+
+    #[test]
+    fn demojfuscate_checksum_mismatch() {
+        let emojified: Vec<char> = ("hello".to_string(), 1234u16)
+            .emojfuscate_stream()
+            .with_header()
+            .with_checksum()
+            .collect();
+
+        let mut swapped = emojified.clone();
+        swapped.swap(6, 7);
+        let swapped: String = swapped.into_iter().collect();
+
+        let roundtrip_message: Result<(String, u16), _> = (&swapped).demojfuscate();
+        assert_eq!(
+            roundtrip_message.map_err(|err| err.kind),
+            Err(FromEmojiErrorKind::ChecksumMismatch)
+        );
+
+        let mut dropped = emojified.clone();
+        dropped.remove(8);
+        let dropped: String = dropped.into_iter().collect();
+
+        let roundtrip_message: Result<(String, u16), _> = (&dropped).demojfuscate();
+        assert_eq!(
+            roundtrip_message.map_err(|err| err.kind),
+            Err(FromEmojiErrorKind::ChecksumMismatch)
+        );
+    }
+
+    #[test]
+    fn demojfuscate_missing_checksum() {
+        let emojified = 1234u16.emojfuscate();
+        let roundtrip_message: Result<u16, _> = (&emojified)
+            .demojfuscate_byte_stream()
+            .expect_checksum()
+            .demojfuscate();
+
+        assert_eq!(
+            roundtrip_message.map_err(|err| err.kind),
+            Err(FromEmojiErrorKind::MissingChecksum)
+        );
+    }
+
+    #[test]
+    fn demojfuscate_stream_checksum_mismatch() {
+        let mut emojified: Vec<char> = vec![1u8, 2, 3, 4, 5]
+            .emojfuscate_stream()
+            .with_header()
+            .with_checksum()
+            .collect();
+
+        let last_data_emoji = emojified.len() - 7;
+        emojified[last_data_emoji] = emojified[last_data_emoji - 1];
+        let emojified: String = emojified.into_iter().collect();
+
+        let roundtrip_message: Vec<Result<u8, _>> = (&emojified).demojfuscate_stream().collect();
+        assert_eq!(
+            roundtrip_message
+                .last()
+                .cloned()
+                .map(|r| r.map_err(|err| err.kind)),
+            Some(Err(FromEmojiErrorKind::ChecksumMismatch))
+        );
+    }
+
+    #[test]
+    fn emojfuscate_with_error_correction() {
+        let original_message = "hello there, this message is long enough for a few blocks";
+        let error_correction = ErrorCorrection::new(16, 4).unwrap();
+
+        let emojified: String = original_message
+            .emojfuscate_stream()
+            .with_header()
+            .with_checksum()
+            .with_error_correction(error_correction)
+            .collect();
+
+        assert_eq!(
+            (&emojified).demojfuscate(),
+            Ok(original_message.to_string())
+        );
+
+        // without a header the decoder has to be told about the error correction
+        let emojified = original_message.emojfuscate_with_error_correction(error_correction);
+        let roundtrip_message = (&emojified)
+            .demojfuscate_byte_stream()
+            .with_error_correction(error_correction)
+            .demojfuscate();
+
+        assert_eq!(roundtrip_message, Ok(original_message.to_string()));
+    }
+
+    #[test]
+    fn demojfuscate_corrects_errors() {
+        let original_message = "hello there, this message is long enough for a few blocks";
+        let error_correction = ErrorCorrection::new(16, 4).unwrap();
+
+        let mut emojified: Vec<char> = original_message
+            .emojfuscate_stream()
+            .with_header()
+            .with_checksum()
+            .with_error_correction(error_correction)
+            .collect();
+
+        // two wrong emoji in the first block and an unreadable one in the second block, the header
+        // and the error correction parameters are the first six emoji
+        emojified[7] = emojified[8];
+        emojified[20] = emojified[21];
+        emojified[30] = '?';
+        let emojified: String = emojified.into_iter().collect();
+
+        let mut byte_stream = (&emojified).demojfuscate_byte_stream();
+        assert_eq!(byte_stream.verify_checksum(), Ok(()));
+        assert_eq!(
+            String::construct_from_emoji(&mut byte_stream),
+            Ok(original_message.to_string())
+        );
+        assert_eq!(byte_stream.corrected_symbols(), 3);
+    }
+
+    #[test]
+    fn demojfuscate_too_many_errors() {
+        let error_correction = ErrorCorrection::new(16, 4).unwrap();
+        let mut emojified: Vec<char> = "hello there"
+            .emojfuscate_stream()
+            .with_header()
+            .with_error_correction(error_correction)
+            .collect();
+
+        emojified[7] = emojified[8];
+        emojified[9] = emojified[10];
+        emojified[11] = emojified[12];
+        let emojified: String = emojified.into_iter().collect();
+
+        let roundtrip_message: Result<String, _> = (&emojified).demojfuscate();
+        assert_eq!(
+            roundtrip_message.map_err(|err| err.kind),
+            Err(FromEmojiErrorKind::TooManyErrors)
+        );
+    }
+
+    #[test]
+    fn error_correction_rejects_invalid_parameters() {
+        assert_eq!(
+            ErrorCorrection::new(0, 4),
+            Err(ErrorCorrectionError::NoDataSymbols)
+        );
+        assert_eq!(
+            ErrorCorrection::new(16, 0),
+            Err(ErrorCorrectionError::NoParitySymbols)
+        );
+        assert_eq!(
+            ErrorCorrection::new(1000, 24),
+            Err(ErrorCorrectionError::BlockTooLong {
+                max: 1023,
+                found: 1024
+            })
+        );
+    }
+
+    #[test]
+    fn emojfuscate_with_compression() {
+        let original_message = "na ".repeat(1000) + "batman!";
+
+        let emojified: String = original_message
+            .clone()
+            .emojfuscate_stream()
+            .with_compression()
+            .with_header()
+            .collect();
+
+        let uncompressed = original_message.clone().emojfuscate();
+        assert!(emojified.chars().count() < uncompressed.chars().count() / 10);
+        assert_eq!((&emojified).demojfuscate(), Ok(original_message.clone()));
+
+        // without a header the decoder has to be told that the data is compressed
+        let emojified: String = original_message
+            .clone()
+            .emojfuscate_stream()
+            .with_compression()
+            .collect();
+        let roundtrip_message = (&emojified)
+            .demojfuscate_byte_stream()
+            .with_compression()
+            .demojfuscate();
+
+        assert_eq!(roundtrip_message, Ok(original_message));
+    }
+
+    #[test]
+    fn emojfuscate_compressed_infinite_stream() {
+        let demojfuscated: Result<Vec<String>, FromEmojiError> = std::iter::repeat("hello")
+            .emojfuscate_stream()
+            .with_compression()
+            .with_header()
+            .with_checksum()
+            .with_error_correction(ErrorCorrection::default())
+            .demojfuscate_stream()
+            .take(1000)
+            .collect();
+
+        assert_eq!(demojfuscated, Ok(vec!["hello".to_string(); 1000]));
+    }
+
+    fn numbered_values() -> Vec<String> {
+        (0..10).map(|i| format!("value {}", i)).collect()
+    }
+
+    // the sync marker is the last emoji in the alphabet
+    fn sync_marker_positions(emojified: &[char]) -> Vec<usize> {
+        let sync_marker = Alphabet::default_alphabet().emoji()[ALPHABET_SIZE - 1];
+
+        emojified
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| **c == sync_marker)
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    #[test]
+    fn emojfuscate_with_sync_markers() {
+        for interval in [1, 3, 100] {
+            let emojified: String = numbered_values()
+                .into_iter()
+                .emojfuscate_stream_with_sync_markers(interval)
+                .collect();
+
+            let roundtrip_message: Result<Vec<String>, _> =
+                (&emojified).demojfuscate_stream().collect();
+            assert_eq!(roundtrip_message, Ok(numbered_values()));
+        }
+    }
+
+    #[test]
+    fn demojfuscate_stream_recovers_from_errors() {
+        let mut emojified: Vec<char> = numbered_values()
+            .into_iter()
+            .emojfuscate_stream_with_sync_markers(1)
+            .collect();
+
+        let sync_markers = sync_marker_positions(&emojified);
+        assert_eq!(sync_markers.len(), 10);
+
+        // garble the emoji right after the start emoji of the fourth value
+        emojified[sync_markers[3] + 2] = '?';
+        let emojified: String = emojified.into_iter().collect();
+
+        let mut stream = (&emojified).demojfuscate_stream().recover_from_errors();
+        let roundtrip_message: Vec<Result<String, _>> = stream.by_ref().collect();
+
+        let mut expected: Vec<Result<String, _>> = numbered_values().into_iter().map(Ok).collect();
+        expected[3] = Err(FromEmojiErrorKind::InputIsNotAnEmoji('?'));
+
+        assert_eq!(
+            roundtrip_message
+                .iter()
+                .cloned()
+                .map(|r| r.map_err(|err| err.kind))
+                .collect::<Vec<_>>(),
+            expected
+        );
+        assert_eq!(
+            roundtrip_message[3].as_ref().unwrap_err().emoji_offset,
+            sync_markers[3] + 2
+        );
+        assert_eq!(
+            stream.skipped_emoji(),
+            sync_markers[4] - sync_markers[3] - 3
+        );
+
+        // without recovering the stream ends after the first error
+        let roundtrip_message: Vec<Result<String, _>> =
+            (&emojified).demojfuscate_stream().collect();
+        assert_eq!(roundtrip_message.len(), 4);
+    }
+
+    #[test]
+    fn demojfuscate_stream_join_midway() {
+        let emojified: Vec<char> = numbered_values()
+            .into_iter()
+            .emojfuscate_stream_with_sync_markers(1)
+            .collect();
+
+        let sync_markers = sync_marker_positions(&emojified);
+        let midway: String = emojified[sync_markers[5] - 2..].iter().collect();
+
+        let mut stream = (&midway).demojfuscate_stream().join_midway();
+        let roundtrip_message: Result<Vec<String>, _> = stream.by_ref().collect();
+
+        assert_eq!(roundtrip_message, Ok(numbered_values()[5..].to_vec()));
+        assert_eq!(stream.skipped_emoji(), 2);
+    }
+
+    #[test]
+    fn demojfuscate_compressed_stream_join_midway() {
+        let values: Vec<String> = numbered_values().into_iter().cycle().take(100).collect();
+        let emojified: Vec<char> = values
+            .clone()
+            .into_iter()
+            .emojfuscate_stream_with_sync_markers(10)
+            .with_compression()
+            .collect();
+
+        let sync_markers = sync_marker_positions(&emojified);
+        let midway: String = emojified[sync_markers[3] - 1..].iter().collect();
+
+        let roundtrip_message: Result<Vec<String>, _> = (&midway)
+            .demojfuscate_byte_stream()
+            .with_compression()
+            .demojfuscate_stream()
+            .join_midway()
+            .collect();
+
+        assert_eq!(roundtrip_message, Ok(values[30..].to_vec()));
+    }
+
+    #[test]
+    fn demojfuscate_error_location() {
+        let pairs: Vec<(u8, u8)> = vec![(1, 0), (2, 1), (3, 0), (4, 7), (5, 1)];
+        let emojified = pairs.emojfuscate();
+
+        let roundtrip_message: Result<Vec<(u8, bool)>, _> = (&emojified).demojfuscate();
+        let err = roundtrip_message.unwrap_err();
+
+        assert_eq!(err.kind, FromEmojiErrorKind::UnexpectedInput);
+        assert_eq!(err.expected, Some("0 or 1"));
+        assert_eq!(err.found, Some(Token::Byte(7)));
+        assert_eq!(err.path_string(), "Vec[3].1");
+
+        let emoji: Vec<char> = emojified.chars().collect();
+        let bytes_before: usize = emoji[..err.emoji_offset].iter().map(|c| c.len_utf8()).sum();
+        assert_eq!(err.byte_offset, bytes_before);
+
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "unexpected input in Vec[3].1 at emoji {} (byte {}): expected 0 or 1, found byte 7",
+                err.emoji_offset, err.byte_offset
+            )
+        );
+    }
+
+    #[test]
+    fn demojfuscate_error_path_from_derive() {
+        #[derive(Emojfuscate, ConstructFromEmoji, Debug, PartialEq)]
+        struct Person {
+            age: u8,
+            name: String,
+        }
+
+        #[derive(Emojfuscate, ConstructFromEmoji, Debug, PartialEq)]
+        enum Shape {
+            Circle { radius: u8, filled: bool },
+        }
+
+        let roundtrip_message: Result<Person, _> = (30u8, 5u8).emojfuscate().demojfuscate();
+        let err = roundtrip_message.unwrap_err();
+
+        assert_eq!(err.kind, FromEmojiErrorKind::MissingSequenceStart);
+        assert_eq!(err.found, Some(Token::Byte(5)));
+        assert_eq!(err.path_string(), "Person.name");
+
+        // tag, filled, radius
+        let roundtrip_message: Result<Shape, _> = (0u8, 2u8, 3u8).emojfuscate().demojfuscate();
+        let err = roundtrip_message.unwrap_err();
+
+        assert_eq!(err.path_string(), "Shape::Circle.filled");
+
+        let roundtrip_message: Result<Shape, _> = 1u8.emojfuscate().demojfuscate();
+        let err = roundtrip_message.unwrap_err();
+        assert_eq!(err.kind, FromEmojiErrorKind::UnknownVariant(1));
+        assert_eq!(err.path_string(), "Shape");
+    }
+
+    #[test]
+    fn demojfuscate_peek_and_depth() {
+        let emojified = vec!["a".to_string()].emojfuscate();
+        let mut byte_stream = (&emojified).demojfuscate_byte_stream();
+
+        assert_eq!(byte_stream.peek(), Some(&Ok(ByteInSequence::SequenceStart)));
+        assert_eq!(byte_stream.depth(), 0);
+
+        let mut tokens_and_depths = Vec::new();
+        while let Some(token) = byte_stream.next() {
+            tokens_and_depths.push((token, byte_stream.depth()));
+        }
+
+        assert_eq!(
+            tokens_and_depths,
+            vec![
+                (Ok(ByteInSequence::SequenceStart), 1),
+                (Ok(ByteInSequence::SequenceStart), 2),
+                (Ok(ByteInSequence::Byte(b'a')), 2),
+                (Ok(ByteInSequence::SequenceEnd), 1),
+                (Ok(ByteInSequence::SequenceEnd), 0),
+            ]
+        );
+        assert_eq!(byte_stream.peek(), None);
+    }
+
+    #[test]
+    fn demojfuscate_skip_value() {
+        let emojified = (vec!["skip".to_string(), "me".to_string()], 42u8).emojfuscate();
+        let mut byte_stream = (&emojified).demojfuscate_byte_stream();
+
+        assert_eq!(byte_stream.skip_value(), Ok(()));
+        assert_eq!(u8::construct_from_emoji(&mut byte_stream), Ok(42));
+
+        let err = byte_stream.skip_value().unwrap_err();
+        assert_eq!(err.kind, FromEmojiErrorKind::NotEnoughEmoji);
+    }
+
+    #[test]
+    fn demojfuscate_visitor() {
+        struct PrettyPrinter(String);
+
+        impl EmojiVisitor for PrettyPrinter {
+            fn visit_byte(&mut self, byte: u8) {
+                self.0.push_str(&format!(" {}", byte));
+            }
+
+            fn visit_sequence_start(&mut self) {
+                self.0.push_str(" [");
+            }
+
+            fn visit_sequence_end(&mut self) {
+                self.0.push_str(" ]");
+            }
+        }
+
+        let emojified = (7u8, vec!["ab".to_string(), "c".to_string()]).emojfuscate();
+        let mut printer = PrettyPrinter(String::new());
+
+        assert_eq!(
+            (&emojified).demojfuscate_byte_stream().visit(&mut printer),
+            Ok(())
+        );
+        assert_eq!(printer.0, " 7 [ [ 97 98 ] [ 99 ] ]");
+    }
+
+    #[test]
+    fn emoji_writer_and_reader_io_copy() {
+        let data: Vec<u8> = (0..=255u8).cycle().take(10_000).collect();
+
+        let mut emojified = Vec::new();
+        std::io::copy(&mut &data[..], &mut EmojiWriter::new(&mut emojified)).unwrap();
+
+        let expected: String = data
+            .clone()
+            .into_iter()
+            .emojfuscate_byte_stream_no_start_or_stop()
+            .collect();
+        assert_eq!(String::from_utf8(emojified.clone()), Ok(expected));
+
+        let mut roundtrip = Vec::new();
+        std::io::copy(&mut EmojiReader::new(&emojified[..]), &mut roundtrip).unwrap();
+        assert_eq!(roundtrip, data);
+    }
+
+    #[test]
+    fn emoji_writer_flush_in_the_middle() {
+        let mut emojified = Vec::new();
+        let mut writer = EmojiWriter::new(&mut emojified);
+
+        writer.write_all(b"abc").unwrap();
+        writer.flush().unwrap();
+        writer.write_all(b"de").unwrap();
+        drop(writer);
+
+        let mut roundtrip = String::new();
+        EmojiReader::new(&emojified[..])
+            .read_to_string(&mut roundtrip)
+            .unwrap();
+        assert_eq!(roundtrip, "abcde");
+    }
+
+    #[test]
+    fn emoji_reader_invalid_data() {
+        let mut roundtrip = Vec::new();
+        let err = EmojiReader::new("hello".as_bytes())
+            .read_to_end(&mut roundtrip)
+            .unwrap_err();
+
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        let inner = err.into_inner().unwrap();
+        let err = inner.downcast_ref::<FromEmojiError>().unwrap();
+        assert_eq!(err.kind, FromEmojiErrorKind::InputIsNotAnEmoji('h'));
+    }
+
+    #[test]
+    fn decode_str_into_matches_emoji_reader() {
+        let mut emojified = Vec::new();
+        let mut writer = EmojiWriter::new(&mut emojified);
+
+        writer.write_all(b"abc").unwrap();
+        writer.flush().unwrap();
+        writer.write_all(b"defghijklmnop").unwrap();
+        drop(writer);
+
+        let mut emojified = String::from_utf8(emojified).unwrap();
+        emojified.insert_str(12 * 4, "\n");
+        emojified.insert_str(5 * 4, " ");
+
+        let mut decoded = b"xyz".to_vec();
+        assert_eq!(decode_str_into(&emojified, &mut decoded), Ok(()));
+        assert_eq!(decoded, b"xyzabcdefghijklmnop");
+    }
+
+    #[test]
+    fn decode_str_into_errors() {
+        let invalid_inputs = ["hello".emojfuscate(), "hello".to_string()];
+
+        for input in invalid_inputs {
+            let mut read = Vec::new();
+            let err = EmojiReader::new(input.as_bytes())
+                .read_to_end(&mut read)
+                .unwrap_err()
+                .into_inner()
+                .unwrap();
+
+            let mut decoded = Vec::new();
+            assert_eq!(
+                decode_str_into(&input, &mut decoded).as_ref(),
+                Err(err.downcast_ref::<FromEmojiError>().unwrap())
+            );
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn emojfuscate_async_stream_matches_iterator() {
+        let values: Vec<String> = (0..50).map(|i| "hello ".repeat(i)).collect();
+        let error_correction = ErrorCorrection::new(16, 4).unwrap();
+
+        let expected: String = values
+            .clone()
+            .into_iter()
+            .emojfuscate_stream()
+            .with_header()
+            .with_checksum()
+            .with_error_correction(error_correction)
+            .collect();
+
+        let emojified: String = block_on(
+            stream::iter(values)
+                .emojfuscate_async_stream()
+                .with_header()
+                .with_checksum()
+                .with_error_correction(error_correction)
+                .collect(),
+        );
+
+        assert_eq!(emojified, expected);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn demojfuscate_async_stream_in_chunks() {
+        let values: Vec<(u32, String)> = (0..100).map(|i| (i, i.to_string())).collect();
+        let emojified: String = values
+            .clone()
+            .into_iter()
+            .emojfuscate_stream()
+            .with_header()
+            .with_checksum()
+            .with_compression()
+            .collect();
+
+        // the chunks end in the middle of emoji and values
+        let chunks: Vec<Vec<u8>> = emojified
+            .as_bytes()
+            .chunks(5)
+            .map(|chunk| chunk.to_vec())
+            .collect();
+
+        let roundtrip: Vec<Result<(u32, String), FromEmojiError>> =
+            block_on(stream::iter(chunks).demojfuscate_async_stream().collect());
+
+        assert_eq!(roundtrip, values.into_iter().map(Ok).collect::<Vec<_>>());
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn emojfuscate_async_stream_through_a_pipe() {
+        let values: Vec<u16> = (0..100).collect();
+        let (reader, mut writer) = sluice::pipe::pipe();
+
+        let write = async {
+            let mut emoji = stream::iter(values.clone())
+                .emojfuscate_async_stream()
+                .with_compression();
+            let mut buf = [0; 4];
+
+            while let Some(c) = emoji.next().await {
+                let encoded = c.encode_utf8(&mut buf);
+                writer.write_all(encoded.as_bytes()).await.unwrap();
+            }
+
+            writer.close().await.unwrap();
+        };
+
+        // the streams need to be Unpin, which the one from unfold isn't
+        let chunks = Box::pin(stream::unfold(reader, |mut reader| async move {
+            let mut chunk = vec![0; 7];
+            let read = reader.read(&mut chunk).await.unwrap();
+            chunk.truncate(read);
+            (read > 0).then_some((chunk, reader))
+        }));
+
+        let read = chunks
+            .demojfuscate_async_stream::<u16>()
+            .with_compression()
+            .collect::<Vec<_>>();
+
+        let ((), roundtrip) = block_on(futures::future::join(write, read));
+        assert_eq!(roundtrip, values.into_iter().map(Ok).collect::<Vec<_>>());
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn async_emoji_writer_and_reader_through_a_pipe() {
+        let data: Vec<u8> = (0..=255u8).cycle().take(10_000).collect();
+        let (reader, writer) = sluice::pipe::pipe();
+
+        let write = async {
+            let mut writer = AsyncEmojiWriter::new(writer);
+
+            for chunk in data.chunks(333) {
+                writer.write_all(chunk).await.unwrap();
+            }
+
+            writer.close().await.unwrap();
+        };
+
+        let read = async {
+            let mut roundtrip = Vec::new();
+            AsyncEmojiReader::new(reader)
+                .read_to_end(&mut roundtrip)
+                .await
+                .unwrap();
+            roundtrip
+        };
+
+        let ((), roundtrip) = block_on(futures::future::join(write, read));
+        assert_eq!(roundtrip, data);
+    }
+
+    #[cfg(feature = "serde")]
+    #[derive(Emojfuscate, ConstructFromEmoji, Serialize, Deserialize, Debug, PartialEq, Clone)]
+    struct Profile {
+        name: String,
+        age: u8,
+        tags: Vec<String>,
+        nickname: Option<String>,
+        score: (i32, f64),
+        initial: char,
+        active: bool,
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_struct_matches_derive() {
+        let profile = Profile {
+            name: "Ada".to_string(),
+            age: 36,
+            tags: vec!["math".to_string(), "engines".to_string()],
+            nickname: None,
+            score: (-7, 2.5),
+            initial: 'A',
+            active: true,
+        };
+
+        let emojified = to_emoji(&profile).unwrap();
+        assert_eq!(emojified, profile.clone().emojfuscate());
+        assert_eq!(from_emoji(emojified.bytes()), Ok(profile.clone()));
+
+        let without_id = (profile.name.clone(), profile.nickname.clone(), profile.tags);
+        assert_eq!(
+            to_emoji(&without_id).unwrap(),
+            without_id.clone().emojfuscate()
+        );
+        assert_eq!(
+            from_emoji(without_id.clone().emojfuscate().bytes()),
+            Ok(without_id)
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_enums_and_maps() {
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        enum Shape {
+            Empty,
+            Circle(f32),
+            Rectangle(u16, u16),
+            Polygon { sides: u8, name: String },
+        }
+
+        let shapes = vec![
+            Shape::Empty,
+            Shape::Circle(1.5),
+            Shape::Rectangle(3, 4),
+            Shape::Polygon {
+                sides: 6,
+                name: "hexagon".to_string(),
+            },
+        ];
+
+        // the variant tag is followed by only the fields of that variant
+        assert_eq!(
+            to_emoji(&shapes[2]).unwrap(),
+            (2u8, 3u16, 4u16).emojfuscate()
+        );
+        assert_eq!(
+            to_emoji(&shapes[3]).unwrap(),
+            (3u8, "hexagon".to_string(), 6u8).emojfuscate()
+        );
+        assert_eq!(from_emoji(to_emoji(&shapes).unwrap().bytes()), Ok(shapes));
+
+        let map: std::collections::BTreeMap<String, Vec<u32>> =
+            (0..10).map(|i| (i.to_string(), (0..i).collect())).collect();
+        let pairs: Vec<(String, Vec<u32>)> = map.clone().into_iter().collect();
+        assert_eq!(to_emoji(&map).unwrap(), pairs.emojfuscate());
+        assert_eq!(from_emoji(to_emoji(&map).unwrap().bytes()), Ok(map));
+
+        // tags that don't fit in 7 bits take more than one byte
+        struct Wide;
+
+        impl Serialize for Wide {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_newtype_variant("Wide", 300, "V300", &5u8)
+            }
+        }
+
+        assert_eq!(
+            to_emoji(&Wide).unwrap(),
+            (0xacu8, 0x02u8, 5u8).emojfuscate()
+        );
+
+        let err = from_emoji::<Shape, _, _>((0x80u8, 0x01u8).emojfuscate().bytes()).unwrap_err();
+        assert_eq!(err.kind, FromEmojiErrorKind::UnknownVariant(128));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_errors() {
+        // the fields after `active` and `age` are missing
+        let emojified = to_emoji(&(true, 36u8)).unwrap();
+        let err = from_emoji::<Profile, _, _>(emojified.bytes()).unwrap_err();
+        assert_eq!(err.kind, FromEmojiErrorKind::NotEnoughEmoji);
+        assert_eq!(err.path_string(), "Profile.initial[0]");
+
+        // errors from Deserialize implementations are placed where they happened
+        let emojified = to_emoji(&(1u8, vec![1u8, 0u8])).unwrap();
+        let err =
+            from_emoji::<(u8, Vec<std::num::NonZeroU8>), _, _>(emojified.bytes()).unwrap_err();
+        assert!(matches!(err.kind, FromEmojiErrorKind::Custom(_)));
+        assert_eq!(
+            err.path,
+            vec![PathSegment::Position(1), PathSegment::Index(1)]
+        );
+        assert!(err.emoji_offset > 0);
+
+        let err = from_emoji::<serde::de::IgnoredAny, _, _>(emojified.bytes()).unwrap_err();
+        assert!(matches!(err.kind, FromEmojiErrorKind::Custom(_)));
+    }
+
+    #[test]
+    fn emoji_value_roundtrip() {
+        let value = EmojiValue::Map(vec![
+            (
+                EmojiValue::String("name".to_string()),
+                EmojiValue::String("Ada".to_string()),
+            ),
+            (EmojiValue::UInt(1), EmojiValue::Int(-36)),
+            (
+                EmojiValue::Null,
+                EmojiValue::List(vec![
+                    EmojiValue::Bool(true),
+                    EmojiValue::Float(0.5),
+                    EmojiValue::Bytes(vec![1, 2, 3]),
+                    EmojiValue::List(vec![]),
+                ]),
+            ),
+        ]);
+
+        let emojified = (&value).emojfuscate();
+        assert_eq!((&emojified).demojfuscate(), Ok(value));
+
+        // the values say what they are, the tag of a string is 6
+        assert_eq!(
+            (6u8, "Ada".to_string()).emojfuscate(),
+            EmojiValue::String("Ada".to_string()).emojfuscate()
+        );
+
+        let err: Result<EmojiValue, _> = (10u8, 0u8).emojfuscate().demojfuscate();
+        assert_eq!(err.unwrap_err().kind, FromEmojiErrorKind::UnexpectedInput);
+    }
+
+    #[test]
+    fn derive_emoji_schema() {
+        #[derive(Emojfuscate, ConstructFromEmoji, EmojiSchema, Debug, PartialEq)]
+        enum Animal {
+            Cat { name: String, likes_cuddles: bool },
+            Dog(u32),
+            Lizard,
+        }
+
+        #[derive(Emojfuscate, ConstructFromEmoji, EmojiSchema, Debug, PartialEq)]
+        struct Pet<A> {
+            owner: Option<String>,
+            animal: A,
+            ages: (u8, u8),
+        }
+
+        assert_eq!(
+            Animal::emoji_schema(),
+            Schema::Enum {
+                name: "Animal",
+                variants: vec![
+                    Variant {
+                        name: "Cat",
+                        tag: 0,
+                        fields: Fields::Named(vec![
+                            Field {
+                                name: "likes_cuddles",
+                                schema: Schema::Bool
+                            },
+                            Field {
+                                name: "name",
+                                schema: Schema::String
+                            },
+                        ]),
+                    },
+                    Variant {
+                        name: "Dog",
+                        tag: 1,
+                        fields: Fields::Unnamed(vec![Schema::U32]),
+                    },
+                    Variant {
+                        name: "Lizard",
+                        tag: 2,
+                        fields: Fields::Unit,
+                    },
+                ],
+            }
+        );
+        assert_eq!(
+            Pet::<Animal>::emoji_schema().to_string(),
+            "Pet { ages: (u8, u8), animal: Animal { Cat { likes_cuddles: bool, name: String } = 0, \
+             Dog(u32) = 1, Lizard = 2 }, owner: Option<String> }"
+        );
+        assert_eq!(
+            <Result<Vec<[i8; 2]>, ()>>::emoji_schema().to_string(),
+            "Result { Ok(Vec<[i8; 2]>) = 0, Err(()) = 1 }"
+        );
+
+        // the schema is enough to look at the emoji
+        let pet = Pet {
+            owner: None,
+            animal: Animal::Cat {
+                name: "Tom".to_string(),
+                likes_cuddles: true,
+            },
+            ages: (3, 4),
+        };
+        let emojified = pet.emojfuscate();
+        let string = |s: &str| EmojiValue::String(s.to_string());
+
+        assert_eq!(
+            Pet::<Animal>::emoji_schema().decode(emojified.bytes()),
+            Ok(EmojiValue::Map(vec![
+                (
+                    string("ages"),
+                    EmojiValue::List(vec![EmojiValue::UInt(3), EmojiValue::UInt(4)])
+                ),
+                (
+                    string("animal"),
+                    EmojiValue::Map(vec![(
+                        string("Cat"),
+                        EmojiValue::Map(vec![
+                            (string("likes_cuddles"), EmojiValue::Bool(true)),
+                            (string("name"), string("Tom")),
+                        ])
+                    )])
+                ),
+                (string("owner"), EmojiValue::Null),
+            ]))
+        );
+
+        let lizard = Animal::Lizard.emojfuscate();
+        assert_eq!(
+            Animal::emoji_schema().decode(lizard.bytes()),
+            Ok(string("Lizard"))
+        );
+
+        // Animal only has three variants
+        let unknown = 3u8.emojfuscate();
+        let err = Animal::emoji_schema().decode(unknown.bytes()).unwrap_err();
+        assert_eq!(err.kind, FromEmojiErrorKind::UnknownVariant(3));
+    }
+
+    #[test]
+    fn evolvable_struct_fields() {
+        #[derive(Emojfuscate, ConstructFromEmoji, EmojiSchema, Debug, PartialEq)]
+        #[emojfuscate(evolvable)]
+        struct SettingsV1 {
+            volume: u8,
+            nickname: String,
+            muted: bool,
+        }
+
+        // `muted` was removed and `theme` and `favorites` were added
+        #[derive(Emojfuscate, ConstructFromEmoji, EmojiSchema, Clone, Debug, PartialEq)]
+        #[emojfuscate(evolvable)]
+        struct SettingsV2 {
+            nickname: String,
+            volume: u8,
+            #[emojfuscate(default)]
+            theme: Option<String>,
+            #[emojfuscate(default)]
+            favorites: Vec<u32>,
+        }
+
+        let old = SettingsV1 {
+            volume: 11,
+            nickname: "Ada".to_string(),
+            muted: true,
+        }
+        .emojfuscate();
+
+        assert_eq!(
+            (&old).demojfuscate(),
+            Ok(SettingsV2 {
+                nickname: "Ada".to_string(),
+                volume: 11,
+                theme: None,
+                favorites: vec![],
+            })
+        );
+
+        let new = SettingsV2 {
+            nickname: "Ada".to_string(),
+            volume: 3,
+            theme: Some("dark".to_string()),
+            favorites: vec![1, 2, 3],
+        };
+        let emojified = new.clone().emojfuscate();
+        assert_eq!(
+            SettingsV2::emoji_schema().decode(emojified.bytes()),
+            Ok(EmojiValue::Map(vec![
+                (
+                    EmojiValue::String("favorites".to_string()),
+                    EmojiValue::List(vec![
+                        EmojiValue::UInt(1),
+                        EmojiValue::UInt(2),
+                        EmojiValue::UInt(3)
+                    ])
+                ),
+                (
+                    EmojiValue::String("nickname".to_string()),
+                    EmojiValue::String("Ada".to_string())
+                ),
+                (
+                    EmojiValue::String("theme".to_string()),
+                    EmojiValue::String("dark".to_string())
+                ),
+                (
+                    EmojiValue::String("volume".to_string()),
+                    EmojiValue::UInt(3)
+                ),
+            ]))
+        );
+        assert_eq!((&emojified).demojfuscate(), Ok(new));
+
+        // `muted` has no default, so the old version can't read the new emoji
+        let demojfuscated: Result<SettingsV1, _> = emojified.demojfuscate();
+        let err = demojfuscated.unwrap_err();
+        assert_eq!(err.kind, FromEmojiErrorKind::MissingField("muted"));
+        assert_eq!(err.path_string(), "SettingsV1");
+
+        assert_eq!(
+            SettingsV2::emoji_schema().to_string(),
+            "SettingsV2 { favorites: Vec<u32>, nickname: String, theme: Option<String>, \
+             volume: u8, .. }"
+        );
+    }
+
+    // Duration doesn't implement Emojfuscate, so it's encoded as its number of seconds
+    mod seconds {
+        use emojfuscate::{
+            ByteInSequence, ConstructFromEmoji, DecodeEmojiToBytes, Emojfuscate,
+            EncodeBytesAsEmoji, FromEmojiError, Schema,
+        };
+        use std::time::Duration;
+
+        pub fn emojfuscate_stream(
+            duration: Duration,
+        ) -> EncodeBytesAsEmoji<impl Iterator<Item = ByteInSequence>> {
+            duration.as_secs().emojfuscate_stream()
+        }
+
+        pub fn construct_from_emoji<I: Iterator<Item = u8>>(
+            byte_stream: &mut DecodeEmojiToBytes<I>,
+        ) -> Result<Duration, FromEmojiError> {
+            u64::construct_from_emoji(byte_stream).map(Duration::from_secs)
+        }
+
+        pub fn emoji_schema() -> Schema {
+            Schema::U64
+        }
+    }
+
+    #[test]
+    fn derive_field_attributes() {
+        use std::time::Duration;
+
+        fn unknown_host() -> String {
+            "unknown".to_string()
+        }
+
+        #[derive(Emojfuscate, ConstructFromEmoji, EmojiSchema, Debug, PartialEq)]
+        struct Session {
+            #[emojfuscate(rename = "name")]
+            user_name: String,
+            #[emojfuscate(with = seconds)]
+            timeout: Duration,
+            #[emojfuscate(skip)]
+            cache: Vec<u8>,
+            #[emojfuscate(skip, default = unknown_host)]
+            host: String,
+        }
+
+        let emojified = Session {
+            user_name: "Ada".to_string(),
+            timeout: Duration::from_secs(90),
+            cache: vec![1, 2, 3],
+            host: "example.com".to_string(),
+        }
+        .emojfuscate();
+
+        // only the fields that are left, in the order of their names in the emoji
+        assert_eq!(emojified, ("Ada", 90u64).emojfuscate());
+        assert_eq!(
+            emojified.demojfuscate(),
+            Ok(Session {
+                user_name: "Ada".to_string(),
+                timeout: Duration::from_secs(90),
+                cache: vec![],
+                host: "unknown".to_string(),
+            })
+        );
+        assert_eq!(
+            Session::emoji_schema().to_string(),
+            "Session { name: String, timeout: u64 }"
+        );
+
+        #[derive(Emojfuscate, ConstructFromEmoji, Debug, PartialEq)]
+        struct Handle(u16, #[emojfuscate(skip)] Vec<u8>, bool);
+
+        let emojified = Handle(7, vec![1, 2, 3], true).emojfuscate();
+        assert_eq!(emojified, (7u16, true).emojfuscate());
+        assert_eq!(emojified.demojfuscate(), Ok(Handle(7, vec![], true)));
+
+        #[derive(Emojfuscate, ConstructFromEmoji, Debug, PartialEq)]
+        enum Event {
+            Login {
+                #[emojfuscate(rename = "a_user")]
+                user: String,
+                #[emojfuscate(skip)]
+                attempts: u32,
+                at: u64,
+            },
+            Logout(u32, #[emojfuscate(skip)] bool),
+        }
+
+        let emojified = Event::Login {
+            user: "Ada".to_string(),
+            attempts: 3,
+            at: 1234,
+        }
+        .emojfuscate();
+        assert_eq!(emojified, (0u8, "Ada", 1234u64).emojfuscate());
+        assert_eq!(
+            emojified.demojfuscate(),
+            Ok(Event::Login {
+                user: "Ada".to_string(),
+                attempts: 0,
+                at: 1234,
+            })
+        );
+        assert_eq!(
+            Event::Logout(5, true).emojfuscate().demojfuscate(),
+            Ok(Event::Logout(5, false))
+        );
+
+        // the attributes work for evolvable structs too, e.g. to rename a field in Rust only
+        #[derive(Emojfuscate)]
+        #[emojfuscate(evolvable)]
+        struct ProfileV1 {
+            name: String,
+            age: u8,
+        }
+
+        #[derive(ConstructFromEmoji, Debug, PartialEq)]
+        #[emojfuscate(evolvable)]
+        struct ProfileV2 {
+            #[emojfuscate(rename = "name")]
+            display_name: String,
+            #[emojfuscate(with = seconds, default)]
+            timeout: Duration,
+            #[emojfuscate(skip)]
+            cache: Vec<u8>,
+        }
+
+        let emojified = ProfileV1 {
+            name: "Ada".to_string(),
+            age: 36,
+        }
+        .emojfuscate();
+        assert_eq!(
+            emojified.demojfuscate(),
+            Ok(ProfileV2 {
+                display_name: "Ada".to_string(),
+                timeout: Duration::ZERO,
+                cache: vec![],
+            })
+        );
+    }
+
+    #[test]
+    fn derive_compact_enums() {
+        use std::time::Duration;
+
+        #[derive(Emojfuscate, ConstructFromEmoji, EmojiSchema, Debug, PartialEq)]
+        enum Command {
+            Quit,
+            Sleep(#[emojfuscate(with = seconds)] Duration),
+            Move {
+                x: i16,
+                y: i16,
+            },
+            #[rustfmt::skip]
+            Wide(
+                u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8,
+                u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8,
+            ),
+        }
+
+        // the tag is followed by only the fields of the chosen variant
+        assert_eq!(Command::Quit.emojfuscate(), 0u8.emojfuscate());
+        assert_eq!(
+            Command::Sleep(Duration::from_secs(90)).emojfuscate(),
+            (1u8, 90u64).emojfuscate()
+        );
+        assert_eq!(
+            Command::Move { x: -1, y: 2 }.emojfuscate(),
+            (2u8, -1i16, 2i16).emojfuscate()
+        );
+
+        // there is no limit on the number of fields
+        let wide = Command::Wide(
+            0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23,
+            24, 25, 26, 27, 28, 29,
+        );
+        let emojified = wide.emojfuscate();
+        let expected: Vec<u8> = (3..=3).chain(0..30).collect();
+        assert_eq!(
+            emojified,
+            expected
+                .into_iter()
+                .emojfuscate_byte_stream_no_start_or_stop()
+                .collect::<String>()
+        );
+        assert_eq!(
+            emojified.demojfuscate(),
+            Ok(Command::Wide(
+                0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22,
+                23, 24, 25, 26, 27, 28, 29,
+            ))
+        );
+
+        let emojified = Command::Sleep(Duration::from_secs(5)).emojfuscate();
+        assert_eq!(
+            Command::emoji_schema().decode(emojified.bytes()),
+            Ok(EmojiValue::Map(vec![(
+                EmojiValue::String("Sleep".to_string()),
+                EmojiValue::UInt(5)
+            )]))
+        );
+
+        // Result is laid out the same way
+        assert_eq!(
+            Err::<u8, String>("no".to_string()).emojfuscate(),
+            (1u8, "no".to_string()).emojfuscate()
+        );
+
+        // the tags of the first 128 variants are a single byte, later ones take more
+        assert_eq!(
+            EncodeBytesAsEmoji::variant_tag(300).collect::<String>(),
+            (0xacu8, 0x02u8).emojfuscate()
+        );
+        let mut byte_stream = (0xacu8, 0x02u8).emojfuscate().demojfuscate_byte_stream();
+        assert_eq!(byte_stream.read_variant_tag(), Ok(300));
+    }
+
+    #[test]
+    fn derive_explicit_variant_tags() {
+        // C-like enums are just their tag, which is their discriminant
+        #[derive(Emojfuscate, ConstructFromEmoji, EmojiSchema, Debug, PartialEq)]
+        enum Status {
+            Active = 1,
+            Paused = 5,
+            Archived,
+        }
+
+        assert_eq!(Status::Active.emojfuscate(), 1u8.emojfuscate());
+        assert_eq!(Status::Archived.emojfuscate(), 6u8.emojfuscate());
+        assert_eq!(
+            Status::Paused.emojfuscate().demojfuscate(),
+            Ok(Status::Paused)
+        );
+        assert_eq!(
+            Status::emoji_schema().to_string(),
+            "Status { Active = 1, Paused = 5, Archived = 6 }"
+        );
+
+        #[derive(Emojfuscate, ConstructFromEmoji, Debug, PartialEq)]
+        #[repr(u16)]
+        enum MessageV1 {
+            Ping = 1,
+            Text(String) = 2,
+            #[emojfuscate(tag = 300)]
+            Bye {
+                code: u8,
+            },
+        }
+
+        // the variants can be reordered and new ones can be inserted as long as the tags stay
+        #[derive(Emojfuscate, ConstructFromEmoji, Debug, PartialEq)]
+        #[repr(u16)]
+        enum MessageV2 {
+            #[emojfuscate(tag = 300)]
+            Bye {
+                code: u8,
+            },
+            Image(Vec<u8>) = 3,
+            Text(String) = 2,
+            Ping = 1,
+        }
 
-        let emojified = UnitStruct.emojfuscate();
-        let roundtrip_message = (&emojified).demojfuscate();
         assert_eq!(
-            roundtrip_message,
-            Ok(UnitStruct),
-            "emojfuscated version: {}",
+            MessageV1::Bye { code: 7 }.emojfuscate(),
+            (0xacu8, 0x02u8, 7u8).emojfuscate()
+        );
+        assert_eq!(
+            MessageV1::Text("hi".to_string())
+                .emojfuscate()
+                .demojfuscate(),
+            Ok(MessageV2::Text("hi".to_string()))
+        );
+        assert_eq!(
+            MessageV1::Bye { code: 7 }.emojfuscate().demojfuscate(),
+            Ok(MessageV2::Bye { code: 7 })
+        );
+
+        let demojfuscated: Result<MessageV1, _> =
+            MessageV2::Image(vec![1]).emojfuscate().demojfuscate();
+        let err = demojfuscated.unwrap_err();
+        assert_eq!(err.kind, FromEmojiErrorKind::UnknownVariant(3));
+    }
+
+    #[test]
+    fn derive_borrowed_fields() {
+        use std::borrow::Cow;
+
+        #[derive(Emojfuscate, EmojiSchema)]
+        struct Event<'a> {
+            name: &'a str,
+            tags: &'a [u8],
+        }
+
+        #[derive(Emojfuscate, ConstructFromEmoji, EmojiSchema, Debug, PartialEq)]
+        struct OwnedEvent<'a> {
+            name: Cow<'a, str>,
+            tags: Cow<'a, [u8]>,
+        }
+
+        let name = "deploy".to_string();
+        let tags = vec![1, 2, 3];
+        let emojified = Event {
+            name: &name,
+            tags: &tags,
+        }
+        .emojfuscate();
+
+        // borrowed data is laid out like owned data and can be decoded into it
+        assert_eq!(emojified, (name.clone(), tags.clone()).emojfuscate());
+        assert_eq!(
+            Event::emoji_schema().to_string(),
+            "Event { name: String, tags: Vec<u8> }"
+        );
+        assert_eq!(
+            (&emojified).demojfuscate(),
+            Ok(OwnedEvent {
+                name: Cow::Owned(name.clone()),
+                tags: Cow::Owned(tags.clone()),
+            })
+        );
+        assert_eq!(
+            OwnedEvent {
+                name: Cow::Borrowed(&name),
+                tags: Cow::Borrowed(&tags),
+            }
+            .emojfuscate(),
             emojified
         );
+
+        #[derive(Emojfuscate, ConstructFromEmoji, Debug, PartialEq)]
+        enum Token<'a> {
+            Word(Cow<'a, str>),
+            Number(u32),
+        }
+
+        let emojified = Token::Word(Cow::Borrowed("hi")).emojfuscate();
+        assert_eq!(emojified, (0u8, "hi").emojfuscate());
+        assert_eq!(
+            emojified.demojfuscate(),
+            Ok(Token::Word(Cow::Owned("hi".to_string())))
+        );
+
+        // const generics are carried through too
+        #[derive(
+            Emojfuscate, ConstructFromEmoji, EmojfuscateFixedSize, EmojiSchema, Debug, PartialEq,
+        )]
+        struct Block<const N: usize> {
+            data: [u8; N],
+        }
+
+        let emojified = Block { data: [1, 2, 3] }.emojfuscate();
+        assert_eq!(emojified, [1u8, 2, 3].emojfuscate());
+        assert_eq!(emojified.demojfuscate(), Ok(Block { data: [1, 2, 3] }));
+        assert_eq!(Block::<3>::ENCODED_BYTES, 3);
+        assert_eq!(
+            Block::<3>::emoji_schema().to_string(),
+            "Block { data: [u8; 3] }"
+        );
     }
 
     #[test]
-    fn emojfuscate_infinite_streams() {
-        let mut source = std::iter::repeat("hello"); // infinite stream of String : Iterator<Item = String>
+    fn derive_generic_signatures() {
+        use std::marker::PhantomData;
 
-        let demojfuscated: Result<Vec<String>, emojfuscate::FromEmojiError> = (&mut source)
-            .emojfuscate_stream() // infinite stream of emoji: Iterator<Item = char>
-            .demojfuscate_stream() // infinite stream of hopefully String: Iterator<Item = Result<String, FromEmojiError>>
-            .take(3) // finite stream of hopefully String: Iterator<Item = Result<String, FromEmojiError>>
-            .collect(); // Result<Vec<String>, FromEmojiError>
+        // the bounds and where clauses of the type are kept
+        #[derive(
+            Emojfuscate, ConstructFromEmoji, EmojfuscateFixedSize, EmojiSchema, Debug, PartialEq,
+        )]
+        struct Frame<T: Copy, const N: usize = 4>
+        where
+            T: Default,
+        {
+            data: [T; N],
+            sequence: u16,
+        }
 
+        let frame = Frame {
+            data: [1u8, 2, 3, 4],
+            sequence: 9,
+        };
+        let emojified = frame.emojfuscate();
+        assert_eq!(emojified, ([1u8, 2, 3, 4], 9u16).emojfuscate());
         assert_eq!(
-            demojfuscated,
-            Ok(vec![
-                "hello".to_string(),
-                "hello".to_string(),
-                "hello".to_string()
-            ])
+            emojified.demojfuscate(),
+            Ok(Frame {
+                data: [1u8, 2, 3, 4],
+                sequence: 9,
+            })
+        );
+        assert_eq!(<Frame<u8>>::ENCODED_BYTES, 6);
+
+        // the derive would want T to implement ConstructFromEmoji and EmojiSchema, but it's only
+        // a marker
+        #[derive(Emojfuscate, ConstructFromEmoji, EmojiSchema, Debug, PartialEq)]
+        #[emojfuscate(bound = "")]
+        struct Id<T> {
+            id: u32,
+            #[emojfuscate(skip)]
+            kind: PhantomData<T>,
+        }
+
+        #[derive(Debug, PartialEq)]
+        struct User;
+
+        let id: Id<User> = Id {
+            id: 7,
+            kind: PhantomData,
+        };
+        let emojified = id.emojfuscate();
+        assert_eq!(emojified, 7u32.emojfuscate());
+        assert_eq!(
+            emojified.demojfuscate(),
+            Ok(Id::<User> {
+                id: 7,
+                kind: PhantomData,
+            })
+        );
+        assert_eq!(<Id<User>>::emoji_schema().to_string(), "Id { id: u32 }");
+
+        // the bounds can also be spelled out
+        #[derive(Emojfuscate, ConstructFromEmoji, Debug, PartialEq)]
+        #[emojfuscate(bound(construct_from_emoji = "T: Ord + ConstructFromEmoji<T, I>"))]
+        struct Sorted<T: Ord> {
+            values: Vec<T>,
+        }
+
+        let sorted = Sorted {
+            values: vec![1i16, 5, 9],
+        };
+        assert_eq!(
+            sorted.emojfuscate().demojfuscate(),
+            Ok(Sorted {
+                values: vec![1i16, 5, 9]
+            })
+        );
+    }
+
+    #[test]
+    fn derive_recursive_types() {
+        #[derive(Emojfuscate, ConstructFromEmoji, EmojiSchema, Clone, Debug, PartialEq)]
+        enum Expr {
+            Lit(i64),
+            Neg(Box<Expr>),
+            Add(Box<Expr>, Box<Self>),
+        }
+
+        let expr = Expr::Add(
+            Box::new(Expr::Lit(1)),
+            Box::new(Expr::Neg(Box::new(Expr::Lit(2)))),
+        );
+        let emojified = expr.clone().emojfuscate();
+        assert_eq!(
+            emojified,
+            EncodeBytesAsEmoji::variant_tag(2)
+                .chain_emoji_bytes(EncodeBytesAsEmoji::variant_tag(0))
+                .chain_emoji_bytes(1i64.emojfuscate_stream())
+                .chain_emoji_bytes(EncodeBytesAsEmoji::variant_tag(1))
+                .chain_emoji_bytes(EncodeBytesAsEmoji::variant_tag(0))
+                .chain_emoji_bytes(2i64.emojfuscate_stream())
+                .collect::<String>()
+        );
+        assert_eq!((&emojified).demojfuscate(), Ok(expr));
+
+        // the fields refer back to the type instead of going on forever
+        let schema = Expr::emoji_schema();
+        assert_eq!(
+            schema.to_string(),
+            "Expr { Lit(i64) = 0, Neg(Expr) = 1, Add(Expr, Expr) = 2 }"
+        );
+        assert_eq!(
+            schema.decode(&emojified),
+            Ok(EmojiValue::Map(vec![(
+                EmojiValue::String("Add".to_string()),
+                EmojiValue::List(vec![
+                    EmojiValue::Map(vec![(
+                        EmojiValue::String("Lit".to_string()),
+                        EmojiValue::Int(1)
+                    )]),
+                    EmojiValue::Map(vec![(
+                        EmojiValue::String("Neg".to_string()),
+                        EmojiValue::Map(vec![(
+                            EmojiValue::String("Lit".to_string()),
+                            EmojiValue::Int(2)
+                        )])
+                    )]),
+                ])
+            )]))
+        );
+
+        #[derive(Emojfuscate, ConstructFromEmoji, EmojiSchema, Clone, Debug, PartialEq)]
+        struct Tree<T> {
+            value: T,
+            children: Vec<Tree<T>>,
+        }
+
+        let tree = Tree {
+            value: "root".to_string(),
+            children: vec![
+                Tree {
+                    value: "left".to_string(),
+                    children: vec![],
+                },
+                Tree {
+                    value: "right".to_string(),
+                    children: vec![Tree {
+                        value: "leaf".to_string(),
+                        children: vec![],
+                    }],
+                },
+            ],
+        };
+        let emojified = tree.clone().emojfuscate();
+        assert_eq!(emojified.demojfuscate(), Ok(tree));
+        assert_eq!(
+            <Tree<String>>::emoji_schema().to_string(),
+            "Tree { children: Vec<Tree>, value: String }"
+        );
+    }
+
+    #[test]
+    fn derive_type_with_the_same_name_from_another_module() {
+        mod other {
+            use emojfuscate::{ConstructFromEmoji, Emojfuscate, EmojiSchema};
+
+            #[derive(Emojfuscate, ConstructFromEmoji, EmojiSchema, Clone, Debug, PartialEq)]
+            pub struct Expr(pub u8);
+        }
+
+        // `other::Expr` isn't `Expr`, so this isn't a recursive type
+        #[derive(Emojfuscate, ConstructFromEmoji, EmojiSchema, Clone, Debug, PartialEq)]
+        struct Expr {
+            inner: Box<other::Expr>,
+        }
+
+        let expr = Expr {
+            inner: Box::new(other::Expr(7)),
+        };
+        let emojified = expr.clone().emojfuscate();
+        assert_eq!(emojified, 7u8.emojfuscate());
+        assert_eq!((&emojified).demojfuscate(), Ok(expr));
+        assert_eq!(Expr::emoji_schema().to_string(), "Expr { inner: Expr(u8) }");
+    }
+
+    #[test]
+    fn derive_recursive_nesting_limit() {
+        #[derive(Emojfuscate, ConstructFromEmoji, EmojiSchema, Debug, PartialEq)]
+        enum List {
+            Nil,
+            Cons(u16, Box<List>),
+        }
+
+        // values up to the limit are fine, 128 by default
+        let list = (0..500)
+            .rev()
+            .fold(List::Nil, |list, n| List::Cons(n, Box::new(list)));
+        let emojified = list.emojfuscate();
+
+        let demojfuscated: Result<List, _> = (&emojified)
+            .demojfuscate_byte_stream()
+            .with_nesting_limit(500)
+            .demojfuscate();
+        let mut values = Vec::new();
+        let mut rest = &demojfuscated.unwrap();
+
+        while let List::Cons(n, tail) = rest {
+            values.push(*n);
+            rest = tail;
+        }
+
+        assert_eq!(values, (0..500).collect::<Vec<_>>());
+
+        let demojfuscated: Result<List, _> = (&emojified).demojfuscate();
+        assert_eq!(
+            demojfuscated.map_err(|err| err.kind),
+            Err(FromEmojiErrorKind::TooDeeplyNested)
+        );
+
+        // emoji made to be nested until the stack overflows are an error instead
+        let mut bytes = [1u8, 0, 0].repeat(100_000);
+        bytes.push(0);
+        let emojified: String = bytes
+            .into_iter()
+            .emojfuscate_byte_stream_no_start_or_stop()
+            .collect();
+
+        let demojfuscated: Result<List, _> = (&emojified).demojfuscate();
+        assert_eq!(
+            demojfuscated.map_err(|err| err.kind),
+            Err(FromEmojiErrorKind::TooDeeplyNested)
+        );
+        assert_eq!(
+            List::emoji_schema()
+                .decode(&emojified)
+                .map_err(|err| err.kind),
+            Err(FromEmojiErrorKind::TooDeeplyNested)
+        );
+
+        // and so are lists in lists in the self-describing format
+        let list_start = [ByteInSequence::Byte(8), ByteInSequence::SequenceStart];
+        let emojified: String =
+            EncodeBytesAsEmoji::new(list_start.into_iter().cycle().take(200_000)).collect();
+
+        let demojfuscated: Result<EmojiValue, _> = (&emojified).demojfuscate();
+        assert_eq!(
+            demojfuscated.map_err(|err| err.kind),
+            Err(FromEmojiErrorKind::TooDeeplyNested)
+        );
+    }
+
+    #[test]
+    fn derive_recursive_default_nesting_limit() {
+        #[derive(Emojfuscate, ConstructFromEmoji, EmojiSchema, Clone, Debug, PartialEq)]
+        enum List {
+            Nil,
+            Cons(u16, Box<List>),
+        }
+
+        let at_limit = (0..DEFAULT_NESTING_LIMIT as u16)
+            .fold(List::Nil, |list, n| List::Cons(n, Box::new(list)));
+        let emojified = at_limit.clone().emojfuscate();
+
+        assert_eq!((&emojified).demojfuscate(), Ok(at_limit));
+        assert!(List::emoji_schema().decode(&emojified).is_ok());
+
+        // one more level encodes fine but needs a higher limit to be decoded
+        let over_limit = (0..DEFAULT_NESTING_LIMIT as u16 + 1)
+            .fold(List::Nil, |list, n| List::Cons(n, Box::new(list)));
+        let emojified = over_limit.clone().emojfuscate();
+
+        let demojfuscated: Result<List, _> = (&emojified).demojfuscate();
+        assert_eq!(
+            demojfuscated.map_err(|err| err.kind),
+            Err(FromEmojiErrorKind::TooDeeplyNested)
+        );
+        assert_eq!(
+            List::emoji_schema()
+                .decode(&emojified)
+                .map_err(|err| err.kind),
+            Err(FromEmojiErrorKind::TooDeeplyNested)
+        );
+        assert_eq!(
+            (&emojified)
+                .demojfuscate_byte_stream()
+                .with_nesting_limit(DEFAULT_NESTING_LIMIT + 1)
+                .demojfuscate(),
+            Ok(over_limit)
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_tagged_emoji() {
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        enum Shape {
+            Empty,
+            Circle(f32),
+            Polygon { sides: u8, name: String },
+        }
+
+        let profile = Profile {
+            name: "Ada".to_string(),
+            age: 36,
+            tags: vec!["math".to_string()],
+            nickname: Some("Countess".to_string()),
+            score: (-7, 2.5),
+            initial: 'A',
+            active: false,
+        };
+
+        let emojified = to_tagged_emoji(&profile).unwrap();
+        let value: EmojiValue = (&emojified).demojfuscate().unwrap();
+        assert_eq!(Ok(value), to_emoji_value(&profile));
+        assert_eq!(from_tagged_emoji(emojified.bytes()), Ok(profile));
+
+        let shapes = vec![
+            Shape::Empty,
+            Shape::Circle(1.5),
+            Shape::Polygon {
+                sides: 6,
+                name: "hexagon".to_string(),
+            },
+        ];
+
+        assert_eq!(
+            to_emoji_value(&shapes[1]),
+            Ok(EmojiValue::Map(vec![(
+                EmojiValue::String("Circle".to_string()),
+                EmojiValue::Float(1.5)
+            )]))
+        );
+        assert_eq!(
+            from_tagged_emoji(to_tagged_emoji(&shapes).unwrap().bytes()),
+            Ok(shapes)
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn emoji_value_to_and_from_json() {
+        let json = serde_json::json!({
+            "name": "Ada",
+            "age": 36,
+            "balance": -12,
+            "score": 2.5,
+            "tags": ["math", null, true],
+        });
+
+        let value = EmojiValue::from(json.clone());
+        assert_eq!(serde_json::Value::from(value.clone()), json);
+
+        let emojified = value.emojfuscate();
+        let roundtrip: EmojiValue = (&emojified).demojfuscate().unwrap();
+        assert_eq!(serde_json::Value::from(roundtrip), json);
+
+        let value = EmojiValue::Map(vec![
+            (EmojiValue::UInt(1), EmojiValue::Bytes(vec![1, 2])),
+            (EmojiValue::Null, EmojiValue::Float(f64::NAN)),
+        ]);
+        assert_eq!(
+            serde_json::Value::from(value),
+            serde_json::json!({ "1": [1, 2], "null": null })
         );
     }
 
@@ -254,6 +2320,50 @@ mod tests {
             assert_eq!(roundtrip_message, Ok(original_message), "emojfuscated version: {}", emojified);
         }
 
+        #[test]
+        fn emojfuscate_u8_vec_with_error_correction(original_message : Vec<u8>, corrupted_index : usize) {
+            let error_correction = ErrorCorrection::default();
+            let mut emojified: Vec<char> = original_message
+                .clone()
+                .emojfuscate_stream()
+                .with_error_correction(error_correction)
+                .collect();
+
+            let corrupted_index = corrupted_index % emojified.len();
+            emojified[corrupted_index] = if emojified[corrupted_index] == '😀' { '😃' } else { '😀' };
+            let emojified: String = emojified.into_iter().collect();
+
+            let roundtrip_message = (&emojified)
+                .demojfuscate_byte_stream()
+                .with_error_correction(error_correction)
+                .demojfuscate();
+            assert_eq!(roundtrip_message, Ok(original_message), "emojfuscated version: {}", emojified);
+        }
+
+        #[test]
+        fn emojfuscate_u8_vec_with_compression(original_message : Vec<u8>) {
+            let emojified: String = original_message
+                .clone()
+                .emojfuscate_stream()
+                .with_compression()
+                .with_header()
+                .collect();
+            let roundtrip_message = (&emojified).demojfuscate();
+            assert_eq!(roundtrip_message, Ok(original_message), "emojfuscated version: {}", emojified);
+        }
+
+        #[test]
+        fn emojfuscate_string_vec_with_compression(strings in proptest::collection::vec("(ab|\\PC){0,20}", 0..20)) {
+            let emojified: String = strings
+                .clone()
+                .emojfuscate_stream()
+                .with_compression()
+                .with_header()
+                .collect();
+            let roundtrip_message = (&emojified).demojfuscate();
+            assert_eq!(roundtrip_message, Ok(strings), "emojfuscated version: {}", emojified);
+        }
+
         #[test]
         fn emojfuscate_u8_stream(original_message : Vec<u8>) {
             let emojified = original_message.clone().emojfuscate();
@@ -329,6 +2439,22 @@ mod tests {
             assert_eq!(roundtrip_message, Ok(original_message), "emojfuscated version: {}", emojified);
         }
 
+        #[test]
+        fn emojfuscate_derive_evolvable_struct(age : u8, name : String, luggage : Vec<i16>) {
+            #[derive(ConstructFromEmoji, Emojfuscate, Debug, PartialEq, Clone)]
+            #[emojfuscate(evolvable)]
+            struct Person<A> {
+                age: u8,
+                name: String,
+                luggage: A
+            }
+
+            let original_message = Person { age, name, luggage };
+            let emojified = original_message.clone().emojfuscate();
+            let roundtrip_message = (&emojified).demojfuscate();
+            assert_eq!(roundtrip_message, Ok(original_message), "emojfuscated version: {}", emojified);
+        }
+
         #[test]
         fn emojfuscate_derive_construct_from_emoji_tuple_struct(age : u8, name : String) {
             #[derive(Emojfuscate, ConstructFromEmoji, Debug, PartialEq, Clone)]
@@ -363,78 +2489,6 @@ mod tests {
                 Donkey((), i16)
             }
 
-            /*
-            impl<IA, IB> Emojfuscate<Chain<Chain<Once<ByteOrBreak>, IA>, IB>> for Animal
-            where
-                Option<(bool, String)>: Emojfuscate<IA>,
-                Option<u32>: Emojfuscate<IB>,
-                IA: Iterator<Item = ByteOrBreak>,
-                IB: Iterator<Item = ByteOrBreak>,
-            {
-                fn emojfuscate_stream(self) -> EncodeBytesAsEmoji<Chain<Chain<Once<ByteOrBreak>, IA>, IB>> {
-                    match self {
-                        Animal::Cat(b,s) => {
-                            0u8
-                                .emojfuscate_stream()
-                                .chain_emoji_bytes(Some((b,s)).emojfuscate_stream())
-                                .chain_emoji_bytes(None.emojfuscate_stream())
-                        },
-                        Animal::Dog(i) => {
-                            1u8
-                                .emojfuscate_stream()
-                                .chain_emoji_bytes(None.emojfuscate_stream())
-                                .chain_emoji_bytes(Some(i).emojfuscate_stream())
-                        }
-                    }
-                }
-            }
-            */
-
-            /*
-            impl<I> ConstructFromEmoji<Animal, I> for Animal
-            where
-                I: Iterator<Item = u8>,
-            {
-                fn construct_from_emoji(
-                    mut byte_stream: DecodeEmojiToBytes<I>,
-                ) -> Result<(Animal, DecodeEmojiToBytes<I>), FromEmojiError> {
-                    let constructor_discriminator =
-                        match u8::construct_from_emoji(byte_stream) {
-                            Err(err) => return Err(err),
-                            Ok((n, new_byte_stream)) => {
-                                byte_stream = new_byte_stream;
-                                n
-                            }
-                        };
-
-                    let constructor0 =
-                        match Option::<(bool, String)>::construct_from_emoji(byte_stream) {
-                            Err(err) => return Err(err),
-                            Ok((x, new_byte_stream)) => {
-                                byte_stream = new_byte_stream;
-                                x
-                            }
-                        };
-
-                    let constructor1 =
-                        match Option::<u32>::construct_from_emoji(byte_stream) {
-                            Err(err) => return Err(err),
-                            Ok((x, new_byte_stream)) => {
-                                byte_stream = new_byte_stream;
-                                x
-                            }
-                        };
-
-                    match (constructor_discriminator, constructor0, constructor1) {
-                        (0, Some((likes_cuddles, name)), None) => Ok((Animal::Cat{likes_cuddles, name}, byte_stream)),
-                        (1, None, Some(i)) => Ok((Animal::Dog(i), byte_stream)),
-                        (2, None, None) => Ok((Animal::Lizard, byte_stream)),
-                        _ => Err(FromEmojiError::UnexpectedInput("Constructor choice and data don't agree when demojfuscating Animal".to_string()))
-                    }
-                }
-            }
-            */
-
             let original_message = match input {
                 Ok(Some((likes_cuddles, name))) => Animal::Cat{likes_cuddles, name},
                 Ok(None) => Animal::Lizard,
@@ -463,5 +2517,121 @@ mod tests {
             let roundtrip_message = (&emojified).demojfuscate();
             assert_eq!(roundtrip_message, Ok(original_message), "emojfuscated version: {}", emojified);
         }
+
+        #[test]
+        fn emojfuscate_size_hint(original_message in "\\PC*", number : u64, parity_symbols in 1u16..4) {
+            let error_correction = ErrorCorrection::new(8, parity_symbols).unwrap();
+
+            assert_size_hints(original_message.as_bytes().iter().copied().emojfuscate_byte_stream_no_start_or_stop());
+            assert_size_hints((&original_message).emojfuscate_stream());
+            assert_size_hints((&original_message, number).emojfuscate_stream().with_header().with_checksum());
+            assert_size_hints((&original_message).emojfuscate_stream().with_error_correction(error_correction));
+            assert_size_hints(number.emojfuscate_stream().with_header().with_error_correction(error_correction));
+            assert_size_hints((&original_message).emojfuscate_stream().with_compression());
+            assert_size_hints(vec![number; 3].into_iter().emojfuscate_stream_with_sync_markers(2));
+
+            let exact = number.emojfuscate_stream().with_header().with_checksum();
+            assert_eq!(exact.size_hint(), (17, Some(17)));
+            assert_eq!(exact.count(), 17);
+        }
+
+        #[test]
+        fn emojfuscate_exact_len(original_message : Vec<u8>, number : u64, alphabet : bool, header : bool, checksum : bool) {
+            let bytes = number.to_be_bytes().into_iter().emojfuscate_byte_stream_no_start_or_stop();
+            assert_eq!(bytes.len(), number.emoji_len());
+
+            // every combination of the builders that keep the number of emoji predictable
+            let mut stream = original_message.iter().copied().emojfuscate_byte_stream_no_start_or_stop();
+            if alphabet {
+                stream = stream.with_alphabet(Alphabet::default_alphabet());
+            }
+            if header {
+                stream = stream.with_header();
+            }
+            if checksum {
+                stream = stream.with_checksum();
+            }
+
+            let mut len = stream.len();
+            assert_eq!(len, stream.size_hint().0);
+
+            while stream.next().is_some() {
+                assert_eq!(stream.len(), len - 1);
+                len -= 1;
+            }
+            assert_eq!(len, 0);
+        }
+
+        #[test]
+        #[cfg(feature = "serde")]
+        fn serde_matches_emojfuscate(original_message : (String, Vec<u16>, Option<i64>, char)) {
+            let emojified = to_emoji(&original_message).unwrap();
+            assert_eq!(&emojified, &original_message.clone().emojfuscate());
+            assert_eq!(from_emoji(emojified.bytes()), Ok(original_message));
+        }
+
+        #[test]
+        #[cfg(feature = "serde")]
+        fn schema_decode_matches_serde(original_message : (u16, String, Option<i32>, Vec<bool>, char, [i8; 2])) {
+            let emojified = original_message.clone().emojfuscate();
+            let schema = <(u16, String, Option<i32>, Vec<bool>, char, [i8; 2])>::emoji_schema();
+            assert_eq!(schema.decode(emojified.bytes()).ok(), to_emoji_value(&original_message).ok());
+        }
+
+        #[test]
+        #[cfg(feature = "serde")]
+        fn emojfuscate_derive_enum_matches_serde(input : Result<Option<(bool, String)>, Result<u32, Vec<i16>>>) {
+            #[derive(Emojfuscate, ConstructFromEmoji, Serialize, Deserialize, Debug, PartialEq, Clone)]
+            enum Animal {
+                Cat { name: String, likes_cuddles: bool },
+                Lizard,
+                Dog(u32),
+                Donkey(Vec<i16>, ()),
+            }
+
+            let original_message = match input {
+                Ok(Some((likes_cuddles, name))) => Animal::Cat { likes_cuddles, name },
+                Ok(None) => Animal::Lizard,
+                Err(Ok(i)) => Animal::Dog(i),
+                Err(Err(v)) => Animal::Donkey(v, ()),
+            };
+
+            let emojified = original_message.clone().emojfuscate();
+            assert_eq!(&emojified, &to_emoji(&original_message).unwrap());
+            assert_eq!(from_emoji(emojified.bytes()), Ok(original_message));
+        }
+
+        #[test]
+        #[cfg(feature = "serde")]
+        fn serde_tagged_roundtrip(original_message : (String, Vec<i32>, Option<bool>, Option<u64>, char)) {
+            let emojified = to_tagged_emoji(&original_message).unwrap();
+            assert_eq!(from_tagged_emoji(emojified.bytes()), Ok(original_message));
+        }
+
+        #[test]
+        fn emojfuscate_array_matches_emojfuscate(original_message : (u32, [i16; 3], char, bool)) {
+            let emojified: ArrayString<{ <(u32, [i16; 3], char, bool)>::MAX_UTF8_LEN }> =
+                original_message.emojfuscate_array();
+
+            assert_eq!(emojified.as_str(), original_message.emojfuscate());
+            assert_eq!(emojified.chars().count(), <(u32, [i16; 3], char, bool)>::EMOJI_LEN);
+        }
+
+        #[test]
+        fn encode_slice_into_matches_iterator(original_message : Vec<u8>, prefix in "\\PC{0,3}") {
+            let expected: String = original_message
+                .iter()
+                .copied()
+                .emojfuscate_byte_stream_no_start_or_stop()
+                .collect();
+
+            let mut emojified = prefix.clone();
+            encode_slice_into(&original_message, &mut emojified);
+            assert_eq!(&emojified[prefix.len()..], expected);
+
+            let mut roundtrip_message = Vec::new();
+            assert_eq!(decode_str_into(&expected, &mut roundtrip_message), Ok(()));
+            assert_eq!(roundtrip_message, original_message);
+        }
     }
 }